@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for der in rustls_native_certs::fuzzing::pem_bundle(data) {
+        assert!(rustls_native_certs::fuzzing::certificate(&der));
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustls_native_certs::Purpose;
+
+fuzz_target!(|data: &[u8]| {
+    for purpose in &[Purpose::ServerAuth, Purpose::EmailProtection, Purpose::CodeSigning, Purpose::TimeStamping] {
+        rustls_native_certs::fuzzing::trusted_certificates(data, *purpose);
+    }
+});
@@ -0,0 +1,67 @@
+/*
+ * The C API of rustls-native-certs, built with the "ffi" feature.  See the
+ * crate's `ffi` module for how to build a library to link against.
+ */
+
+#ifndef RUSTLS_NATIVE_CERTS_H
+#define RUSTLS_NATIVE_CERTS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* What a call returned: RNC_OK, or why it failed. */
+#define RNC_OK 0
+#define RNC_ERROR_PLATFORM 1
+#define RNC_ERROR_STORE_NOT_FOUND 2
+#define RNC_ERROR_NO_CERTS_FOUND 3
+#define RNC_ERROR_PARSE 4
+#define RNC_ERROR_PERMISSION_DENIED 5
+#define RNC_ERROR_UNSUPPORTED 6
+#define RNC_ERROR_OTHER 7
+#define RNC_ERROR_INVALID_ARGUMENT 8
+#define RNC_ERROR_PANIC 9
+
+/* What roots are loaded for. */
+#define RNC_PURPOSE_SERVER_AUTH 0
+#define RNC_PURPOSE_EMAIL_PROTECTION 1
+#define RNC_PURPOSE_CODE_SIGNING 2
+#define RNC_PURPOSE_TIME_STAMPING 3
+
+/* The DER encodings of the roots a load found. */
+typedef struct rnc_certs rnc_certs;
+
+/*
+ * Loads the roots trusted for `purpose` from the platform's native store
+ * into `*out`, which must be freed with rnc_certs_free.  On failure, `*out`
+ * is set to null; a store with no roots fails with RNC_ERROR_NO_CERTS_FOUND.
+ */
+int rnc_load_certs(uint32_t purpose, rnc_certs **out);
+
+/* Returns how many roots `certs` holds, or 0 if it is null. */
+size_t rnc_certs_len(const rnc_certs *certs);
+
+/*
+ * Stores the DER encoding of the root at `index` in `*der` and `*der_len`.
+ * The bytes belong to `certs`, and are valid until it is freed.
+ */
+int rnc_certs_get(const rnc_certs *certs, size_t index, const uint8_t **der, size_t *der_len);
+
+/* Frees roots returned by rnc_load_certs.  Does nothing if `certs` is null. */
+void rnc_certs_free(rnc_certs *certs);
+
+/*
+ * Returns the message of the last error a function here returned on this
+ * thread, or "" if there was none.  It is valid until the next such error
+ * on the thread.
+ */
+const char *rnc_last_error_message(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUSTLS_NATIVE_CERTS_H */
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::der;
+use crate::sha256::Sha256;
+
+/// The most issuers fetched to complete one chain.
+const MAX_FETCHES: usize = 4;
+
+/// The largest response accepted from an issuer URL.  Certificates, and
+/// the PKCS#7 bundles some CAs serve, are a few kilobytes.
+const MAX_RESPONSE: u64 = 64 * 1024;
+
+/// Fetches intermediate certificates that a server failed to send, from
+/// the URLs in the "CA Issuers" entries of certificates' authority
+/// information access extension.
+///
+/// This is what the platform verifiers on Windows and macOS do, and why a
+/// server with an incomplete chain can work in a browser but fail with
+/// rustls.  Fetched certificates are only ever offered as candidate
+/// intermediates: the chain must still lead to a trusted root, so nothing
+/// is trusted because it was fetched.
+///
+/// Only `http` URLs are fetched, which is all CAs publish.  Responses are
+/// cached for the life of the fetcher and, if [cache_dir](#method.cache_dir)
+/// is set, on disk.
+///
+/// *This type is available only if the crate is built with the "aia" feature.*
+pub struct IntermediateFetcher {
+    timeout: Duration,
+    cache_dir: Option<PathBuf>,
+    cache: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl IntermediateFetcher {
+    /// Returns a fetcher with a ten second timeout and no disk cache.
+    pub fn new() -> Self {
+        IntermediateFetcher {
+            timeout: Duration::from_secs(10),
+            cache_dir: None,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets how long connecting to an issuer URL, and each read from it,
+    /// may take.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets a directory in which fetched responses are kept, so they can
+    /// be reused by later processes.  It is created if it does not exist.
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Returns `chain`, which starts with the end-entity certificate,
+    /// followed by any intermediates that could be fetched to complete it.
+    ///
+    /// Fetching stops at a self-issued certificate, when a certificate has
+    /// no usable issuer URL, or after a few fetches.  Failures to fetch
+    /// are not reported: the chain is returned as far as it got, and the
+    /// verifier decides whether that is enough.
+    pub fn complete_chain(&self, chain: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let mut chain = chain.to_vec();
+        let mut current = 0;
+        let mut fetches = 0;
+
+        for _ in 0..chain.len() + MAX_FETCHES {
+            let (issuer, urls) = match chain.get(current).and_then(|cert| der::cert_fields(cert)) {
+                Some(fields) if !fields.is_self_issued() => (fields.issuer.to_vec(), fields.ca_issuers()
+                    .into_iter()
+                    .filter_map(|url| std::str::from_utf8(url).ok())
+                    .map(str::to_owned)
+                    .collect::<Vec<_>>()),
+                _ => break,
+            };
+
+            let subject_is_issuer = |cert: &Vec<u8>| der::cert_fields(cert)
+                .is_some_and(|fields| fields.subject == issuer.as_slice());
+
+            if let Some(next) = chain.iter().position(subject_is_issuer) {
+                current = next;
+                continue;
+            }
+
+            if fetches == MAX_FETCHES {
+                break;
+            }
+            fetches += 1;
+
+            let found = urls.iter()
+                .filter_map(|url| self.fetch(url).ok())
+                .flatten()
+                .find(subject_is_issuer);
+            match found {
+                // A root is useful only if it is already trusted, in which
+                // case the verifier has it.
+                Some(cert) if !der::cert_fields(&cert).is_some_and(|fields| fields.is_self_issued()) => {
+                    chain.push(cert);
+                    current = chain.len() - 1;
+                }
+                _ => break,
+            }
+        }
+
+        chain
+    }
+
+    /// Returns the certificates served at `url`.
+    fn fetch(&self, url: &str) -> Result<Vec<Vec<u8>>, Error> {
+        let cached = self.cache.lock().unwrap().get(url).cloned();
+        let body = match cached {
+            Some(body) => body,
+            None => {
+                let body = match self.read_cache_file(url) {
+                    Some(body) => body,
+                    None => {
+                        let body = http_get(url, self.timeout)?;
+                        self.write_cache_file(url, &body);
+                        body
+                    }
+                };
+                self.cache.lock().unwrap().insert(url.to_owned(), body.clone());
+                body
+            }
+        };
+
+        parse_certificates(&body)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "issuer URL did not serve certificates"))
+    }
+
+    fn cache_file(&self, url: &str) -> Option<PathBuf> {
+        let name: String = Sha256::digest(url.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.cache_dir.as_ref().map(|dir| dir.join(name))
+    }
+
+    fn read_cache_file(&self, url: &str) -> Option<Vec<u8>> {
+        let body = fs::read(self.cache_file(url)?).ok()?;
+        parse_certificates(&body).map(|_| body)
+    }
+
+    /// Saves a response to the disk cache, if there is one.  Failure is
+    /// ignored: the response is still cached in memory.
+    fn write_cache_file(&self, url: &str, body: &[u8]) {
+        if let (Some(dir), Some(path)) = (&self.cache_dir, self.cache_file(url)) {
+            if parse_certificates(body).is_some() && fs::create_dir_all(dir).is_ok() {
+                let _ = fs::write(path, body);
+            }
+        }
+    }
+}
+
+impl Default for IntermediateFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses a response from an issuer URL, which RFC 5280 says is a DER
+/// certificate or a certs-only PKCS#7 bundle.  Some CAs serve PEM instead.
+fn parse_certificates(body: &[u8]) -> Option<Vec<Vec<u8>>> {
+    if der::cert_fields(body).is_some() {
+        return Some(vec![body.to_vec()]);
+    }
+    if let Some(certs) = der::pkcs7_certificates(body) {
+        return Some(certs.into_iter().map(<[u8]>::to_vec).collect());
+    }
+    rustls_pemfile::certs(&mut &body[..])
+        .ok()
+        .filter(|certs| !certs.is_empty())
+}
+
+/// Fetches the body of an `http` URL with a plain HTTP/1.0 `GET`.
+fn http_get(url: &str, timeout: Duration) -> Result<Vec<u8>, Error> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidInput, format!("{}: {}", msg, url));
+
+    let rest = url.strip_prefix("http://").ok_or_else(|| invalid("unsupported issuer URL"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() || authority.contains('@') {
+        return Err(invalid("unsupported issuer URL"));
+    }
+    let addr = if authority.rsplit(':').next().is_some_and(|port| port.parse::<u16>().is_ok())
+        && !authority.ends_with(']') {
+        authority.to_owned()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let mut last_err = invalid("issuer URL has no addresses");
+    let mut stream = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(err) => last_err = err,
+        }
+    }
+    let mut stream = stream.ok_or(last_err)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let request = format!("GET {} HTTP/1.0\r\nHost: {}\r\nAccept: */*\r\nConnection: close\r\n\r\n", path, authority);
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = Vec::new();
+    stream.take(MAX_RESPONSE + 1).read_to_end(&mut response)?;
+    if response.len() as u64 > MAX_RESPONSE {
+        return Err(Error::new(ErrorKind::InvalidData, "issuer URL response too large"));
+    }
+
+    let end = response.windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "malformed HTTP response"))?;
+    let status = response[..end].split(|b| *b == b' ').nth(1);
+    if status != Some(b"200") {
+        return Err(Error::other(format!("issuer URL returned an error: {}", url)));
+    }
+    Ok(response.split_off(end + 4))
+}
+
+#[cfg(feature = "rustls")]
+mod verifier {
+    use rustls::{
+        Certificate,
+        RootCertStore,
+        ServerCertVerified,
+        ServerCertVerifier,
+        TLSError,
+        WebPKIVerifier,
+    };
+
+    use super::IntermediateFetcher;
+
+    /// A `rustls::ServerCertVerifier` which verifies as rustls does, but on
+    /// finding that the server sent an incomplete chain, fetches the
+    /// missing intermediates with an [IntermediateFetcher](struct.IntermediateFetcher.html)
+    /// and tries again.
+    ///
+    /// Install it with `ClientConfig::dangerous().set_certificate_verifier`.
+    ///
+    /// *This type is available only if the crate is built with the "rustls" and "aia" features.*
+    pub struct AiaVerifier {
+        fetcher: IntermediateFetcher,
+        inner: WebPKIVerifier,
+    }
+
+    impl AiaVerifier {
+        /// Returns a verifier which wraps rustls' `WebPKIVerifier`, and
+        /// fetches the intermediates a server left out of its chain with
+        /// `fetcher`, from the "CA Issuers" URLs of the certificates it sent.
+        pub fn new(fetcher: IntermediateFetcher) -> Self {
+            AiaVerifier {
+                fetcher,
+                inner: WebPKIVerifier::new(),
+            }
+        }
+    }
+
+    impl ServerCertVerifier for AiaVerifier {
+        fn verify_server_cert(
+            &self,
+            roots: &RootCertStore,
+            presented_certs: &[Certificate],
+            dns_name: webpki::DNSNameRef,
+            ocsp_response: &[u8],
+        ) -> Result<ServerCertVerified, TLSError> {
+            match self.inner.verify_server_cert(roots, presented_certs, dns_name, ocsp_response) {
+                Err(TLSError::WebPKIError(webpki::Error::UnknownIssuer)) if !presented_certs.is_empty() => {
+                    let presented: Vec<_> = presented_certs.iter().map(|cert| cert.0.clone()).collect();
+                    let completed = self.fetcher.complete_chain(&presented);
+                    if completed.len() == presented.len() {
+                        return Err(TLSError::WebPKIError(webpki::Error::UnknownIssuer));
+                    }
+
+                    let completed: Vec<_> = completed.into_iter().map(Certificate).collect();
+                    self.inner.verify_server_cert(roots, &completed, dns_name, ocsp_response)
+                }
+                result => result,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rustls")]
+pub use self::verifier::AiaVerifier;
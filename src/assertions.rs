@@ -0,0 +1,110 @@
+use crate::golden::{hex, parse_fingerprint};
+use crate::StoreSnapshot;
+
+/// Asserts that `store` has a root whose SHA-256 fingerprint is
+/// `fingerprint`, given in hex.  Colons and upper case are accepted, so
+/// fingerprints can be pasted from `openssl x509 -fingerprint -sha256`.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use rustls_native_certs::{assert_contains_fingerprint, StoreSnapshot};
+///
+/// let store = StoreSnapshot::capture()?;
+/// // ISRG Root X1
+/// assert_contains_fingerprint(&store, "96bcec06264976f37460779acf28c5a7cfe8a3c0aae11a8ffcee05c0bddf08c6");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// *This function is available only if the crate is built with the "test-util" feature.*
+#[track_caller]
+pub fn assert_contains_fingerprint(store: &StoreSnapshot, fingerprint: &str) {
+    if !store.contains(&fingerprint_arg(fingerprint)) {
+        panic!("store has no root with fingerprint {}{}", fingerprint, listing(store));
+    }
+}
+
+/// Asserts that `store` has no root whose SHA-256 fingerprint is
+/// `fingerprint`, given as for [assert_contains_fingerprint](fn.assert_contains_fingerprint.html).
+///
+/// *This function is available only if the crate is built with the "test-util" feature.*
+#[track_caller]
+pub fn assert_not_contains_fingerprint(store: &StoreSnapshot, fingerprint: &str) {
+    let fingerprint = fingerprint_arg(fingerprint);
+    if let Some((_, subject)) = store.entries().find(|(f, _)| **f == fingerprint) {
+        panic!("store has a root with fingerprint {}: {}", hex(&fingerprint), subject);
+    }
+}
+
+/// Asserts that `store` has a root whose subject contains `subject`, as
+/// the subject is written in a [StoreSnapshot](struct.StoreSnapshot.html):
+/// `CN=ISRG Root X1, O=Internet Security Research Group, C=US`, for
+/// example.
+///
+/// *This function is available only if the crate is built with the "test-util" feature.*
+#[track_caller]
+pub fn assert_contains_subject(store: &StoreSnapshot, subject: &str) {
+    if !store.entries().any(|(_, s)| s.contains(subject)) {
+        panic!("store has no root with subject {:?}{}", subject, listing(store));
+    }
+}
+
+/// Asserts that `store` has no root whose subject contains `subject`,
+/// matched as by [assert_contains_subject](fn.assert_contains_subject.html).
+/// This suits checking that a distrusted CA is gone:
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use rustls_native_certs::{assert_not_contains_subject, StoreSnapshot};
+///
+/// assert_not_contains_subject(&StoreSnapshot::capture()?, "O=DigiNotar");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// *This function is available only if the crate is built with the "test-util" feature.*
+#[track_caller]
+pub fn assert_not_contains_subject(store: &StoreSnapshot, subject: &str) {
+    let found: String = store.entries()
+        .filter(|(_, s)| s.contains(subject))
+        .map(|(fingerprint, s)| format!("\n  {} {}", hex(fingerprint), s))
+        .collect();
+    if !found.is_empty() {
+        panic!("store has roots with subject {:?}:{}", subject, found);
+    }
+}
+
+/// Asserts that `store` has exactly the roots in `expected`, comparing
+/// fingerprints.  On failure the message lists the roots `store` has in
+/// addition (`+`) and lacks (`-`), as
+/// [StoreSnapshot::check_golden](struct.StoreSnapshot.html#method.check_golden) does.
+///
+/// *This function is available only if the crate is built with the "test-util" feature.*
+#[track_caller]
+pub fn assert_store_eq(store: &StoreSnapshot, expected: &StoreSnapshot) {
+    let changes = store.changes_from(expected);
+    if !changes.is_empty() {
+        panic!("store does not have the expected roots:{}", changes);
+    }
+}
+
+#[track_caller]
+fn fingerprint_arg(fingerprint: &str) -> [u8; 32] {
+    let normalized = fingerprint.replace(':', "").to_ascii_lowercase();
+    match parse_fingerprint(&normalized) {
+        Some(fingerprint) => fingerprint,
+        None => panic!("{:?} is not a SHA-256 fingerprint", fingerprint),
+    }
+}
+
+/// Lists the roots in `store`, one per line, for a failure message.
+fn listing(store: &StoreSnapshot) -> String {
+    let roots: String = store.entries()
+        .map(|(fingerprint, subject)| format!("\n  {} {}", hex(fingerprint), subject))
+        .collect();
+    if roots.is_empty() {
+        "; it is empty".to_owned()
+    } else {
+        format!("; it has:{}", roots)
+    }
+}
@@ -0,0 +1,6 @@
+//! Prints what the platform's native certificate store holds.  See
+//! `native-certs --help`.
+
+fn main() -> std::process::ExitCode {
+    rustls_native_certs::cli::main()
+}
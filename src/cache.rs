@@ -0,0 +1,55 @@
+use std::io::Error;
+use std::sync::{Arc, RwLock};
+
+use crate::iter::Collect;
+
+/// The roots the last load cached, until they are invalidated.
+static CACHED: RwLock<Option<Arc<Vec<Vec<u8>>>>> = RwLock::new(None);
+
+/// Returns the DER encodings of the root certificates found in the
+/// platform's native certificate store, loading them on the first call and
+/// sharing those until [invalidate_native_certs_cache](fn.invalidate_native_certs_cache.html)
+/// is called.
+///
+/// This saves servers which make many TLS clients from enumerating the
+/// store, or parsing its bundle, for each of them.  Duplicates are left
+/// out, and the roots are otherwise as [build_native_certs](fn.build_native_certs.html)
+/// gives them.
+///
+/// A load which fails is not cached, so the next call loads again.  Calls
+/// made while a load is in progress wait for it rather than load too.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let roots = rustls_native_certs::native_certs_cached()?;
+/// println!("{} roots", roots.len());
+/// # Ok(())
+/// # }
+/// ```
+pub fn native_certs_cached() -> Result<Arc<Vec<Vec<u8>>>, Error> {
+    if let Some(roots) = &*CACHED.read().unwrap_or_else(|err| err.into_inner()) {
+        return Ok(roots.clone());
+    }
+
+    let mut cached = CACHED.write().unwrap_or_else(|err| err.into_inner());
+    // Another call may have loaded the roots while this one waited.
+    if let Some(roots) = &*cached {
+        return Ok(roots.clone());
+    }
+
+    let mut roots = Collect(Vec::new());
+    crate::build_native_certs(&mut roots)?;
+    let roots = Arc::new(crate::dedup(roots.0));
+    *cached = Some(roots.clone());
+    Ok(roots)
+}
+
+/// Drops the roots [native_certs_cached](fn.native_certs_cached.html) has
+/// cached, so that its next call loads them again.  Roots already returned
+/// are not changed.
+///
+/// With the "watch" feature, calling this from a [CertWatcher](struct.CertWatcher.html)'s
+/// callback keeps the cache up to date with the store.
+pub fn invalidate_native_certs_cache() {
+    *CACHED.write().unwrap_or_else(|err| err.into_inner()) = None;
+}
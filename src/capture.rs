@@ -0,0 +1,328 @@
+use std::fmt;
+use std::io::{BufRead, Error, ErrorKind};
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::{List, Purpose, RootStoreBuilder, TrustDomain};
+
+#[cfg(feature = "test-util")]
+pub(crate) const REPLAY_VAR: &str = "RUSTLS_NATIVE_CERTS_REPLAY";
+
+const HEADER: &str = "# rustls-native-certs store capture";
+
+const LISTS: [(List, &str); 3] = [
+    (List::Intermediates, "intermediates"),
+    (List::Distrusted, "distrusted"),
+    (List::Crls, "crls"),
+];
+
+/// The error kinds a capture can record; any other is replayed as
+/// `ErrorKind::Other`.
+const KINDS: [ErrorKind; 10] = [
+    ErrorKind::NotFound,
+    ErrorKind::PermissionDenied,
+    ErrorKind::InvalidData,
+    ErrorKind::InvalidInput,
+    ErrorKind::TimedOut,
+    ErrorKind::UnexpectedEof,
+    ErrorKind::Interrupted,
+    ErrorKind::WouldBlock,
+    ErrorKind::Unsupported,
+    ErrorKind::Other,
+];
+
+/// One thing the platform gave a builder while loading roots.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Item {
+    Der(Vec<u8>),
+    Pem(Vec<u8>),
+}
+
+/// What one load of the platform's store produced: what it returned, and
+/// the error it ended with, if any.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Load<T> {
+    items: Vec<T>,
+    error: Option<(ErrorKind, String)>,
+}
+
+impl<T> Load<T> {
+    fn empty() -> Self {
+        Load { items: Vec::new(), error: None }
+    }
+
+    #[cfg(feature = "test-util")]
+    fn error(&self) -> Option<Error> {
+        self.error
+            .as_ref()
+            .map(|(kind, msg)| Error::new(*kind, msg.clone()))
+    }
+}
+
+/// The raw results of enumerating the platform's certificate store, which
+/// can be saved and later replayed through the crate's normal loads.
+///
+/// This is for bug reports: a capture taken on a machine whose store
+/// misbehaves reproduces its roots (for every [Purpose](enum.Purpose.html)),
+/// intermediates, distrusted certificates and CRLs anywhere, including the
+/// certificates that fail to parse and the errors the platform returned.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// // On the affected machine:
+/// let capture = rustls_native_certs::StoreCapture::record();
+/// std::fs::write("store.capture", capture.to_string())?;
+///
+/// // In a test build elsewhere, replaying it through every load:
+/// std::env::set_var("RUSTLS_NATIVE_CERTS_REPLAY", "store.capture");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// With the "test-util" feature, while `RUSTLS_NATIVE_CERTS_REPLAY` names
+/// a capture file, loads of the native store return what was captured
+/// instead of asking the platform.  It is not read otherwise, so that the
+/// environment cannot swap the roots of a release build.
+/// An injected [Fault](enum.Fault.html), or an override fixture, takes
+/// precedence over the replay.  Client identities and change
+/// notifications are not captured.
+///
+/// The file is text: a header, the platform it was captured on, then one
+/// line per certificate or error, with binary data in base64.
+///
+/// *This type is available only if the crate is built with the "capture" feature.*
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoreCapture {
+    platform: String,
+    roots: Vec<(Purpose, Load<Item>)>,
+    lists: Vec<(List, Load<Vec<u8>>)>,
+}
+
+impl StoreCapture {
+    /// Enumerates the platform's store, recording everything it returns.
+    ///
+    /// Failed loads are recorded rather than reported, so this always
+    /// succeeds.
+    pub fn record() -> Self {
+        let roots = Purpose::ALL
+            .iter()
+            .map(|&purpose| {
+                let mut recorder = Recorder(Vec::new());
+                let error = crate::build_platform_certs(purpose, &TrustDomain::ALL, &crate::StoreAccess::default(),
+                                                        &mut recorder).err();
+                (purpose, Load {
+                    items: recorder.0,
+                    error: error.map(|err| (err.kind(), err.to_string())),
+                })
+            })
+            .collect();
+
+        let lists = LISTS
+            .iter()
+            .map(|&(list, _)| {
+                let load = match crate::load_platform_list(list) {
+                    Ok(items) => Load { items, error: None },
+                    Err(err) => Load { items: Vec::new(), error: Some((err.kind(), err.to_string())) },
+                };
+                (list, load)
+            })
+            .collect();
+
+        StoreCapture {
+            platform: std::env::consts::OS.to_owned(),
+            roots,
+            lists,
+        }
+    }
+
+    /// Reads a capture from `path`, as written from its `Display` form.
+    pub fn read_from(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses a capture from its `Display` form.
+    ///
+    /// Loads with nothing recorded in the text succeed, finding nothing.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("not a store capture".to_owned()));
+        }
+
+        let mut capture = StoreCapture {
+            platform: String::new(),
+            roots: Purpose::ALL.iter().map(|&purpose| (purpose, Load::empty())).collect(),
+            lists: LISTS.iter().map(|&(list, _)| (list, Load::empty())).collect(),
+        };
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let mut fields = line.splitn(3, ' ');
+            let (section, tag, rest) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(section), Some(tag), Some(rest)) => (section, tag, rest),
+                (Some("platform"), Some(platform), None) => {
+                    capture.platform = platform.to_owned();
+                    continue;
+                }
+                _ => return Err(invalid(format!("malformed capture line: {}", line))),
+            };
+            let decode = || STANDARD
+                .decode(rest)
+                .map_err(|_| invalid(format!("bad base64 in capture line: {}", line)));
+            let error = || {
+                let (kind, msg) = rest.split_once(' ').unwrap_or((rest, ""));
+                let kind = KINDS.iter()
+                    .find(|k| format!("{:?}", k) == kind)
+                    .copied()
+                    .unwrap_or(ErrorKind::Other);
+                (kind, msg.to_owned())
+            };
+
+            if let Some(purpose) = section.strip_prefix("roots.") {
+                let load = Purpose::from_name(purpose)
+                    .and_then(|purpose| capture.roots.iter_mut().find(|(p, _)| *p == purpose))
+                    .map(|(_, load)| load)
+                    .ok_or_else(|| invalid(format!("unknown purpose in capture: {}", purpose)))?;
+                match tag {
+                    "der" => load.items.push(Item::Der(decode()?)),
+                    "pem" => load.items.push(Item::Pem(decode()?)),
+                    "error" => load.error = Some(error()),
+                    _ => return Err(invalid(format!("malformed capture line: {}", line))),
+                }
+            } else {
+                let load = LISTS.iter()
+                    .position(|&(_, name)| name == section)
+                    .map(|i| &mut capture.lists[i].1)
+                    .ok_or_else(|| invalid(format!("unknown section in capture: {}", section)))?;
+                match tag {
+                    "der" => load.items.push(decode()?),
+                    "error" => load.error = Some(error()),
+                    _ => return Err(invalid(format!("malformed capture line: {}", line))),
+                }
+            }
+        }
+
+        Ok(capture)
+    }
+
+    /// Returns the name of the operating system the capture was taken on,
+    /// as in `std::env::consts::OS`.
+    pub fn platform(&self) -> &str {
+        &self.platform
+    }
+
+    /// Replays the roots captured for `purpose`, as the platform gave them.
+    ///
+    /// As on Windows and macOS, a certificate the builder rejects does not
+    /// stop the others loading: the first such error is returned at the end,
+    /// unless the platform's own error was captured, which is returned
+    /// instead.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn build_certs<B: RootStoreBuilder + ?Sized>(&self, purpose: Purpose, builder: &mut B) -> Result<(), Error> {
+        let load = match self.roots.iter().find(|(p, _)| *p == purpose) {
+            Some((_, load)) => load,
+            None => return Ok(()),
+        };
+
+        let mut first_error = None;
+        for item in &load.items {
+            let result = match item {
+                Item::Der(der) => builder.load_der(der.clone()),
+                Item::Pem(pem) => builder.load_pem_file(&mut &pem[..]),
+            };
+            if let Err(err) = result {
+                first_error.get_or_insert(err);
+            }
+        }
+
+        match load.error().or(first_error) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Replays one of the captured list loads.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn load_list(&self, list: List) -> Result<Vec<Vec<u8>>, Error> {
+        match self.lists.iter().find(|(l, _)| *l == list) {
+            Some((_, load)) => match load.error() {
+                Some(err) => Err(err),
+                None => Ok(load.items.clone()),
+            },
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+impl fmt::Display for StoreCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "platform {}", self.platform)?;
+
+        let write_error = |f: &mut fmt::Formatter<'_>, section: &str, error: &Option<(ErrorKind, String)>| {
+            match error {
+                // Messages are kept to one line, so the file stays one
+                // record per line.
+                Some((kind, msg)) => writeln!(f, "{} error {:?} {}", section, kind, msg.replace('\n', " ")),
+                None => Ok(()),
+            }
+        };
+
+        for (purpose, load) in &self.roots {
+            let section = format!("roots.{}", purpose.name());
+            for item in &load.items {
+                match item {
+                    Item::Der(der) => writeln!(f, "{} der {}", section, STANDARD.encode(der))?,
+                    Item::Pem(pem) => writeln!(f, "{} pem {}", section, STANDARD.encode(pem))?,
+                }
+            }
+            write_error(f, &section, &load.error)?;
+        }
+
+        for (list, load) in &self.lists {
+            let name = LISTS.iter().find(|(l, _)| l == list).map_or("", |(_, name)| name);
+            for der in &load.items {
+                writeln!(f, "{} der {}", name, STANDARD.encode(der))?;
+            }
+            write_error(f, name, &load.error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the path of the capture named by `RUSTLS_NATIVE_CERTS_REPLAY`,
+/// if it is set.
+#[cfg(feature = "test-util")]
+pub(crate) fn replay_path() -> Option<std::ffi::OsString> {
+    std::env::var_os(REPLAY_VAR).filter(|path| !path.is_empty())
+}
+
+/// Returns the capture named by `RUSTLS_NATIVE_CERTS_REPLAY`, if it is set.
+#[cfg(feature = "test-util")]
+pub(crate) fn replaying() -> Option<Result<StoreCapture, Error>> {
+    let path = replay_path()?;
+    Some(StoreCapture::read_from(&path).map_err(|err| {
+        Error::new(err.kind(), format!("could not replay store capture {:?}: {}", path, err))
+    }))
+}
+
+/// A builder which keeps what the platform gives it, without parsing.
+struct Recorder(Vec<Item>);
+
+impl RootStoreBuilder for Recorder {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.0.push(Item::Der(der));
+        Ok(())
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        let mut pem = Vec::new();
+        rd.read_to_end(&mut pem)?;
+        self.0.push(Item::Pem(pem));
+        Ok(())
+    }
+}
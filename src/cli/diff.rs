@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Write};
+use std::process::ExitCode;
+
+use super::{anchor_key, invalid_input, json_string, parse_purpose, Args, Entry, Kind, Loaded};
+use crate::snapshot::diff_maps;
+use crate::{der, Purpose};
+
+const USAGE: &str = "\
+usage: native-certs diff [--against mozilla|FILE] [--purpose PURPOSE] [--format table|json]
+
+Compares the roots the native store trusts with a reference set, printing
+the roots only the native store has, such as a corporate proxy's CA, and
+the reference roots it lacks.
+
+options:
+    --against REFERENCE  mozilla (the default), for the Mozilla roots bundled
+                         in webpki-roots, or a file of PEM certificates
+    --purpose PURPOSE    the roots to compare: server-auth (the default),
+                         email-protection, code-signing or time-stamping
+    --format FORMAT      table (the default) or json
+
+Roots are matched by subject and public key, so a root re-issued with a new
+validity period, or otherwise changed, matches its earlier self.
+
+The exit status is 0 if the roots match, 1 if they differ, and 2 if
+either set could not be loaded.";
+
+/// A root in one of the sets compared.
+struct Anchor {
+    subject: String,
+    /// The SHA-256 fingerprint of the certificate, where there is one: the
+    /// Mozilla roots are only subjects and public keys.
+    fingerprint: Option<String>,
+}
+
+fn certificate_anchors(entries: Vec<Entry>) -> BTreeMap<[u8; 32], Anchor> {
+    entries.into_iter()
+        .map(|entry| (entry.anchor_key(), Anchor { subject: entry.subject, fingerprint: Some(entry.fingerprint) }))
+        .collect()
+}
+
+fn mozilla_anchors() -> BTreeMap<[u8; 32], Anchor> {
+    webpki_roots::TLS_SERVER_ROOTS.0
+        .iter()
+        .map(|anchor| {
+            let subject = der::name_to_string(&der::sequence(anchor.subject))
+                .unwrap_or_else(|| "(unparsable)".to_owned());
+            (anchor_key(anchor.subject, anchor.spki), Anchor { subject, fingerprint: None })
+        })
+        .collect()
+}
+
+fn file_anchors(path: &str) -> Result<BTreeMap<[u8; 32], Anchor>, Error> {
+    let contents = std::fs::read(path)?;
+    let ders = rustls_pemfile::certs(&mut &contents[..])
+        .ok()
+        .filter(|ders| !ders.is_empty())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("no PEM certificates in {}", path)))?;
+    // Which kind makes no difference here.
+    let entries = ders.into_iter().map(|der| Entry::new(der, Kind::Root(Purpose::ServerAuth))).collect();
+    Ok(certificate_anchors(entries))
+}
+
+pub(super) fn run(args: &[String]) -> Result<ExitCode, Error> {
+    let mut against = "mozilla";
+    let mut purpose = Purpose::ServerAuth;
+    let mut json = false;
+
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--against" => against = args.value(arg)?,
+            "--purpose" => purpose = parse_purpose(args.value(arg)?)?,
+            "--format" => json = match args.value(arg)? {
+                "table" => false,
+                "json" => true,
+                other => return Err(invalid_input(format!("unknown format {:?}", other))),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(ExitCode::SUCCESS);
+            }
+            other => return Err(invalid_input(format!("unexpected argument {:?}", other))),
+        }
+    }
+
+    let (reference, reference_name) = match against {
+        "mozilla" => (mozilla_anchors(), "the Mozilla roots".to_owned()),
+        path => match file_anchors(path) {
+            Ok(anchors) => (anchors, path.to_owned()),
+            Err(err) => {
+                eprintln!("native-certs: could not load {}: {}", path, err);
+                return Ok(ExitCode::from(2));
+            }
+        },
+    };
+
+    let loaded = Loaded::load(Kind::Root(purpose));
+    if let Some(err) = &loaded.error {
+        eprintln!("native-certs: loading roots failed: {}", err);
+        if loaded.entries.is_empty() {
+            return Ok(ExitCode::from(2));
+        }
+    }
+    let native = certificate_anchors(loaded.entries);
+
+    let diff = diff_maps(&native, &reference);
+    let only_native: Vec<&Anchor> = diff.added.iter().map(|key| &native[key]).collect();
+    let only_reference: Vec<&Anchor> = diff.removed.iter().map(|key| &reference[key]).collect();
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    if json {
+        write_json(&mut out, &only_native, &only_reference)?;
+    } else {
+        writeln!(out, "{} roots only in the native store ({}):", only_native.len(), loaded.provenance)?;
+        write_anchors(&mut out, &only_native)?;
+        writeln!(out, "{} roots only in {}:", only_reference.len(), reference_name)?;
+        write_anchors(&mut out, &only_reference)?;
+    }
+    out.flush()?;
+
+    Ok(if diff.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+fn write_anchors(out: &mut dyn Write, anchors: &[&Anchor]) -> Result<(), Error> {
+    for anchor in anchors {
+        let fingerprint = anchor.fingerprint.as_ref().map_or("-", |fingerprint| &fingerprint[..16]);
+        writeln!(out, "  {:<16}  {}", fingerprint, anchor.subject)?;
+    }
+    Ok(())
+}
+
+fn write_json(out: &mut dyn Write, only_native: &[&Anchor], only_reference: &[&Anchor]) -> Result<(), Error> {
+    let list = |anchors: &[&Anchor]| anchors
+        .iter()
+        .map(|anchor| format!("\n    {{\"subject\": {}, \"sha256\": {}}}",
+                              json_string(&anchor.subject),
+                              anchor.fingerprint.as_deref().map_or("null".to_owned(), json_string)))
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(out, "{{\n  \"only_native\": [{}\n  ],\n  \"only_reference\": [{}\n  ]\n}}",
+             list(only_native), list(only_reference))
+}
@@ -0,0 +1,169 @@
+use std::io::{Error, ErrorKind, Write};
+use std::process::ExitCode;
+
+use super::{invalid_input, Args, Kind, Loaded};
+use crate::{platform, Probe, Purpose};
+
+const USAGE: &str = "\
+usage: native-certs doctor
+
+Looks into why the native store yields no roots, or not the ones expected:
+reports the environment variables which change where certificates are
+loaded from, probes each place the platform's store may be read from,
+loads every kind of certificate, and says what to do about each problem
+found.
+
+The exit status is 0 if no problems were found, and 1 otherwise.";
+
+pub(super) fn run(args: &[String]) -> Result<ExitCode, Error> {
+    let mut args = Args::new(args);
+    if let Some(arg) = args.next() {
+        if let "-h" | "--help" = arg {
+            println!("{}", USAGE);
+            return Ok(ExitCode::SUCCESS);
+        }
+        return Err(invalid_input(format!("unexpected argument {:?}", arg)));
+    }
+
+    let mut report = Report::default();
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
+    writeln!(out, "Environment:")?;
+    report.check_environment(&mut out)?;
+    writeln!(out, "\nLocations probed:")?;
+    report.check_locations(&mut out, &platform::probe_locations())?;
+    writeln!(out, "\nLoads:")?;
+    report.check_loads(&mut out)?;
+
+    for (heading, items) in [("Notes", &report.notes), ("Problems", &report.problems)] {
+        if !items.is_empty() {
+            writeln!(out, "\n{}:", heading)?;
+            for item in items {
+                writeln!(out, "  - {}", item.replace('\n', "\n    "))?;
+            }
+        }
+    }
+    if report.problems.is_empty() {
+        writeln!(out, "\nNo problems found.")?;
+    }
+    out.flush()?;
+
+    Ok(if report.problems.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+/// What the checks found, besides what they print as they go.
+#[derive(Default)]
+struct Report {
+    /// Findings worth knowing which break nothing.
+    notes: Vec<String>,
+    /// Findings which keep roots from loading, with what to do about them.
+    problems: Vec<String>,
+}
+
+impl Report {
+    fn check_environment(&mut self, out: &mut dyn Write) -> Result<(), Error> {
+        for var in crate::environment() {
+            let value = match std::env::var_os(var).filter(|value| !value.is_empty()) {
+                Some(value) => value,
+                None => {
+                    writeln!(out, "  {} is unset", var)?;
+                    continue;
+                }
+            };
+
+            writeln!(out, "  {}={}", var, value.to_string_lossy())?;
+            for path in std::env::split_paths(&value).filter(|path| !path.as_os_str().is_empty()) {
+                if !path.exists() {
+                    self.problems.push(format!("${} names {}, which does not exist: correct or unset it.",
+                                               var, path.display()));
+                }
+            }
+        }
+
+        if let Some(stand_in) = crate::describe_stand_in() {
+            self.notes.push(format!("Certificates are loaded from the {}, not the platform's store.\n\
+                                     Unset the variable naming it to load the platform's store.",
+                                    stand_in));
+        }
+        Ok(())
+    }
+
+    fn check_locations(&mut self, out: &mut dyn Write, probes: &[Probe]) -> Result<(), Error> {
+        if probes.is_empty() {
+            writeln!(out, "  none")?;
+        }
+
+        for probe in probes {
+            let found = match &probe.found {
+                Ok(count) => format!("{} certificates", count),
+                Err(err) => format!("could not be read: {}", err),
+            };
+            let used = if probe.used_for.is_empty() {
+                String::new()
+            } else {
+                let names: Vec<&str> = probe.used_for.iter().map(|purpose| purpose.name()).collect();
+                format!(", roots for {}", names.join(", "))
+            };
+            writeln!(out, "  {}: {}{}", probe.location, found, used)?;
+
+            if probe.used_for.is_empty() {
+                continue;
+            }
+            match &probe.found {
+                Ok(0) => self.problems.push(format!("{}, which roots are loaded from, has no certificates.",
+                                                    probe.location)),
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::PermissionDenied => self.problems.push(format!(
+                    "{}, which roots are loaded from, could not be read: {}.\n\
+                     Check the permissions of it and the directories above it.",
+                    probe.location, err)),
+                Err(err) => self.problems.push(format!("{}, which roots are loaded from, could not be read: {}.",
+                                                       probe.location, err)),
+            }
+        }
+        Ok(())
+    }
+
+    fn check_loads(&mut self, out: &mut dyn Write) -> Result<(), Error> {
+        let remedy = match crate::describe_stand_in() {
+            Some(stand_in) => format!("Check the {}.", stand_in),
+            None => platform::REMEDY.to_owned(),
+        };
+        let now = super::now();
+
+        let kinds = Purpose::ALL
+            .iter()
+            .map(|&purpose| Kind::Root(purpose))
+            .chain([Kind::Intermediate, Kind::Distrusted]);
+        for kind in kinds {
+            let load = Loaded::load(kind);
+            writeln!(out, "  {} {}, from {}", load.entries.len(), kind.heading(), load.provenance)?;
+            if let Some(err) = &load.error {
+                writeln!(out, "    failed: {}", err)?;
+            }
+
+            if kind != Kind::Root(Purpose::ServerAuth) {
+                // Only server-auth roots are commonly needed, so other
+                // loads failing is unremarkable.
+                if let Some(err) = &load.error {
+                    self.notes.push(format!("Loading {} failed: {}.", kind.heading(), err));
+                }
+                continue;
+            }
+
+            if let Some(err) = &load.error {
+                self.problems.push(format!("Loading roots for server-auth failed: {}.\n{}", err, remedy));
+            } else if load.entries.is_empty() {
+                self.problems.push(format!("There are no roots for server-auth, so no TLS server can be trusted.\n{}",
+                                           remedy));
+            }
+            let expired = load.entries.iter().filter(|entry| entry.is_expired(&now)).count();
+            if expired > 0 {
+                self.notes.push(format!("{} of the roots for server-auth have expired, and certify nothing.",
+                                        expired));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use std::io::{Error, Write};
+use std::process::ExitCode;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use super::{invalid_input, json_string, parse_purpose, Args, Entry, Kind, Loaded};
+use crate::{der, Purpose};
+
+const USAGE: &str = "\
+usage: native-certs dump [--format pem|json|table] [--purpose PURPOSE] [--all]
+
+Prints the roots the native store trusts, with their subjects, SHA-256
+fingerprints, expiry and where they were loaded from.
+
+options:
+    --format FORMAT    table (the default), json, or pem
+    --purpose PURPOSE  the roots to print: server-auth (the default),
+                       email-protection, code-signing or time-stamping
+    --all              also print intermediates and distrusted certificates
+
+With --all, pem output is not a bundle to trust: it has the distrusted
+certificates too.
+
+The exit status is 1 if any load of the store failed, even if it found
+some certificates, which are still printed.";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Table,
+    Json,
+    Pem,
+}
+
+pub(super) fn run(args: &[String]) -> Result<ExitCode, Error> {
+    let mut format = Format::Table;
+    let mut purpose = Purpose::ServerAuth;
+    let mut all = false;
+
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--format" => format = match args.value(arg)? {
+                "table" => Format::Table,
+                "json" => Format::Json,
+                "pem" => Format::Pem,
+                other => return Err(invalid_input(format!("unknown format {:?}", other))),
+            },
+            "--purpose" => purpose = parse_purpose(args.value(arg)?)?,
+            "--all" => all = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(ExitCode::SUCCESS);
+            }
+            other => return Err(invalid_input(format!("unexpected argument {:?}", other))),
+        }
+    }
+
+    let mut kinds = vec![Kind::Root(purpose)];
+    if all {
+        kinds.extend([Kind::Intermediate, Kind::Distrusted]);
+    }
+    let loads: Vec<Loaded> = kinds.into_iter().map(Loaded::load).collect();
+    let now = super::now();
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match format {
+        Format::Table => write_table(&mut out, &loads, &now)?,
+        Format::Json => write_json(&mut out, &loads, &now)?,
+        Format::Pem => write_pem(&mut out, &loads)?,
+    }
+    out.flush()?;
+
+    let mut status = ExitCode::SUCCESS;
+    for load in &loads {
+        if let Some(err) = &load.error {
+            eprintln!("native-certs: loading {} certificates failed: {}", load.kind.name(), err);
+            status = ExitCode::FAILURE;
+        }
+    }
+    Ok(status)
+}
+
+fn write_table(out: &mut dyn Write, loads: &[Loaded], now: &str) -> Result<(), Error> {
+    for (i, load) in loads.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "{} {}, from {}", load.entries.len(), load.kind.heading(), load.provenance)?;
+        if load.entries.is_empty() {
+            continue;
+        }
+
+        writeln!(out, "{:<16}  {:<10}  SUBJECT", "SHA-256", "EXPIRES")?;
+        for entry in &load.entries {
+            let expires = match &entry.not_after {
+                Some(_) if entry.is_expired(now) => "expired".to_owned(),
+                Some(not_after) => not_after[..10].to_owned(),
+                None => "?".to_owned(),
+            };
+            writeln!(out, "{:<16}  {:<10}  {}", &entry.fingerprint[..16], expires, entry.subject)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json(out: &mut dyn Write, loads: &[Loaded], now: &str) -> Result<(), Error> {
+    let entries: Vec<(&Loaded, &Entry)> = loads
+        .iter()
+        .flat_map(|load| load.entries.iter().map(move |entry| (load, entry)))
+        .collect();
+
+    writeln!(out, "[")?;
+    for (i, (load, entry)) in entries.iter().enumerate() {
+        let purpose = match load.kind {
+            Kind::Root(purpose) => json_string(purpose.name()),
+            _ => "null".to_owned(),
+        };
+        let not_after = entry.not_after.as_deref().map_or("null".to_owned(), json_string);
+        writeln!(out, "  {{\"kind\": {}, \"purpose\": {}, \"subject\": {}, \"sha256\": {}, \
+                       \"not_after\": {}, \"expired\": {}, \"provenance\": {}, \"der\": {}}}{}",
+                 json_string(entry.kind.name()),
+                 purpose,
+                 json_string(&entry.subject),
+                 json_string(&entry.fingerprint),
+                 not_after,
+                 entry.is_expired(now),
+                 json_string(&load.provenance),
+                 json_string(&STANDARD.encode(&entry.der)),
+                 if i + 1 < entries.len() { "," } else { "" })?;
+    }
+    writeln!(out, "]")
+}
+
+fn write_pem(out: &mut dyn Write, loads: &[Loaded]) -> Result<(), Error> {
+    for load in loads {
+        for entry in &load.entries {
+            writeln!(out, "# {}: {}", entry.kind.name(), entry.subject)?;
+            writeln!(out, "# SHA-256: {}", entry.fingerprint)?;
+            if let Some(not_after) = &entry.not_after {
+                writeln!(out, "# Expires: {}", not_after)?;
+            }
+            writeln!(out, "# From: {}", load.provenance)?;
+            out.write_all(der::certificate_pem(&entry.der).as_bytes())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,255 @@
+//! The `native-certs` command, for seeing what the native store holds.
+//!
+//! This is the implementation of the binary built with the "cli" feature,
+//! not part of the crate's API.
+
+use std::io::{Error, ErrorKind};
+use std::process::ExitCode;
+use std::time::SystemTime;
+
+use crate::iter::Collect;
+use crate::sha256::Sha256;
+use crate::{der, List, Purpose};
+
+mod diff;
+mod doctor;
+mod dump;
+mod watch;
+
+const USAGE: &str = "\
+usage: native-certs <command> [options]
+
+commands:
+    dump    print the certificates in the native store
+    diff    compare the native store's roots with Mozilla's, or a bundle
+    watch   print changes to the native store's roots as they happen
+    doctor  look into why the native store yields no roots, or the wrong ones
+
+Run `native-certs <command> --help` for a command's options.";
+
+/// Runs the command named by the process's arguments.
+pub fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => return usage_error(USAGE, "no command given"),
+    };
+
+    let result = match command {
+        "dump" => dump::run(args),
+        "diff" => diff::run(args),
+        "watch" => watch::run(args),
+        "doctor" => doctor::run(args),
+        "-h" | "--help" | "help" => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => return usage_error(USAGE, &format!("unknown command {:?}", command)),
+    };
+
+    match result {
+        Ok(code) => code,
+        // Output piped to `head`, say, stops being read.
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err) if err.kind() == ErrorKind::InvalidInput => {
+            usage_error(&format!("Run `native-certs {} --help` for its options.", command), &err.to_string())
+        }
+        Err(err) => {
+            eprintln!("native-certs: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage_error(usage: &str, msg: &str) -> ExitCode {
+    eprintln!("native-certs: {}\n{}", msg, usage);
+    ExitCode::from(2)
+}
+
+/// Splits a command's arguments into options and their values.
+struct Args<'a> {
+    args: std::slice::Iter<'a, String>,
+}
+
+impl<'a> Args<'a> {
+    fn new(args: &'a [String]) -> Self {
+        Args { args: args.iter() }
+    }
+
+    /// Returns the next argument, which is either an option or an operand.
+    fn next(&mut self) -> Option<&'a str> {
+        self.args.next().map(String::as_str)
+    }
+
+    /// Returns the value of `option`, which follows it.
+    fn value(&mut self, option: &str) -> Result<&'a str, Error> {
+        self.next().ok_or_else(|| invalid_input(format!("{} needs a value", option)))
+    }
+}
+
+fn invalid_input(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, msg)
+}
+
+fn parse_purpose(name: &str) -> Result<Purpose, Error> {
+    Purpose::from_name(name).ok_or_else(|| invalid_input(format!(
+        "unknown purpose {:?}; expected one of {}",
+        name,
+        Purpose::ALL.iter().map(|purpose| purpose.name()).collect::<Vec<_>>().join(", "))))
+}
+
+/// What a certificate was loaded as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Root(Purpose),
+    Intermediate,
+    Distrusted,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Root(_) => "root",
+            Kind::Intermediate => "intermediate",
+            Kind::Distrusted => "distrusted",
+        }
+    }
+
+    /// Describes certificates of this kind, like "roots for server-auth".
+    fn heading(self) -> String {
+        match self {
+            Kind::Root(purpose) => format!("roots for {}", purpose.name()),
+            Kind::Intermediate => "intermediates".to_owned(),
+            Kind::Distrusted => "distrusted certificates".to_owned(),
+        }
+    }
+
+    /// Describes where certificates of this kind are loaded from.
+    fn describe(self) -> String {
+        match self {
+            Kind::Root(purpose) => crate::describe_roots(purpose),
+            Kind::Intermediate => crate::describe_list(List::Intermediates),
+            Kind::Distrusted => crate::describe_list(List::Distrusted),
+        }
+    }
+}
+
+/// A certificate from the native store, with what people want to know
+/// about it.
+struct Entry {
+    der: Vec<u8>,
+    kind: Kind,
+    fingerprint: String,
+    subject: String,
+    not_after: Option<String>,
+}
+
+impl Entry {
+    fn new(der: Vec<u8>, kind: Kind) -> Self {
+        let fields = der::cert_fields(&der);
+        let subject = fields.as_ref()
+            .and_then(|fields| der::name_to_string(fields.subject))
+            .unwrap_or_else(|| "(unparsable)".to_owned());
+        let not_after = fields.as_ref().and_then(|fields| fields.not_after());
+        Entry {
+            fingerprint: hex(&Sha256::digest(&der)),
+            der,
+            kind,
+            subject,
+            not_after,
+        }
+    }
+
+    /// Returns the key by which roots are matched across versions of a
+    /// certificate: see [anchor_key](fn.anchor_key.html).  A certificate
+    /// which cannot be parsed is matched only by its fingerprint.
+    fn anchor_key(&self) -> [u8; 32] {
+        der::cert_fields(&self.der)
+            .and_then(|fields| Some(anchor_key(der::contents(fields.subject)?,
+                                               der::contents(fields.public_key)?)))
+            .unwrap_or_else(|| Sha256::digest(&self.der))
+    }
+
+    /// Returns whether the certificate's validity period has ended by `now`,
+    /// which is given as by [now](fn.now.html).
+    fn is_expired(&self, now: &str) -> bool {
+        // RFC 3339 times in UTC compare correctly as strings.
+        self.not_after.as_ref().is_some_and(|not_after| not_after.as_str() < now)
+    }
+}
+
+/// Returns the key by which roots are matched: the SHA-256 digest of the
+/// contents of their subject and public key, as webpki's trust anchors
+/// hold them.
+fn anchor_key(subject: &[u8], spki: &[u8]) -> [u8; 32] {
+    let mut digest = Sha256::new();
+    digest.update((subject.len() as u64).to_be_bytes());
+    digest.update(subject);
+    digest.update(spki);
+    digest.finalize()
+}
+
+/// The certificates of one kind, and the error loading them, if any.
+struct Loaded {
+    kind: Kind,
+    /// Where the certificates were loaded from.
+    provenance: String,
+    entries: Vec<Entry>,
+    error: Option<Error>,
+}
+
+impl Loaded {
+    fn load(kind: Kind) -> Self {
+        let (ders, error) = match kind {
+            Kind::Root(purpose) => {
+                let mut roots = Collect(Vec::new());
+                let error = crate::build_native_certs_for(purpose, &mut roots).err();
+                (roots.0, error)
+            }
+            Kind::Intermediate => split(crate::load_native_intermediates()),
+            Kind::Distrusted => split(crate::load_native_distrusted()),
+        };
+        Loaded {
+            kind,
+            provenance: kind.describe(),
+            entries: ders.into_iter().map(|der| Entry::new(der, kind)).collect(),
+            error,
+        }
+    }
+}
+
+fn split(result: Result<Vec<Vec<u8>>, Error>) -> (Vec<Vec<u8>>, Option<Error>) {
+    match result {
+        Ok(ders) => (ders, None),
+        Err(err) => (Vec::new(), Some(err)),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Returns the current time in RFC 3339 form, as certificates' expiry is
+/// given.
+fn now() -> String {
+    der::format_time(SystemTime::now())
+}
+
+/// Quotes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::io::{Error, Write};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use super::{hex, invalid_input, json_string, Args, Entry, Kind, Loaded};
+use crate::{CertWatcher, ChangeEvent, Purpose, StoreDiff};
+
+const USAGE: &str = "\
+usage: native-certs watch [--format text|json] [--poll SECONDS]
+
+Watches the native store's server-auth roots, printing each root added,
+removed or modified as the change happens, until interrupted.
+
+options:
+    --format FORMAT  text (the default), or json for one object per line
+    --poll SECONDS   reload the store this often instead of waiting for
+                     the platform to report changes
+
+A root is modified when one is removed and another with the same subject
+and public key added, as when a root is re-issued.";
+
+/// A change to one root.
+enum Change<'a> {
+    Added(&'a Entry),
+    Removed(&'a Entry),
+    Modified { previous: &'a Entry, current: &'a Entry },
+}
+
+/// The roots of one load, by fingerprint.
+type Roots = BTreeMap<String, Entry>;
+
+fn load_roots() -> Roots {
+    let loaded = Loaded::load(Kind::Root(Purpose::ServerAuth));
+    loaded.entries
+        .into_iter()
+        .map(|entry| (entry.fingerprint.clone(), entry))
+        .collect()
+}
+
+pub(super) fn run(args: &[String]) -> Result<ExitCode, Error> {
+    let mut json = false;
+    let mut poll = None;
+
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--format" => json = match args.value(arg)? {
+                "text" => false,
+                "json" => true,
+                other => return Err(invalid_input(format!("unknown format {:?}", other))),
+            },
+            "--poll" => {
+                let value = args.value(arg)?;
+                poll = Some(value.parse()
+                    .ok()
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                    .filter(|interval| !interval.is_zero())
+                    .ok_or_else(|| invalid_input(format!("--poll needs a number of seconds, not {:?}", value)))?);
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(ExitCode::SUCCESS);
+            }
+            other => return Err(invalid_input(format!("unexpected argument {:?}", other))),
+        }
+    }
+
+    let mut builder = CertWatcher::builder();
+    if let Some(interval) = poll {
+        builder = builder.poll_interval(interval)
+            .debounce(Duration::ZERO)
+            .force_polling(true);
+    }
+    let watcher = builder.build()?;
+    let events = watcher.subscribe();
+
+    let mut previous = load_roots();
+    eprintln!("native-certs: watching {} roots from {}",
+              previous.len(), Kind::Root(Purpose::ServerAuth).describe());
+
+    let stdout = std::io::stdout();
+    for event in events {
+        let mut out = stdout.lock();
+        let now = super::now();
+        match event {
+            ChangeEvent::Changed(diff) => {
+                let current = load_roots();
+                for change in changes(&diff, &previous, &current) {
+                    write_change(&mut out, &now, &change, json)?;
+                }
+                previous = current;
+            }
+            ChangeEvent::Warning(warning) if json => {
+                writeln!(out, "{{\"time\": {}, \"warning\": {}}}", json_string(&now), json_string(&warning))?;
+            }
+            ChangeEvent::Warning(warning) => writeln!(out, "{} warning: {}", now, warning)?,
+        }
+        out.flush()?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Describes `diff` root by root, finding added roots in `current` and
+/// removed ones in `previous`.  Roots the store changed again too quickly
+/// to see are left out.
+fn changes<'a>(diff: &StoreDiff, previous: &'a Roots, current: &'a Roots) -> Vec<Change<'a>> {
+    let added = diff.added.iter().filter_map(|fingerprint| current.get(&hex(fingerprint)));
+    let mut removed: BTreeMap<[u8; 32], &Entry> = diff.removed
+        .iter()
+        .filter_map(|fingerprint| previous.get(&hex(fingerprint)))
+        .map(|entry| (entry.anchor_key(), entry))
+        .collect();
+
+    let mut changes: Vec<Change> = added
+        .map(|entry| match removed.remove(&entry.anchor_key()) {
+            Some(previous) => Change::Modified { previous, current: entry },
+            None => Change::Added(entry),
+        })
+        .collect();
+    changes.extend(removed.into_values().map(Change::Removed));
+    changes
+}
+
+fn write_change(out: &mut dyn Write, now: &str, change: &Change, json: bool) -> Result<(), Error> {
+    let (name, entry, previous) = match *change {
+        Change::Added(entry) => ("added", entry, None),
+        Change::Removed(entry) => ("removed", entry, None),
+        Change::Modified { previous, current } => ("modified", current, Some(previous)),
+    };
+
+    if json {
+        let previous = previous.map_or(String::new(), |previous| {
+            format!(", \"previous_sha256\": {}", json_string(&previous.fingerprint))
+        });
+        writeln!(out, "{{\"time\": {}, \"change\": {}, \"sha256\": {}{}, \"subject\": {}}}",
+                 json_string(now),
+                 json_string(name),
+                 json_string(&entry.fingerprint),
+                 previous,
+                 json_string(&entry.subject))
+    } else {
+        let fingerprint = match previous {
+            Some(previous) => format!("{} -> {}", previous.fingerprint, entry.fingerprint),
+            None => entry.fingerprint.clone(),
+        };
+        writeln!(out, "{} {} {} {}", now, name, fingerprint, entry.subject)
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::Error;
+use std::sync::Arc;
+
+use rustls::sign::CertifiedKey;
+use rustls::{Certificate, ClientConfig, ResolvesClientCert, SignatureScheme};
+
+use crate::{load_native_certs, load_native_client_identities, ClientIdentity};
+
+/// A `rustls::ResolvesClientCert` which authenticates with the client
+/// identities the platform holds, as found by [load_native_client_identities](fn.load_native_client_identities.html).
+///
+/// For each handshake where the server asks for a client certificate, the
+/// first identity is used whose chain leads to one of the issuers the server
+/// accepts, and whose key can sign with one of the schemes it offers.  The
+/// identities considered can be narrowed with [label](#method.label) and
+/// [issued_by](#method.issued_by).
+///
+/// Keys held by the platform are opened the first time they sign, so
+/// making a resolver never prompts the user.
+///
+/// *This type is available only if the crate is built with the "rustls" feature.*
+pub struct NativeClientCertResolver {
+    identities: Vec<(ClientIdentity, CertifiedKey)>,
+}
+
+impl NativeClientCertResolver {
+    /// Loads the platform's client identities.
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self::from_identities(load_native_client_identities()?))
+    }
+
+    /// Uses `identities`, in order of preference.  Identities whose keys
+    /// rustls cannot use are left out.
+    pub fn from_identities(identities: Vec<ClientIdentity>) -> Self {
+        let identities = identities
+            .into_iter()
+            .filter_map(|identity| {
+                let key = identity.signing_key().ok()?;
+                let chain = identity.chain().iter().cloned().map(Certificate).collect();
+                Some((identity, CertifiedKey::new(chain, Arc::new(key))))
+            })
+            .collect();
+        NativeClientCertResolver { identities }
+    }
+
+    /// Keeps only identities with the given [label](struct.ClientIdentity.html#method.label):
+    /// the friendly name on Windows, for example.
+    pub fn label(mut self, label: &str) -> Self {
+        self.identities.retain(|(identity, _)| identity.label() == Some(label));
+        self
+    }
+
+    /// Keeps only identities issued by one of `issuers`, which are
+    /// DER-encoded distinguished names matched as by
+    /// [ClientIdentity::is_issued_by_any](struct.ClientIdentity.html#method.is_issued_by_any).
+    ///
+    /// This is for servers which do not say which issuers they accept.
+    pub fn issued_by(mut self, issuers: &[&[u8]]) -> Self {
+        self.identities.retain(|(identity, _)| identity.is_issued_by_any(issuers));
+        self
+    }
+
+    /// Returns the identities this resolver chooses from.
+    pub fn identities(&self) -> impl Iterator<Item = &ClientIdentity> {
+        self.identities.iter().map(|(identity, _)| identity)
+    }
+
+    /// Returns a new `rustls::ClientConfig` which trusts the platform's
+    /// native roots and authenticates with this resolver.
+    ///
+    /// As with [ReloadableRoots](struct.ReloadableRoots.html), this fails
+    /// only if loading the roots fails without producing any certificates.
+    pub fn client_config(self) -> Result<ClientConfig, Error> {
+        let mut config = ClientConfig::new();
+        config.root_store = match load_native_certs() {
+            Ok(store) | Err((Some(store), _)) => store,
+            Err((None, err)) => return Err(err),
+        };
+        config.client_auth_cert_resolver = Arc::new(self);
+        Ok(config)
+    }
+}
+
+impl ResolvesClientCert for NativeClientCertResolver {
+    fn resolve(&self, acceptable_issuers: &[&[u8]], sigschemes: &[SignatureScheme]) -> Option<CertifiedKey> {
+        self.identities
+            .iter()
+            .find(|(identity, key)| identity.is_issued_by_any(acceptable_issuers)
+                  && key.key.choose_scheme(sigschemes).is_some())
+            .map(|(_, key)| key.clone())
+    }
+
+    fn has_certs(&self) -> bool {
+        !self.identities.is_empty()
+    }
+}
+
+/// Returns a new `rustls::ClientConfig` for mutual TLS: it trusts the
+/// platform's native roots, and authenticates with the platform's client
+/// identities.
+///
+/// This is `NativeClientCertResolver::new()?.client_config()`; see
+/// [NativeClientCertResolver](struct.NativeClientCertResolver.html) to
+/// choose which identities are used.
+///
+/// *This function is available only if the crate is built with the "rustls" feature.*
+pub fn load_native_client_config() -> Result<ClientConfig, Error> {
+    NativeClientCertResolver::new()?.client_config()
+}
@@ -0,0 +1,53 @@
+//! The WASI component `wit/load-roots.wit` describes, which exports the
+//! crate's loads to WASM plugins and sidecars across a component boundary.
+//!
+//! Build it with
+//! `cargo rustc --release --target wasm32-wasip2 --no-default-features --features component --crate-type cdylib`.
+
+use crate::{Error, NativeCertLoader};
+
+wit_bindgen::generate!({
+    path: "wit",
+    world: "native-certs",
+});
+
+use exports::rustls_native_certs::roots::load_roots::{Error as LoadError, Guest, Purpose};
+
+struct Component;
+
+export!(Component);
+
+impl Guest for Component {
+    fn load_roots(purpose: Purpose) -> Result<Vec<Vec<u8>>, LoadError> {
+        let purpose = match purpose {
+            Purpose::ServerAuth => crate::Purpose::ServerAuth,
+            Purpose::EmailProtection => crate::Purpose::EmailProtection,
+            Purpose::CodeSigning => crate::Purpose::CodeSigning,
+            Purpose::TimeStamping => crate::Purpose::TimeStamping,
+        };
+        NativeCertLoader::new()
+            .purpose(purpose)
+            .load()
+            .map_err(load_error)
+    }
+
+    fn load_intermediates() -> Result<Vec<Vec<u8>>, LoadError> {
+        crate::load_native_intermediates().map_err(load_error)
+    }
+
+    fn load_distrusted() -> Result<Vec<Vec<u8>>, LoadError> {
+        crate::load_native_distrusted().map_err(load_error)
+    }
+}
+
+fn load_error(err: std::io::Error) -> LoadError {
+    match Error::from(err) {
+        Error::Platform(err) => LoadError::Platform(err.to_string()),
+        Error::StoreNotFound(err) => LoadError::StoreNotFound(err.to_string()),
+        Error::NoCertsFound(err) => LoadError::NoCertsFound(err.to_string()),
+        Error::Parse(err) => LoadError::Parse(err.to_string()),
+        Error::PermissionDenied(err) => LoadError::PermissionDenied(err.to_string()),
+        Error::Unsupported(err) => LoadError::Unsupported(err.to_string()),
+        Error::Other(err) => LoadError::Other(err.to_string()),
+    }
+}
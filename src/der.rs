@@ -0,0 +1,466 @@
+// Just enough of a DER reader to pick fields out of X.509 certificates.
+//
+// Nothing here validates certificates: malformed input is reported as
+// `None`, and callers decide what that means for them.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(any(feature = "aia", feature = "fuzzing",
+          all(feature = "rustls", not(feature = "mock-backend"),
+              any(windows, all(unix, not(target_vendor = "apple"), feature = "pkcs11")))))]
+const INTEGER: u8 = 0x02;
+const BOOLEAN: u8 = 0x01;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+/// A uniformResourceIdentifier `GeneralName`.
+#[cfg(any(feature = "aia", feature = "fuzzing"))]
+const URI: u8 = 0x86;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const CONTEXT_0: u8 = 0xa0;
+const CONTEXT_3: u8 = 0xa3;
+
+// Object identifier contents.
+#[cfg(any(feature = "aia", feature = "fuzzing"))]
+const AUTHORITY_INFO_ACCESS: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x01, 0x01];
+#[cfg(any(feature = "aia", feature = "fuzzing"))]
+const CA_ISSUERS: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x02];
+#[cfg(any(feature = "aia", feature = "fuzzing"))]
+const SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+const ANY_EXTENDED_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x25, 0x00];
+
+// AlgorithmIdentifier contents, as they begin for each supported key type.
+const RSA_ENCRYPTION: &[u8] = &[0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const EC_P256: &[u8] = &[0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+                         0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const EC_P384: &[u8] = &[0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+                         0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22];
+
+struct Element<'a> {
+    tag: u8,
+    contents: &'a [u8],
+    /// The complete encoding, including the tag and length.
+    encoded: &'a [u8],
+}
+
+/// Splits the first element off `input`, returning it and whatever follows.
+fn read_element(input: &[u8]) -> Option<(Element<'_>, &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let (bytes, rest) = rest.split_at(count);
+        let len = bytes.iter().fold(0usize, |len, b| (len << 8) | *b as usize);
+        (len, rest)
+    };
+
+    if rest.len() < len {
+        return None;
+    }
+    let (contents, rest) = rest.split_at(len);
+    let encoded = &input[..input.len() - rest.len()];
+    Some((Element { tag, contents, encoded }, rest))
+}
+
+fn expect(input: &[u8], tag: u8) -> Option<(Element<'_>, &[u8])> {
+    read_element(input).filter(|(element, _)| element.tag == tag)
+}
+
+/// The parts of a certificate this crate looks at, each in its complete
+/// DER encoding.  Names are encoded as carried in a TLS `CertificateRequest`.
+pub(crate) struct CertFields<'a> {
+    pub(crate) issuer: &'a [u8],
+    pub(crate) subject: &'a [u8],
+    /// The contents of the `validity` sequence.
+    validity: &'a [u8],
+    pub(crate) public_key: &'a [u8],
+    /// The contents of the `extensions` sequence, or nothing if there is none.
+    extensions: &'a [u8],
+}
+
+impl<'a> CertFields<'a> {
+    pub(crate) fn is_self_issued(&self) -> bool {
+        self.issuer == self.subject
+    }
+
+    /// Returns the value of the extension identified by `oid`, given as
+    /// the contents of its DER encoding.
+    #[cfg_attr(not(any(feature = "aia", feature = "fuzzing")), allow(dead_code))]
+    pub(crate) fn extension(&self, oid: &[u8]) -> Option<&'a [u8]> {
+        let mut rest = self.extensions;
+        while !rest.is_empty() {
+            let (extension, next) = expect(rest, SEQUENCE)?;
+            rest = next;
+
+            let (id, value) = expect(extension.contents, OID)?;
+            if id.contents != oid {
+                continue;
+            }
+            let value = match expect(value, BOOLEAN) {
+                Some((_, value)) => value,
+                None => value,
+            };
+            return expect(value, OCTET_STRING).map(|(value, _)| value.contents);
+        }
+        None
+    }
+
+    /// Returns the URLs from which the issuer's certificate can be fetched,
+    /// as listed in the authority information access extension.
+    #[cfg(any(feature = "aia", feature = "fuzzing"))]
+    pub(crate) fn ca_issuers(&self) -> Vec<&'a [u8]> {
+        let mut urls = Vec::new();
+        let mut rest = match self.extension(AUTHORITY_INFO_ACCESS).and_then(|aia| expect(aia, SEQUENCE)) {
+            Some((aia, _)) => aia.contents,
+            None => return urls,
+        };
+
+        while let Some((description, next)) = expect(rest, SEQUENCE) {
+            rest = next;
+            let location = expect(description.contents, OID)
+                .filter(|(method, _)| method.contents == CA_ISSUERS)
+                .and_then(|(_, location)| expect(location, URI));
+            if let Some((uri, _)) = location {
+                urls.push(uri.contents);
+            }
+        }
+        urls
+    }
+
+    /// Returns the end of the validity period, in RFC 3339 form such as
+    /// `2030-01-31T23:59:59Z`.
+    pub(crate) fn not_after(&self) -> Option<String> {
+        let (_, rest) = read_element(self.validity)?;
+        let (time, _) = read_element(rest)?;
+        let digits = std::str::from_utf8(time.contents).ok()?.strip_suffix('Z')?;
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        // RFC 5280 has UTCTime for years up to 2049, which start "19" from 50.
+        let full = match (time.tag, digits.len()) {
+            (UTC_TIME, 12) if digits < "50" => format!("20{}", digits),
+            (UTC_TIME, 12) => format!("19{}", digits),
+            (GENERALIZED_TIME, 14) => digits.to_owned(),
+            _ => return None,
+        };
+        Some(format!("{}-{}-{}T{}:{}:{}Z", &full[0..4], &full[4..6], &full[6..8],
+                     &full[8..10], &full[10..12], &full[12..14]))
+    }
+
+    /// Returns the type of the subject's key, if it is one rustls can use.
+    #[cfg_attr(not(feature = "rustls"), allow(dead_code))]
+    pub(crate) fn key_type(&self) -> Option<KeyType> {
+        let (spki, _) = expect(self.public_key, SEQUENCE)?;
+        let (algorithm, _) = expect(spki.contents, SEQUENCE)?;
+
+        match algorithm.contents {
+            alg if alg.starts_with(RSA_ENCRYPTION) => Some(KeyType::Rsa),
+            alg if alg == EC_P256 => Some(KeyType::EcdsaP256),
+            alg if alg == EC_P384 => Some(KeyType::EcdsaP384),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(feature = "rustls"), allow(dead_code))]
+pub(crate) enum KeyType {
+    Rsa,
+    EcdsaP256,
+    EcdsaP384,
+}
+
+/// Returns `time` in RFC 3339 form, as [CertFields::not_after] gives the
+/// end of a validity period.  Times before 1970 are given as 1970.
+pub(crate) fn format_time(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Howard Hinnant's `civil_from_days`, for days since 1970-01-01.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Returns the fields of the DER-encoded certificate `cert`.
+pub(crate) fn cert_fields(cert: &[u8]) -> Option<CertFields<'_>> {
+    let (cert, _) = expect(cert, SEQUENCE)?;
+    let (tbs, _) = expect(cert.contents, SEQUENCE)?;
+
+    // version, serialNumber, signature, issuer, validity, subject, subjectPublicKeyInfo, ...
+    let rest = match expect(tbs.contents, CONTEXT_0) {
+        Some((_, rest)) => rest,
+        None => tbs.contents,
+    };
+    let (_, rest) = read_element(rest)?;
+    let (_, rest) = expect(rest, SEQUENCE)?;
+    let (issuer, rest) = expect(rest, SEQUENCE)?;
+    let (validity, rest) = expect(rest, SEQUENCE)?;
+    let (subject, rest) = expect(rest, SEQUENCE)?;
+    let (public_key, mut rest) = expect(rest, SEQUENCE)?;
+
+    // issuerUniqueID, subjectUniqueID, extensions
+    let mut extensions: &[u8] = &[];
+    while let Some((element, next)) = read_element(rest) {
+        if element.tag == CONTEXT_3 {
+            extensions = expect(element.contents, SEQUENCE)?.0.contents;
+        }
+        rest = next;
+    }
+
+    Some(CertFields {
+        issuer: issuer.encoded,
+        subject: subject.encoded,
+        validity: validity.contents,
+        public_key: public_key.encoded,
+        extensions,
+    })
+}
+
+/// Splits OpenSSL's "TRUSTED CERTIFICATE" encoding, a certificate followed
+/// by its `X509_CERT_AUX`, returning the certificate and whether the
+/// auxiliary trust settings trust it for the extended key usage `oid`.
+///
+/// As in OpenSSL, a usage in the reject list wins over one in the trust
+/// list, and a certificate without trust settings is trusted for nothing.
+#[cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+pub(crate) fn openssl_trusted_certificate<'a>(der: &'a [u8], oid: &[u8]) -> Option<(&'a [u8], bool)> {
+    let (cert, rest) = expect(der, SEQUENCE)?;
+    let aux = match expect(rest, SEQUENCE) {
+        Some((aux, _)) => aux.contents,
+        None => return Some((cert.encoded, false)),
+    };
+
+    let contains = |list: &[u8]| {
+        let mut rest = list;
+        while let Some((id, next)) = expect(rest, OID) {
+            if id.contents == oid || id.contents == ANY_EXTENDED_KEY_USAGE {
+                return true;
+            }
+            rest = next;
+        }
+        false
+    };
+
+    // trust, reject, ...
+    let (trusted, rest) = match expect(aux, SEQUENCE) {
+        Some((trust, rest)) => (contains(trust.contents), rest),
+        None => (false, aux),
+    };
+    let rejected = expect(rest, CONTEXT_0).is_some_and(|(reject, _)| contains(reject.contents));
+    Some((cert.encoded, trusted && !rejected))
+}
+
+/// Returns the certificates in a degenerate ("certs-only") PKCS#7
+/// `SignedData`, as served for `.p7c` files.
+#[cfg(any(feature = "aia", feature = "fuzzing"))]
+pub(crate) fn pkcs7_certificates(der: &[u8]) -> Option<Vec<&[u8]>> {
+    let (content_info, _) = expect(der, SEQUENCE)?;
+    let (content_type, rest) = expect(content_info.contents, OID)?;
+    if content_type.contents != SIGNED_DATA {
+        return None;
+    }
+    let (content, _) = expect(rest, CONTEXT_0)?;
+    let (signed_data, _) = expect(content.contents, SEQUENCE)?;
+
+    // version, digestAlgorithms, encapContentInfo, certificates, ...
+    let (_, rest) = expect(signed_data.contents, INTEGER)?;
+    let (_, rest) = expect(rest, SET)?;
+    let (_, rest) = expect(rest, SEQUENCE)?;
+    let (certificates, _) = expect(rest, CONTEXT_0)?;
+
+    let mut certs = Vec::new();
+    let mut rest = certificates.contents;
+    while let Some((cert, next)) = expect(rest, SEQUENCE) {
+        certs.push(cert.encoded);
+        rest = next;
+    }
+    Some(certs)
+}
+
+/// Names a certificate by its subject in log messages.
+pub(crate) fn describe(cert: &[u8]) -> String {
+    cert_fields(cert)
+        .and_then(|fields| name_to_string(fields.subject))
+        .unwrap_or_else(|| "a certificate which cannot be parsed".to_owned())
+}
+
+/// Renders a DER-encoded distinguished name for people to read, like
+/// `CN=Example Root, O=Example, C=US`.
+///
+/// Attributes appear in the order the name lists them, which for most
+/// certificates is the country first; RFC 4514 reverses it.  Values which
+/// are not strings are shown in hex, after a `#`.
+pub(crate) fn name_to_string(name: &[u8]) -> Option<String> {
+    let (name, _) = expect(name, SEQUENCE)?;
+    let mut parts = Vec::new();
+
+    let mut rdns = name.contents;
+    while !rdns.is_empty() {
+        let (rdn, next) = expect(rdns, SET)?;
+        rdns = next;
+
+        let mut attributes = rdn.contents;
+        while !attributes.is_empty() {
+            let (attribute, next) = expect(attributes, SEQUENCE)?;
+            attributes = next;
+
+            let (oid, rest) = expect(attribute.contents, OID)?;
+            let (value, _) = read_element(rest)?;
+            parts.push(format!("{}={}", attribute_name(oid.contents), attribute_value(&value)));
+        }
+    }
+    Some(parts.join(", "))
+}
+
+fn attribute_name(oid: &[u8]) -> String {
+    match oid {
+        [0x55, 0x04, 0x03] => "CN".to_owned(),
+        [0x55, 0x04, 0x05] => "serialNumber".to_owned(),
+        [0x55, 0x04, 0x06] => "C".to_owned(),
+        [0x55, 0x04, 0x07] => "L".to_owned(),
+        [0x55, 0x04, 0x08] => "ST".to_owned(),
+        [0x55, 0x04, 0x0a] => "O".to_owned(),
+        [0x55, 0x04, 0x0b] => "OU".to_owned(),
+        [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01] => "emailAddress".to_owned(),
+        _ => dotted_oid(oid),
+    }
+}
+
+fn attribute_value(value: &Element<'_>) -> String {
+    const UTF8_STRING: u8 = 0x0c;
+    const PRINTABLE_STRING: u8 = 0x13;
+    const TELETEX_STRING: u8 = 0x14;
+    const IA5_STRING: u8 = 0x16;
+    const BMP_STRING: u8 = 0x1e;
+
+    let text = match value.tag {
+        UTF8_STRING | PRINTABLE_STRING | IA5_STRING => std::str::from_utf8(value.contents)
+            .ok()
+            .map(str::to_owned),
+        // Treated as Latin-1, as in practice it is.
+        TELETEX_STRING => Some(value.contents.iter().map(|b| *b as char).collect()),
+        BMP_STRING if value.contents.len().is_multiple_of(2) => {
+            let units = value.contents
+                .chunks(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+            char::decode_utf16(units).collect::<Result<String, _>>().ok()
+        }
+        _ => None,
+    };
+    text.unwrap_or_else(|| {
+        let hex: String = value.encoded.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("#{}", hex)
+    })
+}
+
+/// Renders the contents of an object identifier in dotted decimal.
+fn dotted_oid(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut arc = 0u64;
+    for b in oid {
+        arc = (arc << 7) | u64::from(b & 0x7f);
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (arc / 40).min(2);
+                arcs.push(first);
+                arcs.push(arc - first * 40);
+            } else {
+                arcs.push(arc);
+            }
+            arc = 0;
+        }
+    }
+    arcs.iter().map(u64::to_string).collect::<Vec<_>>().join(".")
+}
+
+#[cfg(any(feature = "cli",
+          all(feature = "rustls", not(feature = "mock-backend"),
+              any(windows, all(unix, not(target_vendor = "apple"), feature = "pkcs11")))))]
+fn write_element(out: &mut Vec<u8>, tag: u8, contents: &[u8]) {
+    out.push(tag);
+    if contents.len() < 0x80 {
+        out.push(contents.len() as u8);
+    } else {
+        let len = contents.len().to_be_bytes();
+        let len = &len[len.iter().take_while(|b| **b == 0).count()..];
+        out.push(0x80 | len.len() as u8);
+        out.extend_from_slice(len);
+    }
+    out.extend_from_slice(contents);
+}
+
+/// Returns the contents of `encoded`, which is exactly one element.
+#[cfg(any(feature = "cli", feature = "webpki-roots"))]
+pub(crate) fn contents(encoded: &[u8]) -> Option<&[u8]> {
+    read_element(encoded)
+        .filter(|(_, rest)| rest.is_empty())
+        .map(|(element, _)| element.contents)
+}
+
+/// Returns the DER encoding of a sequence with the given contents.
+#[cfg(feature = "cli")]
+pub(crate) fn sequence(contents: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(contents.len() + 4);
+    write_element(&mut out, SEQUENCE, contents);
+    out
+}
+
+/// Returns `der` as a PEM "CERTIFICATE" block, with lines of 64
+/// characters as OpenSSL writes them.
+pub(crate) fn certificate_pem(der: &[u8]) -> String {
+    use base64::Engine;
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(der);
+    let mut pem = String::with_capacity(encoded.len() + encoded.len() / 64 + 60);
+    pem.push_str("-----BEGIN CERTIFICATE-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        // Base64 is ASCII, so every chunk is whole characters.
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str("-----END CERTIFICATE-----\n");
+    pem
+}
+
+/// Converts an ECDSA signature from the fixed-width `r || s` form some
+/// platforms produce to the `Ecdsa-Sig-Value` DER encoding TLS uses.
+#[cfg(all(feature = "rustls", not(feature = "mock-backend"),
+          any(windows, all(unix, not(target_vendor = "apple"), feature = "pkcs11"))))]
+pub(crate) fn ecdsa_signature(raw: &[u8]) -> Vec<u8> {
+    let (r, s) = raw.split_at(raw.len() / 2);
+    let mut integers = Vec::new();
+    for half in &[r, s] {
+        let half = &half[half.iter().take_while(|b| **b == 0).count()..];
+        let mut integer = Vec::with_capacity(half.len() + 1);
+        if half.is_empty() || half[0] & 0x80 != 0 {
+            integer.push(0);
+        }
+        integer.extend_from_slice(half);
+        write_element(&mut integers, INTEGER, &integer);
+    }
+
+    let mut out = Vec::new();
+    write_element(&mut out, SEQUENCE, &integers);
+    out
+}
@@ -0,0 +1,143 @@
+use std::io::{self, ErrorKind};
+
+/// The class of failure behind a `std::io::Error` returned by this crate,
+/// for applications which handle each class differently.
+///
+/// Functions in this crate fail with `std::io::Error`, so that a
+/// [RootStoreBuilder](trait.RootStoreBuilder.html) can fail in the same
+/// way; converting the error into this type says which class it is:
+///
+/// ```no_run
+/// use rustls_native_certs::Error;
+///
+/// match rustls_native_certs::load_native_intermediates().map_err(Error::from) {
+///     Ok(certs) => println!("{} intermediates", certs.len()),
+///     Err(Error::PermissionDenied(err)) => eprintln!("run with more privilege: {}", err),
+///     Err(err) => eprintln!("could not load intermediates: {}", err),
+/// }
+/// ```
+///
+/// Each variant holds the original error, which is its `source()`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A call into the platform's certificate store API failed, such as
+    /// the keychain, Windows' certificate stores, or the operating system
+    /// while reading a file.
+    Platform(io::Error),
+    /// There is no certificate store where one was looked for.
+    StoreNotFound(io::Error),
+    /// The store was read, but held no roots, where a
+    /// [NativeCertLoader](struct.NativeCertLoader.html) was told to
+    /// [require them](struct.NativeCertLoader.html#method.require_roots).
+    /// The message says where the roots were looked for.
+    NoCertsFound(io::Error),
+    /// A certificate, or a file of them, could not be parsed.
+    Parse(io::Error),
+    /// The store, or a file in it, could not be read for lack of permission.
+    PermissionDenied(io::Error),
+    /// The platform does not support the operation, as on targets with no
+    /// store this crate can read, where every load fails so.  See
+    /// [is_supported](fn.is_supported.html).
+    Unsupported(io::Error),
+    /// Any other failure.
+    Other(io::Error),
+}
+
+impl Error {
+    /// Returns the original error.
+    pub fn io_error(&self) -> &io::Error {
+        match self {
+            Error::Platform(err) |
+                Error::StoreNotFound(err) |
+                Error::NoCertsFound(err) |
+                Error::Parse(err) |
+                Error::PermissionDenied(err) |
+                Error::Unsupported(err) |
+                Error::Other(err) => err,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::PermissionDenied => Error::PermissionDenied(err),
+            _ if err.get_ref().is_some_and(|inner| inner.is::<NoCertsFound>()) => Error::NoCertsFound(err),
+            ErrorKind::NotFound => Error::StoreNotFound(err),
+            ErrorKind::InvalidData => Error::Parse(err),
+            ErrorKind::Unsupported => Error::Unsupported(err),
+            _ if err.raw_os_error().is_some() => Error::Platform(err),
+            _ if err.get_ref().is_some_and(|inner| inner.is::<PlatformError>()) => Error::Platform(err),
+            _ => Error::Other(err),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Platform(err) |
+                Error::StoreNotFound(err) |
+                Error::NoCertsFound(err) |
+                Error::Parse(err) |
+                Error::PermissionDenied(err) |
+                Error::Unsupported(err) |
+                Error::Other(err) => err,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.io_error().fmt(f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.io_error())
+    }
+}
+
+/// Returns an error for a failed call into a platform API, which classifies
+/// as `Error::Platform`.
+#[cfg_attr(not(all(target_os = "macos", not(feature = "mock-backend"))), allow(dead_code))]
+pub(crate) fn platform_error(err: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::other(PlatformError(Box::new(err)))
+}
+
+/// Returns an error for a load which found no roots in `probed`, which
+/// classifies as `Error::NoCertsFound`.
+pub(crate) fn no_certs_found(probed: String) -> io::Error {
+    io::Error::new(ErrorKind::NotFound, NoCertsFound(probed))
+}
+
+/// A load found no roots in the place it holds.
+#[derive(Debug)]
+struct NoCertsFound(String);
+
+impl std::fmt::Display for NoCertsFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no root certificates found in {}", self.0)
+    }
+}
+
+impl std::error::Error for NoCertsFound {}
+
+/// The error a platform API failed with.  Its message is the platform's,
+/// and its source is the platform's error itself.
+#[derive(Debug)]
+struct PlatformError(Box<dyn std::error::Error + Send + Sync>);
+
+impl std::fmt::Display for PlatformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for PlatformError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.0)
+    }
+}
@@ -0,0 +1,96 @@
+use std::cell::Cell;
+use std::io::{Error, ErrorKind};
+
+use crate::RootStoreBuilder;
+
+/// A simulated platform failure, for testing how an application copes
+/// when it cannot load the trust it expects.
+///
+/// Faults are injected into the native loads with [inject_fault](fn.inject_fault.html),
+/// or into a [MockCertificateSource](struct.MockCertificateSource.html)
+/// with its `with_fault` method.
+///
+/// *This type is available only if the crate is built with the "test-util" feature.*
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Fault {
+    /// The store cannot be read, as when a sandbox withholds the keychain
+    /// or the CA bundle is not readable.  Loads fail with
+    /// `ErrorKind::PermissionDenied` before finding anything.
+    PermissionDenied,
+    /// The store holds a corrupt PEM block.  Roots load as usual, then the
+    /// builder is given the corrupt block, and if it rejects it the load
+    /// fails with `ErrorKind::InvalidData`, as for a damaged bundle.  Loads
+    /// which return lists fail outright.
+    CorruptPem,
+    /// The platform does not answer in time, as when the keychain waits on
+    /// a prompt nobody answers.  Loads fail with `ErrorKind::TimedOut`.
+    Timeout,
+}
+
+/// A PEM block whose contents are not valid base64.
+const CORRUPT_PEM: &[u8] = b"-----BEGIN CERTIFICATE-----\nnot base64!\n-----END CERTIFICATE-----\n";
+
+impl Fault {
+    /// Returns the fault injected on this thread, if any.
+    pub(crate) fn current() -> Option<Fault> {
+        FAULT.with(Cell::get)
+    }
+
+    pub(crate) fn to_error(self) -> Error {
+        match self {
+            Fault::PermissionDenied => Error::new(ErrorKind::PermissionDenied, "permission denied (injected fault)"),
+            Fault::CorruptPem => Error::new(ErrorKind::InvalidData, "Could not load PEM file (injected fault)"),
+            Fault::Timeout => Error::new(ErrorKind::TimedOut, "timed out (injected fault)"),
+        }
+    }
+
+    /// Performs a load of roots, `load`, as it would go with this fault.
+    pub(crate) fn build_certs<B: RootStoreBuilder + ?Sized>(self,
+                                                            builder: &mut B,
+                                                            load: impl FnOnce(&mut B) -> Result<(), Error>)
+                                                            -> Result<(), Error> {
+        match self {
+            Fault::CorruptPem => {
+                load(builder)?;
+                builder.load_pem_file(&mut &CORRUPT_PEM[..]).map_err(|_| self.to_error())
+            }
+            _ => Err(self.to_error()),
+        }
+    }
+}
+
+thread_local! {
+    static FAULT: Cell<Option<Fault>> = const { Cell::new(None) };
+}
+
+/// Makes every load of the native store on this thread fail with `fault`,
+/// until the returned guard is dropped.
+///
+/// This covers the roots, intermediates, distrusted certificates, CRLs and
+/// client identities, and so everything built on them, such as
+/// [NativeCertificateSource](struct.NativeCertificateSource.html).  Loads
+/// made on other threads, like those of a [CertWatcher](struct.CertWatcher.html),
+/// are unaffected, so tests running in parallel do not see each other's
+/// faults.
+///
+/// *This function is available only if the crate is built with the "test-util" feature.*
+pub fn inject_fault(fault: Fault) -> FaultGuard {
+    FaultGuard { previous: FAULT.with(|current| current.replace(Some(fault))) }
+}
+
+/// Removes an injected [Fault](enum.Fault.html) when dropped, restoring
+/// whatever was injected before.
+///
+/// *This type is available only if the crate is built with the "test-util" feature.*
+#[must_use = "the fault is removed when the guard is dropped"]
+#[derive(Debug)]
+pub struct FaultGuard {
+    previous: Option<Fault>,
+}
+
+impl Drop for FaultGuard {
+    fn drop(&mut self) {
+        FAULT.with(|current| current.set(self.previous));
+    }
+}
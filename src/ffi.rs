@@ -0,0 +1,208 @@
+//! A C API over the crate's loads, for applications in other languages and
+//! their bindings.
+//!
+//! `include/rustls_native_certs.h` declares these functions for C.  Build a
+//! library to link them from with
+//! `cargo rustc --release --features ffi --crate-type cdylib`, or
+//! `--crate-type staticlib`.
+//!
+//! A load returns an `rnc_certs` holding the DER encoding of each root,
+//! which is read by index and then freed:
+//!
+//! ```c
+//! rnc_certs *certs;
+//! if (rnc_load_certs(RNC_PURPOSE_SERVER_AUTH, &certs) != RNC_OK) {
+//!     fprintf(stderr, "no roots: %s\n", rnc_last_error_message());
+//!     return;
+//! }
+//! for (size_t i = 0; i < rnc_certs_len(certs); i++) {
+//!     const uint8_t *der;
+//!     size_t der_len;
+//!     rnc_certs_get(certs, i, &der, &der_len);
+//!     add_root(der, der_len);
+//! }
+//! rnc_certs_free(certs);
+//! ```
+//!
+//! Every function which can fail returns one of the `RNC_` codes below,
+//! which are those of [Error](../enum.Error.html), and a few of the API's
+//! own.
+//!
+//! *This module is available only if the crate is built with the "ffi" feature.*
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::{Error, NativeCertLoader, Purpose};
+
+/// The call succeeded.
+pub const RNC_OK: c_int = 0;
+/// A call into the platform's certificate store API failed.
+pub const RNC_ERROR_PLATFORM: c_int = 1;
+/// There is no certificate store where one was looked for.
+pub const RNC_ERROR_STORE_NOT_FOUND: c_int = 2;
+/// The store was read, but held no roots.  Loads through this API fail so,
+/// rather than returning an empty set.
+pub const RNC_ERROR_NO_CERTS_FOUND: c_int = 3;
+/// A certificate, or a file of them, could not be parsed.
+pub const RNC_ERROR_PARSE: c_int = 4;
+/// The store could not be read for lack of permission.
+pub const RNC_ERROR_PERMISSION_DENIED: c_int = 5;
+/// The platform has no store the crate can read.
+pub const RNC_ERROR_UNSUPPORTED: c_int = 6;
+/// Any other failure.
+pub const RNC_ERROR_OTHER: c_int = 7;
+/// A pointer was null, or a purpose or index out of range.
+pub const RNC_ERROR_INVALID_ARGUMENT: c_int = 8;
+/// The crate panicked.  This is a bug in the crate.
+pub const RNC_ERROR_PANIC: c_int = 9;
+
+/// Roots for TLS server authentication, as `Purpose::ServerAuth`.
+pub const RNC_PURPOSE_SERVER_AUTH: u32 = 0;
+/// Roots for S/MIME, as `Purpose::EmailProtection`.
+pub const RNC_PURPOSE_EMAIL_PROTECTION: u32 = 1;
+/// Roots for code signing, as `Purpose::CodeSigning`.
+pub const RNC_PURPOSE_CODE_SIGNING: u32 = 2;
+/// Roots for RFC 3161 timestamps, as `Purpose::TimeStamping`.
+pub const RNC_PURPOSE_TIME_STAMPING: u32 = 3;
+
+/// The DER encodings of the roots a load found, called `rnc_certs` in C.
+pub struct RncCerts(Vec<Vec<u8>>);
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Records `message` as the last error on this thread, and returns `code`.
+fn fail(code: c_int, message: String) -> c_int {
+    // A message with a NUL in it is cut short there.
+    let message = CString::new(message).unwrap_or_else(|err| {
+        let end = err.nul_position();
+        let mut bytes = err.into_vec();
+        bytes.truncate(end);
+        CString::new(bytes).unwrap_or_default()
+    });
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    code
+}
+
+fn error_code(err: &Error) -> c_int {
+    match err {
+        Error::Platform(_) => RNC_ERROR_PLATFORM,
+        Error::StoreNotFound(_) => RNC_ERROR_STORE_NOT_FOUND,
+        Error::NoCertsFound(_) => RNC_ERROR_NO_CERTS_FOUND,
+        Error::Parse(_) => RNC_ERROR_PARSE,
+        Error::PermissionDenied(_) => RNC_ERROR_PERMISSION_DENIED,
+        Error::Unsupported(_) => RNC_ERROR_UNSUPPORTED,
+        Error::Other(_) => RNC_ERROR_OTHER,
+    }
+}
+
+fn to_purpose(purpose: u32) -> Option<Purpose> {
+    match purpose {
+        RNC_PURPOSE_SERVER_AUTH => Some(Purpose::ServerAuth),
+        RNC_PURPOSE_EMAIL_PROTECTION => Some(Purpose::EmailProtection),
+        RNC_PURPOSE_CODE_SIGNING => Some(Purpose::CodeSigning),
+        RNC_PURPOSE_TIME_STAMPING => Some(Purpose::TimeStamping),
+        _ => None,
+    }
+}
+
+/// Loads the roots trusted for `purpose`, one of the `RNC_PURPOSE_`
+/// constants, from the platform's native store, storing them in `*out`.
+///
+/// On failure, `*out` is set to null, and the code returned says why; a
+/// store with no roots fails with `RNC_ERROR_NO_CERTS_FOUND`.
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes.  The roots stored in it must be
+/// freed with [rnc_certs_free](fn.rnc_certs_free.html).
+#[no_mangle]
+pub unsafe extern "C" fn rnc_load_certs(purpose: u32, out: *mut *mut RncCerts) -> c_int {
+    if out.is_null() {
+        return fail(RNC_ERROR_INVALID_ARGUMENT, "out is null".to_owned());
+    }
+    *out = std::ptr::null_mut();
+    let purpose = match to_purpose(purpose) {
+        Some(purpose) => purpose,
+        None => return fail(RNC_ERROR_INVALID_ARGUMENT, format!("no purpose numbered {}", purpose)),
+    };
+
+    let load = || NativeCertLoader::new().purpose(purpose).require_roots(true).load();
+    match panic::catch_unwind(AssertUnwindSafe(load)) {
+        Ok(Ok(roots)) => {
+            *out = Box::into_raw(Box::new(RncCerts(roots)));
+            RNC_OK
+        }
+        Ok(Err(err)) => {
+            let err = Error::from(err);
+            fail(error_code(&err), err.to_string())
+        }
+        Err(_) => fail(RNC_ERROR_PANIC, "the load panicked".to_owned()),
+    }
+}
+
+/// Returns how many roots `certs` holds, or 0 if it is null.
+///
+/// # Safety
+///
+/// `certs` must be null or returned by [rnc_load_certs](fn.rnc_load_certs.html),
+/// and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn rnc_certs_len(certs: *const RncCerts) -> usize {
+    match certs.as_ref() {
+        Some(certs) => certs.0.len(),
+        None => 0,
+    }
+}
+
+/// Stores the DER encoding of the root at `index` in `certs` in `*der` and
+/// `*der_len`.  The bytes belong to `certs`, and are valid until it is
+/// freed.
+///
+/// # Safety
+///
+/// `certs` must be null or returned by [rnc_load_certs](fn.rnc_load_certs.html),
+/// and not yet freed, and `der` and `der_len` must be null or valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn rnc_certs_get(certs: *const RncCerts, index: usize, der: *mut *const u8,
+                                       der_len: *mut usize) -> c_int {
+    let certs = match certs.as_ref() {
+        Some(certs) if !der.is_null() && !der_len.is_null() => certs,
+        _ => return fail(RNC_ERROR_INVALID_ARGUMENT, "certs, der or der_len is null".to_owned()),
+    };
+    match certs.0.get(index) {
+        Some(cert) => {
+            *der = cert.as_ptr();
+            *der_len = cert.len();
+            RNC_OK
+        }
+        None => fail(RNC_ERROR_INVALID_ARGUMENT,
+                     format!("index {} is past the {} roots", index, certs.0.len())),
+    }
+}
+
+/// Frees roots returned by [rnc_load_certs](fn.rnc_load_certs.html).  Does
+/// nothing if `certs` is null.
+///
+/// # Safety
+///
+/// `certs` must be null or returned by `rnc_load_certs`, and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn rnc_certs_free(certs: *mut RncCerts) {
+    if !certs.is_null() {
+        drop(Box::from_raw(certs));
+    }
+}
+
+/// Returns the message of the last error a function of this API returned
+/// on this thread, or an empty string if there was none.  The string is
+/// valid until the next such error on the thread.
+#[no_mangle]
+pub extern "C" fn rnc_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
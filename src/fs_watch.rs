@@ -0,0 +1,44 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use notify::Watcher as _;
+
+/// Watches directories of the store, for the backends which keep it in
+/// files, until dropped.
+pub struct Watcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+/// Calls `on_change` whenever any of `dirs`, or a file directly in one of
+/// them, is modified.
+///
+/// Directories are watched rather than the files in them, because tools
+/// which update the store replace files rather than rewriting them in
+/// place.  Those which cannot be watched, as when they do not exist, are
+/// skipped; this fails if none can be.
+pub fn watch_dirs<'a>(dirs: impl IntoIterator<Item = &'a Path>,
+                      mut on_change: impl FnMut() + Send + 'static)
+                      -> Result<Watcher, Error> {
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if !event.kind.is_access() {
+                on_change();
+            }
+        }
+    })
+        .map_err(crate::error::platform_error)?;
+
+    let mut watched_any = false;
+    for dir in dirs {
+        if watcher.watch(dir, notify::RecursiveMode::NonRecursive).is_ok() {
+            watched_any = true;
+        }
+    }
+
+    if !watched_any {
+        return Err(Error::new(ErrorKind::NotFound,
+                              "no certificate locations found to watch"));
+    }
+
+    Ok(Watcher { _watcher: watcher })
+}
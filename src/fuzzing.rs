@@ -0,0 +1,57 @@
+//! Entry points into the crate's parsers, for fuzzing.
+//!
+//! Each function takes arbitrary bytes, runs them through the same code the
+//! crate uses on the platform's store, and returns what it found.  None of
+//! them panic on malformed input; a fuzz target needs only to call them.
+//! The functions will keep their signatures across releases, so fuzz
+//! targets outside this repository keep building.
+//!
+//! *This module is available only if the crate is built with the "fuzzing" feature.*
+
+use crate::der;
+
+/// Reads a PEM bundle, as the unix backend reads the system CA bundle,
+/// returning the certificates which parse.
+pub fn pem_bundle(data: &[u8]) -> Vec<Vec<u8>> {
+    rustls_pemfile::certs(&mut &data[..])
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|der| certificate(der))
+        .collect()
+}
+
+/// Picks apart a DER-encoded certificate as the crate does, returning
+/// whether it could.
+pub fn certificate(data: &[u8]) -> bool {
+    let fields = match der::cert_fields(data) {
+        Some(fields) => fields,
+        None => return false,
+    };
+    let _ = fields.is_self_issued();
+    let _ = fields.key_type();
+    let _ = fields.ca_issuers();
+    let _ = der::name_to_string(fields.subject);
+    let _ = der::name_to_string(fields.issuer);
+    true
+}
+
+/// Reads a bundle in OpenSSL's "TRUSTED CERTIFICATE" format, as p11-kit
+/// extracts to `ca-bundle.trust.crt`, returning the certificates trusted
+/// for `purpose`.
+///
+/// *This function is available only on unix-like platforms other than macOS.*
+#[cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+pub fn trusted_certificates(data: &[u8], purpose: crate::Purpose) -> Vec<Vec<u8>> {
+    crate::unix::trusted_certificates(&String::from_utf8_lossy(data))
+        .iter()
+        .filter_map(|der| der::openssl_trusted_certificate(der, purpose.oid_der()))
+        .filter(|(_, trusted)| *trusted)
+        .map(|(cert, _)| cert.to_vec())
+        .collect()
+}
+
+/// Reads a certs-only PKCS#7 bundle, as served by some CAs' issuer URLs,
+/// returning the certificates in it, or `None` if it is not one.
+pub fn pkcs7_certificates(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+    der::pkcs7_certificates(data).map(|certs| certs.into_iter().map(<[u8]>::to_vec).collect())
+}
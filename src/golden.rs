@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{BufRead, Error, ErrorKind};
+use std::path::Path;
+
+use crate::sha256::Sha256;
+use crate::{der, CertificateSource, NativeCertificateSource, Purpose, RootStoreBuilder};
+
+/// Set to anything but the empty string to have
+/// [StoreSnapshot::check_golden](struct.StoreSnapshot.html#method.check_golden)
+/// write golden files rather than compare against them.
+const UPDATE_GOLDEN_VAR: &str = "RUSTLS_NATIVE_CERTS_UPDATE_GOLDEN";
+
+const HEADER: &str = "# rustls-native-certs store snapshot: SHA-256 fingerprint, subject";
+
+/// A canonical record of the roots in a store, for regression tests which
+/// assert that a machine trusts what it should.
+///
+/// Each root is recorded by the SHA-256 fingerprint of its DER encoding,
+/// with its subject for people to read.  The text form, from `Display` or
+/// [to_text](#method.to_text), lists one root per line in order of
+/// fingerprint, so the same store always gives the same text and golden
+/// files diff cleanly.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use rustls_native_certs::StoreSnapshot;
+///
+/// StoreSnapshot::capture()?.check_golden("tests/golden/roots.txt")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// *This type is available only if the crate is built with the "test-util" feature.*
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StoreSnapshot {
+    entries: BTreeMap<[u8; 32], String>,
+}
+
+impl StoreSnapshot {
+    /// Captures the roots the platform's native store trusts for TLS
+    /// servers.
+    pub fn capture() -> Result<Self, Error> {
+        Self::capture_from(&NativeCertificateSource, Purpose::ServerAuth)
+    }
+
+    /// Captures the roots `source` trusts for `purpose`.
+    pub fn capture_from(source: &dyn CertificateSource, purpose: Purpose) -> Result<Self, Error> {
+        let mut snapshot = StoreSnapshot::default();
+        source.build_certs(purpose, &mut snapshot)?;
+        Ok(snapshot)
+    }
+
+    /// Parses the text form of a snapshot.  Blank lines, and lines
+    /// starting with `#`, are ignored.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut entries = BTreeMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || Error::new(ErrorKind::InvalidData,
+                                        format!("line {} of snapshot is not a fingerprint and subject", number + 1));
+            let (hex, subject) = line.split_once(' ').unwrap_or((line, ""));
+            let fingerprint = parse_fingerprint(hex).ok_or_else(invalid)?;
+            entries.insert(fingerprint, subject.to_owned());
+        }
+        Ok(StoreSnapshot { entries })
+    }
+
+    /// Returns the text form of this snapshot.
+    pub fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    /// Returns the fingerprints and subjects of the roots, in order of
+    /// fingerprint.
+    pub fn entries(&self) -> impl Iterator<Item = (&[u8; 32], &str)> {
+        self.entries.iter().map(|(fingerprint, subject)| (fingerprint, subject.as_str()))
+    }
+
+    /// Compares this snapshot with the golden file at `path`, failing with
+    /// `ErrorKind::InvalidData`, and a message listing the roots which were
+    /// added (`+`) and removed (`-`), if their fingerprints differ.
+    /// Subjects are not compared.
+    ///
+    /// If the `RUSTLS_NATIVE_CERTS_UPDATE_GOLDEN` environment variable is
+    /// set, the golden file is written with this snapshot instead.
+    pub fn check_golden(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        if std::env::var_os(UPDATE_GOLDEN_VAR).is_some_and(|update| !update.is_empty()) {
+            return std::fs::write(path, self.to_text());
+        }
+
+        let golden = match std::fs::read_to_string(path) {
+            Ok(golden) => StoreSnapshot::parse(&golden)?,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Err(Error::new(ErrorKind::NotFound,
+                                      format!("no golden file {:?}; set {} to write it",
+                                              path, UPDATE_GOLDEN_VAR)));
+            }
+            Err(err) => return Err(err),
+        };
+
+        let changes = self.changes_from(&golden);
+        if changes.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::new(ErrorKind::InvalidData,
+                           format!("store does not match golden file {:?}:{}", path, changes)))
+        }
+    }
+}
+
+impl StoreSnapshot {
+    /// Returns whether there is a root whose fingerprint is `fingerprint`.
+    pub(crate) fn contains(&self, fingerprint: &[u8; 32]) -> bool {
+        self.entries.contains_key(fingerprint)
+    }
+
+    /// Returns a line for each root added (`+`) to `before` or removed
+    /// (`-`) from it to get this snapshot, each starting with a newline.
+    pub(crate) fn changes_from(&self, before: &StoreSnapshot) -> String {
+        let mut changes = String::new();
+        for (fingerprint, subject) in self.entries() {
+            if !before.contains(fingerprint) {
+                changes.push_str(&format!("\n+ {} {}", hex(fingerprint), subject));
+            }
+        }
+        for (fingerprint, subject) in before.entries() {
+            if !self.contains(fingerprint) {
+                changes.push_str(&format!("\n- {} {}", hex(fingerprint), subject));
+            }
+        }
+        changes
+    }
+}
+
+impl fmt::Display for StoreSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for (fingerprint, subject) in self.entries() {
+            writeln!(f, "{} {}", hex(fingerprint), subject)?;
+        }
+        Ok(())
+    }
+}
+
+impl RootStoreBuilder for StoreSnapshot {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        let subject = der::cert_fields(&der)
+            .and_then(|fields| der::name_to_string(fields.subject))
+            .unwrap_or_else(|| "(unparsable)".to_owned());
+        self.entries.insert(Sha256::digest(&der), subject);
+        Ok(())
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        for der in rustls_pemfile::certs(rd)? {
+            self.load_der(der)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn parse_fingerprint(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut fingerprint = [0u8; 32];
+    for (i, byte) in fingerprint.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(fingerprint)
+}
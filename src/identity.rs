@@ -0,0 +1,243 @@
+use std::fmt;
+use std::io::Error;
+use std::path::PathBuf;
+
+#[cfg(feature = "rustls")]
+use std::io::ErrorKind;
+
+#[cfg(feature = "rustls")]
+use rustls::sign::SigningKey;
+
+use crate::der;
+use crate::platform;
+
+/// A client certificate, and the chain needed to present it, for which the
+/// platform also holds a private key.
+///
+/// The metadata is the same on every platform: where the identity came
+/// from, and a human-readable label if the platform records one.
+#[derive(Clone)]
+pub struct ClientIdentity {
+    chain: Vec<Vec<u8>>,
+    label: Option<String>,
+    source: IdentitySource,
+    private_key: Option<Vec<u8>>,
+    native_key: Option<platform::NativeKey>,
+}
+
+impl ClientIdentity {
+    /// `chain` starts with the end-entity certificate.  A self-issued
+    /// root at the end is dropped, since servers already have it.
+    #[cfg_attr(any(feature = "mock-backend", all(not(unix), not(windows)), all(target_vendor = "apple", not(target_os = "macos")),
+                   all(unix, not(target_vendor = "apple"), not(feature = "pkcs12"), not(feature = "pkcs11"))),
+               allow(dead_code))]
+    pub(crate) fn new(mut chain: Vec<Vec<u8>>, label: Option<String>, source: IdentitySource) -> Self {
+        if chain.len() > 1 {
+            let ends_in_root = chain.last()
+                .and_then(|cert| der::cert_fields(cert))
+                .is_some_and(|fields| fields.is_self_issued());
+            if ends_in_root {
+                chain.pop();
+            }
+        }
+
+        ClientIdentity {
+            chain,
+            label,
+            source,
+            private_key: None,
+            native_key: None,
+        }
+    }
+
+    /// Attaches the platform's handle to the private key, which stays
+    /// with the platform.
+    #[cfg_attr(any(feature = "mock-backend", all(not(unix), not(windows)), all(target_vendor = "apple", not(target_os = "macos")),
+                   all(unix, not(target_vendor = "apple"), not(feature = "pkcs11"))),
+               allow(dead_code))]
+    pub(crate) fn with_native_key(mut self, key: platform::NativeKey) -> Self {
+        self.native_key = Some(key);
+        self
+    }
+
+    #[cfg(all(feature = "pkcs12", unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+    pub(crate) fn with_private_key(mut self, pkcs8_der: Vec<u8>) -> Self {
+        self.private_key = Some(pkcs8_der);
+        self
+    }
+
+    /// Returns the DER encoding of the end-entity certificate.
+    pub fn certificate(&self) -> &[u8] {
+        &self.chain[0]
+    }
+
+    /// Returns the DER encodings of the end-entity certificate followed by
+    /// any intermediate certificates, in the order they should be sent.
+    pub fn chain(&self) -> &[Vec<u8>] {
+        &self.chain
+    }
+
+    /// Returns a human-readable name for this identity: the friendly name
+    /// on Windows, the certificate's subject summary (as shown by Keychain
+    /// Access) on macOS, or the PKCS#12 friendly name.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns where this identity was found.
+    pub fn source(&self) -> &IdentitySource {
+        &self.source
+    }
+
+    /// Returns true if this identity's chain leads to one of
+    /// `acceptable_issuers`, or if that list is empty.
+    ///
+    /// `acceptable_issuers` are DER-encoded distinguished names, as sent by
+    /// a server in its TLS `CertificateRequest` and passed to
+    /// `rustls::ResolvesClientCert::resolve`.  A name matches if it is the
+    /// issuer of any certificate in the chain; names are compared byte for
+    /// byte, as the platforms' own selection does.
+    pub fn is_issued_by_any(&self, acceptable_issuers: &[&[u8]]) -> bool {
+        acceptable_issuers.is_empty() || self.chain
+            .iter()
+            .filter_map(|cert| der::cert_fields(cert))
+            .any(|fields| acceptable_issuers.contains(&fields.issuer))
+    }
+
+    /// Returns the PKCS#8 DER encoding of the private key, if the platform
+    /// made it available.
+    ///
+    /// This is only the case for identities loaded from PKCS#12 files; the
+    /// keys of identities in the Windows certificate store, the macOS
+    /// keychain, and on PKCS#11 tokens stay where they are.
+    pub fn private_key_der(&self) -> Option<&[u8]> {
+        self.private_key.as_deref()
+    }
+
+    /// Exports this identity, its chain and private key, as a PKCS#12
+    /// archive encrypted with `password`, for handing to another process.
+    ///
+    /// This only works where the key may leave its holder.  Windows and
+    /// macOS refuse to export keys which were imported or generated as
+    /// non-exportable, as smart card and Secure Enclave keys always are,
+    /// and keys on PKCS#11 tokens are never exported.  Identities loaded
+    /// from PKCS#12 files can always be exported.
+    pub fn to_pkcs12(&self, password: &str) -> Result<Vec<u8>, Error> {
+        #[cfg(all(feature = "pkcs12", unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+        {
+            if let Some(der) = &self.private_key {
+                return platform::pkcs12_with_key(&self.chain, der, self.label.as_deref(), password);
+            }
+        }
+
+        let key = self.native_key
+            .as_ref()
+            .ok_or_else(|| Error::new(std::io::ErrorKind::NotFound, "no private key available"))?;
+        platform::export_pkcs12(key, &self.chain, password)
+    }
+
+    /// Returns a `rustls::sign::SigningKey` for this identity's private key,
+    /// for use in client authentication.
+    ///
+    /// Keys held by the platform are never exported: on Windows, signing
+    /// is delegated to CNG, so keys protected by a TPM or a smart card can
+    /// be used; on macOS, it is delegated to `SecKeyCreateSignature`, so
+    /// non-extractable and Secure Enclave keys can be used; and keys on
+    /// PKCS#11 tokens sign on the token.
+    ///
+    /// The key is not opened here, but the first time it signs, which is
+    /// only once a server asks for a client certificate.  That is when the
+    /// platform may prompt the user for a PIN or for consent; if opening
+    /// fails, the signature fails, and the next one tries again.
+    ///
+    /// This fails if the key is of a type rustls does not support.  A
+    /// PKCS#12 key which cannot be parsed also fails here.
+    ///
+    /// *This method is available only if the crate is built with the "rustls" feature.*
+    #[cfg(feature = "rustls")]
+    pub fn signing_key(&self) -> Result<Box<dyn SigningKey>, Error> {
+        if let Some(der) = &self.private_key {
+            return rustls::sign::any_supported_type(&rustls::PrivateKey(der.clone()))
+                .map_err(|()| Error::new(ErrorKind::InvalidData, "unsupported private key type"));
+        }
+
+        let key = self.native_key
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "no private key available"))?;
+        let key_type = der::cert_fields(self.certificate())
+            .and_then(|fields| fields.key_type())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "unsupported private key type"))?;
+        platform::signing_key(key, key_type)
+    }
+}
+
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field("chain", &self.chain.len())
+            .field("label", &self.label)
+            .field("source", &self.source)
+            .field("private_key", &self.private_key.as_ref().map(|_| "<PKCS#8>"))
+            .finish()
+    }
+}
+
+/// Where a [ClientIdentity](struct.ClientIdentity.html) was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IdentitySource {
+    /// The named certificate store of the current user on Windows.
+    WindowsStore(String),
+    /// The keychain search list of the current user on macOS.
+    Keychain,
+    /// A smart card or other hardware token made available through
+    /// CryptoTokenKit on macOS.
+    SmartCard,
+    /// A PKCS#12 file named by `RUSTLS_NATIVE_CERTS_CLIENT_PKCS12`.
+    Pkcs12File(PathBuf),
+    /// The PKCS#11 token with the given label.
+    Pkcs11Token(String),
+}
+
+/// Loads the client certificates, with their chains, which the platform
+/// holds private keys for and so could be used for mutual TLS.
+///
+/// These come from:
+///
+/// * on Windows, the current user's "My" certificate store;
+/// * on macOS, identities in the current user's keychain search list, and
+///   on smart cards and other CryptoTokenKit tokens;
+/// * elsewhere, the PKCS#12 files listed in the `RUSTLS_NATIVE_CERTS_CLIENT_PKCS12`
+///   environment variable (separated like `PATH`), decrypted with the password in
+///   `RUSTLS_NATIVE_CERTS_CLIENT_PKCS12_PASSWORD`, and the PKCS#11 tokens
+///   matching the whitespace-separated URIs in `RUSTLS_NATIVE_CERTS_CLIENT_PKCS11`
+///   (see [load_pkcs11_identities](fn.load_pkcs11_identities.html)).  Reading
+///   these needs the crate to be built with the "pkcs12" and "pkcs11" features
+///   respectively.
+///
+/// Identities on smart cards and other hardware tokens are included.
+/// Listing them never prompts the user or logs in to a token: see
+/// [ClientIdentity::signing_key](struct.ClientIdentity.html#method.signing_key)
+/// for when that happens.
+///
+/// Like [build_native_certs](fn.build_native_certs.html), this fails in a
+/// platform-specific way, expressed in a `std::io::Error`.
+pub fn load_native_client_identities() -> Result<Vec<ClientIdentity>, Error> {
+    #[cfg(feature = "test-util")]
+    if let Some(fault) = crate::fault::Fault::current() {
+        return Err(fault.to_error());
+    }
+
+    crate::platform::load_client_identities()
+}
+
+/// Loads the client identities, as [load_native_client_identities](fn.load_native_client_identities.html)
+/// does, keeping only those a server asking for `acceptable_issuers` would accept.
+///
+/// See [ClientIdentity::is_issued_by_any](struct.ClientIdentity.html#method.is_issued_by_any)
+/// for how identities are matched.
+pub fn load_native_client_identities_for(acceptable_issuers: &[&[u8]]) -> Result<Vec<ClientIdentity>, Error> {
+    let mut identities = load_native_client_identities()?;
+    identities.retain(|identity| identity.is_issued_by_any(acceptable_issuers));
+    Ok(identities)
+}
@@ -0,0 +1,57 @@
+use std::io::{BufRead, Error};
+
+use crate::RootStoreBuilder;
+
+/// An iterator over the roots in the platform's native certificate store,
+/// as returned by [native_cert_iter](fn.native_cert_iter.html).
+///
+/// Each item is the DER encoding of a root, or an error reading part of
+/// the store.  Iteration goes on after an error where there is more of
+/// the store to read.
+pub struct NativeCertIter {
+    inner: Box<dyn Iterator<Item = Result<Vec<u8>, Error>> + Send>,
+}
+
+impl NativeCertIter {
+    pub(crate) fn new(inner: impl Iterator<Item = Result<Vec<u8>, Error>> + Send + 'static) -> Self {
+        NativeCertIter { inner: Box::new(inner) }
+    }
+
+    /// Returns an iterator over the roots `load` gives a builder, for
+    /// stores which can only be read all at once.  The error `load` fails
+    /// with, if any, comes last.
+    pub(crate) fn collected(load: impl FnOnce(&mut Collect) -> Result<(), Error>) -> Self {
+        let mut roots = Collect(Vec::new());
+        let error = load(&mut roots).err();
+        NativeCertIter::new(roots.0.into_iter().map(Ok).chain(error.map(Err)))
+    }
+}
+
+impl Iterator for NativeCertIter {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl std::fmt::Debug for NativeCertIter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeCertIter").finish_non_exhaustive()
+    }
+}
+
+/// A builder which keeps the DER encodings of the roots it is given.
+pub(crate) struct Collect(pub(crate) Vec<Vec<u8>>);
+
+impl RootStoreBuilder for Collect {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.0.push(der);
+        Ok(())
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        self.0.extend(rustls_pemfile::certs(rd)?);
+        Ok(())
+    }
+}
@@ -5,38 +5,496 @@
 //! * A higher level function [load_native_certs](fn.build_native_certs.html)
 //!   which returns a `rustls::RootCertStore` pre-filled from the native
 //!   certificate store. It is only available if the `rustls` feature is
-//!   enabled.
+//!   enabled, as is [load_native_root_store](fn.load_native_root_store.html),
+//!   which also counts the roots rustls rejects, rather than failing.
+//!   With the `tokio` feature, [load_native_certs_async](fn.load_native_certs_async.html)
+//!   does the same load on tokio's blocking thread pool, for async
+//!   applications.  With the `webpki-roots` feature,
+//!   [load_native_certs_or_fallback](fn.load_native_certs_or_fallback.html)
+//!   adds the Mozilla roots of the webpki-roots crate, for machines whose
+//!   store is empty or missing.
+//! * A function [load_native_certs_der](fn.load_native_certs_der.html)
+//!   which returns the roots as the `CertificateDer`s of rustls-pki-types,
+//!   which newer rustls, webpki and other TLS stacks take, without tying
+//!   callers to a version of rustls.  It is only available if the
+//!   `pki-types` feature is enabled, which also makes a
+//!   `Vec<CertificateDer>` a [RootStoreBuilder](trait.RootStoreBuilder.html).
+//!   With the `webpki` feature, [load_native_trust_anchors](fn.load_native_trust_anchors.html)
+//!   returns the roots parsed into webpki's trust anchors.
 //! * A lower level function [build_native_certs](fn.build_native_certs.html)
 //!   that lets callers pass their own certificate parsing logic. It is
-//!   available to all users.
+//!   available to all users, as is [build_native_certs_for](fn.build_native_certs_for.html),
+//!   which does the same for roots trusted for other [purposes](enum.Purpose.html),
+//!   such as S/MIME.
+//! * A function [native_cert_iter](fn.native_cert_iter.html) which returns
+//!   the roots one at a time, reading the store no further than needed
+//!   where it can.  It is available to all users, as is
+//!   [load_native_certs_partial](fn.load_native_certs_partial.html), which
+//!   returns every root that could be read along with what was skipped.
+//! * A function [native_certs_cached](fn.native_certs_cached.html) which
+//!   loads the roots once for the whole process and shares them until
+//!   [invalidate_native_certs_cache](fn.invalidate_native_certs_cache.html)
+//!   is called, for servers which make many TLS clients.  It is available
+//!   to all users.
+//! * A [NativeCertLoader](struct.NativeCertLoader.html) builder for loads
+//!   with options.  It is available to all users.  The options say:
+//!   * which [trust domains](enum.TrustDomain.html) to read on macOS;
+//!   * which certificate stores to read on Windows, and whether the current
+//!     user's, the local machine's or both;
+//!   * whether to report the roots Microsoft's AuthRoot list names which
+//!     Windows has not downloaded;
+//!   * whether to leave out expired roots;
+//!   * whether to leave out roots sharing a public key with another;
+//!   * whether to leave out roots macOS trusts only for some hosts;
+//!   * whether to leave out roots macOS trusts only where evaluation meets
+//!     an error their trust settings allow;
+//!   * whether to keep the roots found by a load which then fails;
+//!   * whether to fail a load which finds none;
+//!   * whether to read only what never prompts for keychain access, for
+//!     sandboxed apps on macOS;
+//!   * whether to skip, with a time limit, the macOS trust domains which
+//!     cannot be read, returning what was skipped with the roots found;
+//!   * whether to add the self-signed certificates installers leave in the
+//!     macOS System keychain without trust settings;
+//!   * which [CertSource](enum.CertSource.html)s of one's own to add, such
+//!     as an internal CA.
+//! * A function [load_native_certs_with_metadata](fn.load_native_certs_with_metadata.html)
+//!   which returns each root with where it was found, such as the macOS
+//!   trust domain which separates Apple's roots from those added locally,
+//!   whether a macOS configuration profile, as MDM uses, installed it, the
+//!   errors its macOS trust settings allow, on which its trust depends,
+//!   the trust the platform gives it, and its fingerprint.  It is only
+//!   available if the `metadata` feature is enabled, as is a
+//!   [CertStore](struct.CertStore.html) type which finds roots by subject,
+//!   by the certificates they issued, or by fingerprint or public key pin,
+//!   says which roots differ between two loads, and which certificates the
+//!   platform denies.  With the `x509` feature, each root displays as its
+//!   subject, issuer and expiry, for logging.
+//! * A function [load_native_intermediates](fn.load_native_intermediates.html)
+//!   which returns the intermediate certificates the platform keeps apart
+//!   from its roots, for verifiers that build chains.  It is available to
+//!   all users.
+//! * A function [load_native_distrusted](fn.load_native_distrusted.html)
+//!   which returns the certificates the platform explicitly distrusts, and
+//!   [distrusted_fingerprints](fn.distrusted_fingerprints.html), their
+//!   SHA-256 fingerprints, for auditing what loads leave out.  The first
+//!   is available to all users; the second only if the `metadata` feature
+//!   is enabled.
+//! * A function [load_native_crls](fn.load_native_crls.html) which returns
+//!   the certificate revocation lists in the native store.  It is available
+//!   to all users.
+//! * A function [export_pem_bundle](fn.export_pem_bundle.html) which
+//!   writes the roots to a PEM file, for tools which take only a CA bundle,
+//!   such as curl and git.  It is available to all users.
+//! * A function [load_native_client_identities](fn.load_native_client_identities.html)
+//!   which returns the client certificates the platform holds private keys
+//!   for, for use in mutual TLS.  It is available to all users.
+//! * A function [load_native_client_config](fn.load_native_client_config.html)
+//!   which returns a `rustls::ClientConfig` set up for mutual TLS with the
+//!   native roots and client identities, using a
+//!   [NativeClientCertResolver](struct.NativeClientCertResolver.html).  It
+//!   is only available if the `rustls` feature is enabled.
+//! * A [CertWatcher](struct.CertWatcher.html) type which reports changes to
+//!   the native certificate store, as the platform notifies them: changes
+//!   to the bundle's files on Linux, to the keychains and trust settings on
+//!   macOS, and to the stores themselves on Windows.  It is only available
+//!   if the `watch` feature is enabled; enabling the `tokio` feature additionally lets
+//!   changes be consumed as a `Stream`.
+//! * A [ReloadableRoots](struct.ReloadableRoots.html) type which keeps a
+//!   `rustls::RootCertStore` up to date with the native certificate store.
+//!   It is only available if both the `rustls` and `watch` features are
+//!   enabled.
+//! * An [IntermediateFetcher](struct.IntermediateFetcher.html) type which
+//!   fetches intermediate certificates a server failed to send, and an
+//!   [AiaVerifier](struct.AiaVerifier.html) which uses it while verifying.
+//!   Both make network requests, so they are only available if the `aia`
+//!   feature is enabled, and the verifier also needs `rustls`.
+//! * A [CertificateSource](trait.CertificateSource.html) trait over all of
+//!   the above loads, implemented for the native store by
+//!   [NativeCertificateSource](struct.NativeCertificateSource.html), and by
+//!   an in-memory [MockCertificateSource](struct.MockCertificateSource.html)
+//!   for tests.  The mock is only available if the `test-util` feature is
+//!   enabled.  A function [load_from](fn.load_from.html) runs the crate's
+//!   processing of the native store, such as leaving out distrusted
+//!   certificates, over any source.  A [CertSource](enum.CertSource.html)
+//!   names a known place instead, such as a PEM file, a directory, a
+//!   Windows store, or a macOS trust domain or keychain file, such as a
+//!   test keychain, and [load_certs_from](fn.load_certs_from.html)
+//!   loads the roots from a list of them, for deployments which know where
+//!   their certificates are.
+//! * A [StoreSnapshot](struct.StoreSnapshot.html) type which records the
+//!   roots in a store canonically and compares them against a golden file,
+//!   for regression tests of a machine's trust.  It is only available if
+//!   the `test-util` feature is enabled, as are assertions over a
+//!   snapshot, such as [assert_contains_fingerprint](fn.assert_contains_fingerprint.html)
+//!   and [assert_not_contains_subject](fn.assert_not_contains_subject.html),
+//!   which list the store's roots when they fail.
+//! * An [inject_fault](fn.inject_fault.html) function which makes loads of
+//!   the native store fail in the ways platforms do, for testing how an
+//!   application copes.  It is only available if the `test-util` feature
+//!   is enabled.
+//! * A [fuzzing](fuzzing/index.html) module of entry points into the
+//!   crate's parsers, for fuzz targets.  It is only available if the
+//!   `fuzzing` feature is enabled.
+//! * An [ffi](ffi/index.html) module of C functions, such as
+//!   `rnc_load_certs`, for applications in other languages and their
+//!   bindings, declared in `include/rustls_native_certs.h`.  It is only
+//!   available if the `ffi` feature is enabled.
+//! * A WASI component exporting the loads through the interface in
+//!   `wit/load-roots.wit`, for WASM plugins and sidecars, which reads the
+//!   bundle its host grants it.  It is only built for `wasm32-wasip2`, and
+//!   only if the `component` feature is enabled.
+//! * A [TestStore](struct.TestStore.html) type which stands in for the
+//!   user's store on Windows and macOS, made with the platform's own APIs,
+//!   for end-to-end tests of those backends.  It is only available on those
+//!   platforms, if the `test-util` feature is enabled.
+//! * A [StoreCapture](struct.StoreCapture.html) type which records the raw
+//!   results of enumerating the native store to a file, so a bug report can
+//!   carry a reproducible copy of a machine's store.  It is only available
+//!   if the `capture` feature is enabled; with the `test-util` feature as
+//!   well, the capture is replayed through every load when
+//!   `RUSTLS_NATIVE_CERTS_REPLAY` names that file.
+//!
+//! Loads fail with a `std::io::Error`, as a [RootStoreBuilder](trait.RootStoreBuilder.html)
+//! does.  Converting one into an [Error](enum.Error.html) says which class
+//! of failure it is: a platform API failing, no store being found, a
+//! store holding no roots, a certificate which could not be parsed, a lack of permission, or a
+//! platform with no store the crate can read.  On such platforms, which
+//! include iOS, tvOS and watchOS, the crate still builds, and [is_supported](fn.is_supported.html)
+//! returns false.
+//!
+//! The `rustls` feature builds against rustls 0.19.  With the `rustls-021`
+//! or `rustls-022` feature, a rustls 0.21 or 0.22 `RootCertStore` is a
+//! [RootStoreBuilder](trait.RootStoreBuilder.html) too, so that
+//! [build_native_certs](fn.build_native_certs.html) or a
+//! [NativeCertLoader](struct.NativeCertLoader.html) loads straight into it.
+//! rustls 0.23 and later take the `CertificateDer`s of
+//! [load_native_certs_der](fn.load_native_certs_der.html), with the
+//! `pki-types` feature.
+//!
+//! With the `serde` feature, [Purpose](enum.Purpose.html), [TrustDomain](enum.TrustDomain.html),
+//! [Provenance](enum.Provenance.html), [PlatformTrust](enum.PlatformTrust.html)
+//! and, with `metadata`, [NativeCert](struct.NativeCert.html) implement
+//! serde's `Serialize` and `Deserialize`, so a loaded store can be cached
+//! on disk or handed from a privileged process to a sandboxed one.
+//!
+//! With the `tracing` feature, loads report what they do as debug-level
+//! `tracing` events: where they read roots from, and each root they skip or
+//! leave out, and why, such as trust settings which deny it or a purpose it
+//! is not enabled for.  Each source a load reads, such as the Linux bundle,
+//! a macOS trust domain or a Windows store, has a `source` span of its own,
+//! whose fields name it, and its events fall within it.
+//!
+//! With the `log` feature, and not `tracing`, the same events are logged at
+//! the debug level through the `log` crate instead, without spans.
+//!
+//! If the crate is built with the `test-util` feature, setting the
+//! `RUSTLS_NATIVE_CERTS_OVERRIDE` environment variable to a PEM file, or
+//! to a directory of certificates, points every load of the native store
+//! at that fixture instead, so integration tests can run the same on any
+//! machine.  In a directory, the files are the roots (for every purpose),
+//! and `intermediates`, `distrusted` and `crls` subdirectories supply the
+//! other loads.  Client identities and change notifications are not
+//! redirected.
+//!
+//! Building with the `cli` feature adds a `native-certs` command, whose
+//! `dump` subcommand prints what the native store holds, with each
+//! certificate's subject, fingerprint, expiry and where it was loaded from:
+//! `cargo install rustls-native-certs --features cli`, then `native-certs
+//! dump --format table`, `json` or `pem`.  `native-certs diff` compares the
+//! store's roots with the Mozilla roots from webpki-roots, or with a PEM
+//! bundle, to show what a machine trusts beyond the norm.  `native-certs
+//! watch` prints each root added, removed or modified as the store
+//! changes, for following an MDM or group policy rollout as it lands.
+//! And when a program finds no roots, `native-certs doctor` checks the
+//! environment, probes each place the store may be read from, and says
+//! what to do about what it finds.
+//!
+//! The `mock-backend` feature goes further, replacing the platform's store
+//! on every target with one held in memory, whose contents are set with
+//! [install_mock_backend](fn.install_mock_backend.html).  The Windows and
+//! macOS backends, and the platform libraries they link, are only built
+//! with the default `native-backend` feature, so with `default-features =
+//! false` and `mock-backend` the crate builds in minimal containers and
+//! cross-compilation environments which lack those libraries.
+//!
+//! The features which take SHA-256 digests, such as `metadata` for
+//! fingerprints, build the `sha2` crate, except on macOS, where the digests
+//! come from CommonCrypto, which every process there already links.
 
-#[cfg(all(unix, not(target_os = "macos")))]
+/// Logs what a load did, at the debug level: as a tracing event, in the
+/// span of the source being read, with the "tracing" feature, or else as a
+/// log record, with the "log" feature.  Without either, the arguments are
+/// checked but not evaluated.
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)+);
+        #[cfg(all(feature = "log", not(feature = "tracing")))]
+        log::debug!($($arg)+);
+        #[cfg(not(any(feature = "log", feature = "tracing")))]
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+/// Opens the span of a source of roots, with the "tracing" feature, whose
+/// fields say which source it is, as in `source_span!(bundle = path)`; each
+/// is recorded with its `Debug` form.  Without it, the fields are not
+/// evaluated.
+macro_rules! source_span {
+    ($($name:ident = $value:expr),+) => {{
+        #[cfg(feature = "tracing")]
+        let span = crate::trace::SourceSpan::new(
+            tracing::debug_span!("source", $($name = tracing::field::debug(&$value)),+));
+        #[cfg(not(feature = "tracing"))]
+        let span = {
+            if false {
+                let _ = ($(&$value,)+);
+            }
+            crate::trace::SourceSpan::new()
+        };
+        span
+    }};
+}
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
 mod unix;
-#[cfg(all(unix, not(target_os = "macos")))]
+#[cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
 use unix as platform;
 
-#[cfg(windows)]
+#[cfg(all(windows, not(feature = "mock-backend")))]
 mod windows;
-#[cfg(windows)]
+#[cfg(all(windows, not(feature = "mock-backend")))]
 use windows as platform;
 
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "mock-backend")))]
 mod macos;
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "mock-backend")))]
 use macos as platform;
 
+#[cfg(feature = "mock-backend")]
+mod mock_backend;
+#[cfg(feature = "mock-backend")]
+use mock_backend as platform;
+
+#[cfg(all(target_os = "wasi", not(feature = "mock-backend")))]
+mod wasi;
+#[cfg(all(target_os = "wasi", not(feature = "mock-backend")))]
+use wasi as platform;
+
+#[cfg(all(any(all(not(unix), not(windows), not(target_os = "wasi")), all(target_vendor = "apple", not(target_os = "macos"))),
+          not(feature = "mock-backend")))]
+mod unsupported;
+#[cfg(all(any(all(not(unix), not(windows), not(target_os = "wasi")), all(target_vendor = "apple", not(target_os = "macos"))),
+          not(feature = "mock-backend")))]
+use unsupported as platform;
+
+#[cfg(all(feature = "test-util", not(feature = "mock-backend"), any(windows, target_os = "macos")))]
+mod test_store;
+
+#[cfg(all(any(windows, target_os = "macos"), not(feature = "native-backend"), not(feature = "mock-backend")))]
+compile_error!("rustls-native-certs needs the \"native-backend\" or \"mock-backend\" feature on this platform");
+
 #[cfg(feature = "rustls")]
 mod rustls;
+#[cfg(feature = "rustls")]
+mod client_auth;
+#[cfg(all(feature = "rustls", not(feature = "mock-backend"),
+          any(windows, target_os = "macos", all(unix, not(target_vendor = "apple"), feature = "pkcs11"))))]
+mod sign;
+
+mod cache;
+#[cfg_attr(any(feature = "mock-backend", all(not(unix), not(windows)), all(target_vendor = "apple", not(target_os = "macos")),
+               all(unix, not(target_vendor = "apple"), not(feature = "pkcs12"), not(feature = "pkcs11"), not(feature = "aia"))),
+           allow(dead_code))]
+mod der;
+mod error;
+mod identity;
+mod iter;
+mod loader;
+mod metadata;
+mod partial;
+mod purpose;
+#[cfg(feature = "serde")]
+mod serialize;
+mod source;
+#[cfg(feature = "metadata")]
+mod store;
+mod trace;
+#[cfg(feature = "test-util")]
+mod mock;
+#[cfg(feature = "test-util")]
+mod overrides;
+#[cfg(feature = "test-util")]
+mod golden;
+#[cfg(feature = "test-util")]
+mod assertions;
+#[cfg(feature = "test-util")]
+mod fault;
+#[cfg(feature = "capture")]
+mod capture;
+#[cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend"), feature = "pkcs11"))]
+mod pkcs11;
+
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(all(feature = "watch", any(target_os = "macos", all(unix, not(target_vendor = "apple"))), not(feature = "mock-backend")))]
+mod fs_watch;
+#[cfg(feature = "watch")]
+mod poll;
+#[cfg(any(feature = "watch", feature = "metadata"))]
+#[cfg_attr(not(feature = "watch"), allow(dead_code))]
+mod snapshot;
+
+#[cfg(feature = "aia")]
+mod aia;
+mod replace;
+#[cfg(any(feature = "watch", feature = "aia", feature = "test-util", feature = "metadata", feature = "cli"))]
+mod sha256;
+
+#[cfg(feature = "pki-types")]
+mod pki_types;
+#[cfg(any(feature = "rustls-021", feature = "rustls-022"))]
+mod rustls_versions;
+
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(all(feature = "component", target_os = "wasi"))]
+mod component;
+
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod cli;
 
-use std::io::Error;
+#[cfg(all(feature = "rustls", feature = "watch"))]
+mod reload;
+#[cfg(all(feature = "rustls", feature = "watch"))]
+mod persist;
+
+use std::io;
 use std::io::BufRead;
+use std::time::Duration;
+
+pub use crate::identity::{
+    load_native_client_identities,
+    load_native_client_identities_for,
+    ClientIdentity,
+    IdentitySource,
+};
+#[cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend"), feature = "pkcs11"))]
+pub use crate::pkcs11::load_pkcs11_identities;
+pub use crate::cache::{invalidate_native_certs_cache, native_certs_cached};
+pub use crate::error::Error;
+pub use crate::iter::NativeCertIter;
+pub use crate::loader::{NativeCertLoader, TrustDomain, WindowsLocation};
+pub use crate::metadata::{PlatformTrust, Provenance};
+#[cfg(feature = "metadata")]
+pub use crate::metadata::{load_native_certs_with_metadata, NativeCert};
+#[cfg(feature = "metadata")]
+pub use crate::store::CertStore;
+pub use crate::partial::{CertificateResult, LoadError};
+pub use crate::purpose::Purpose;
+pub use crate::source::{load_certs_from, load_from, CertSource, CertificateSource, LoadedCertificates, NativeCertificateSource};
+#[cfg(feature = "test-util")]
+pub use crate::mock::{MockCertificateSource, TrustStatus};
+#[cfg(feature = "test-util")]
+pub use crate::golden::StoreSnapshot;
+#[cfg(feature = "test-util")]
+pub use crate::assertions::{
+    assert_contains_fingerprint,
+    assert_contains_subject,
+    assert_not_contains_fingerprint,
+    assert_not_contains_subject,
+    assert_store_eq,
+};
+#[cfg(feature = "test-util")]
+pub use crate::fault::{inject_fault, Fault, FaultGuard};
+#[cfg(feature = "capture")]
+pub use crate::capture::StoreCapture;
+#[cfg(feature = "mock-backend")]
+pub use crate::mock_backend::install_mock_backend;
+#[cfg(all(feature = "test-util", not(feature = "mock-backend"), any(windows, target_os = "macos")))]
+pub use crate::test_store::{TestStore, TestStoreGuard};
 
 #[cfg(feature = "rustls")]
-pub use crate::rustls::{load_native_certs, PartialResult};
+pub use crate::rustls::{load_native_certs, load_native_root_store, NativeRootStore, PartialResult};
+#[cfg(all(feature = "rustls", feature = "tokio"))]
+pub use crate::rustls::load_native_certs_async;
+#[cfg(feature = "webpki-roots")]
+pub use crate::rustls::load_native_certs_or_fallback;
+#[cfg(feature = "rustls")]
+pub use crate::client_auth::{load_native_client_config, NativeClientCertResolver};
+
+#[cfg(feature = "pki-types")]
+pub use crate::pki_types::load_native_certs_der;
+#[cfg(feature = "webpki")]
+pub use crate::pki_types::{load_native_trust_anchors, NativeTrustAnchors};
+
+#[cfg(feature = "aia")]
+pub use crate::aia::IntermediateFetcher;
+#[cfg(all(feature = "rustls", feature = "aia"))]
+pub use crate::aia::AiaVerifier;
+
+#[cfg(any(feature = "watch", feature = "metadata"))]
+pub use crate::snapshot::StoreDiff;
+#[cfg(feature = "watch")]
+pub use crate::watch::{CertWatcher, CertWatcherBuilder, ChangeEvent};
+
+#[cfg(all(feature = "rustls", feature = "watch"))]
+pub use crate::reload::{ReloadableRoots, ReloadableRootsBuilder, ReloadingVerifier};
 
 pub trait RootStoreBuilder {
-    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error>;
-    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error>;
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), io::Error>;
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), io::Error>;
+
+    /// Says where the certificates given to the builder next were found,
+    /// and the trust the platform gives them.  Builders which have no use
+    /// for this can leave it to the default, which ignores it.
+    fn set_provenance(&mut self, provenance: &Provenance, trust: PlatformTrust) {
+        let _ = (provenance, trust);
+    }
+
+    /// Says that the platform trusts the certificates given to the builder
+    /// next only for `hosts`, as trust settings on macOS can, until
+    /// `set_provenance` is next called.  Builders which have no use for
+    /// this can leave it to the default, which ignores it.
+    fn set_host_restrictions(&mut self, hosts: &[String]) {
+        let _ = hosts;
+    }
+
+    /// Says that the platform trusts the certificates given to the builder
+    /// next only where evaluation meets one of `errors`, `OSStatus` codes
+    /// which trust settings on macOS can allow, until `set_provenance` is
+    /// next called.  Builders which have no use for this can leave it to the
+    /// default, which ignores it.
+    fn set_allowed_errors(&mut self, errors: &[i32]) {
+        let _ = errors;
+    }
+
+    /// Says that the platform explicitly distrusts `der`, found where
+    /// `set_provenance` last said, as trust settings on macOS can deny a
+    /// certificate, and the Windows "Disallowed" store holds those Windows
+    /// denies, so it is not given to `load_der`.  A denial in one
+    /// domain wins over trust in a less specific one, so a certificate the
+    /// user denies is reported here though the system trusts it.  Builders
+    /// which have no use for this can leave it to the default, which
+    /// ignores it.
+    fn denied(&mut self, der: &[u8]) {
+        let _ = der;
+    }
+
+    /// Says that the load skipped part of the store for `error`, and went
+    /// on, as loads which skip inaccessible trust domains on macOS do.  Such
+    /// a load succeeds with what it could read, so this is the only word of
+    /// what it could not.  Builders which have no use for this can leave it
+    /// to the default, which ignores it.
+    fn skipped(&mut self, error: io::Error) {
+        let _ = error;
+    }
 }
 
 /// Loads root certificates found in the platform's native certificate
@@ -44,9 +502,510 @@ pub trait RootStoreBuilder {
 ///
 /// This function fails in a platform-specific way, expressed in a `std::io::Error`.
 ///
+/// The roots are given to the builder in the same order on every load of
+/// an unchanged store: the order of the bundle on Linux and other Unix
+/// systems, the order of the store on Windows, and on macOS, which keeps
+/// no order, ascending order of their DER encodings.
+///
+/// On macOS, where the trust settings of every domain enumerate nothing, as
+/// with a locked keychain or some MDM configurations, the system's anchor
+/// certificates, from `SecTrustCopyAnchorCertificates`, are loaded in their
+/// place, rather than no roots at all.  Either way, roots Apple ships but
+/// has since withdrawn trust from, which only the system's trust
+/// evaluation knows of, are left out.
+///
 /// This function can be expensive: on some platforms it involves loading
 /// and parsing a ~300KB disk file.  It's therefore prudent to call
 /// this sparingly.
-pub fn build_native_certs<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Error> {
-    platform::build_native_certs(builder)
+pub fn build_native_certs<B: RootStoreBuilder>(builder: &mut B) -> Result<(), io::Error> {
+    build_native_certs_for(Purpose::ServerAuth, builder)
+}
+
+/// Returns whether this crate can read the platform's native certificate
+/// store on the target it was built for: on Windows, macOS, Linux and other
+/// Unix systems, in WASI guests, which read the bundle their host grants
+/// them, or anywhere with the "mock-backend" feature.
+///
+/// It is not supported on iOS, tvOS, watchOS or visionOS, where no public
+/// API lists the system's roots: `SecTrustCopyAnchorCertificates` is
+/// macOS-only, and apps cannot see the profiles a user installs.  Verify with the platform's own trust
+/// evaluation there, or bundle roots.
+///
+/// Elsewhere, the crate still builds, but every load fails with
+/// `ErrorKind::Unsupported`, which converts into `Error::Unsupported`, so a
+/// crate built for every target can depend on this one and fall back to
+/// roots it bundles:
+///
+/// ```no_run
+/// if !rustls_native_certs::is_supported() {
+///     // Use bundled roots, such as webpki-roots.
+/// }
+/// ```
+pub const fn is_supported() -> bool {
+    cfg!(any(windows, target_os = "macos", all(unix, not(target_vendor = "apple")), target_os = "wasi",
+         feature = "mock-backend"))
+}
+
+/// Returns an iterator over the root certificates found in the platform's
+/// native certificate store, which yields each as it is read.
+///
+/// Where the store is a PEM bundle, as on Linux, the bundle is read only as
+/// far as the iterator is advanced, so a caller which wants the first few
+/// roots, or searches for one, need not parse the whole of it.  Elsewhere,
+/// and when something stands in for the store, the store is read in full
+/// when the iterator is created, and the iterator yields what was found.
+///
+/// ```no_run
+/// let first_root = rustls_native_certs::native_cert_iter()
+///     .next()
+///     .transpose()
+///     .expect("could not read the store");
+/// ```
+pub fn native_cert_iter() -> NativeCertIter {
+    #[cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+    if !is_stood_in() {
+        return platform::native_cert_iter();
+    }
+
+    NativeCertIter::collected(build_native_certs)
+}
+
+/// Loads the root certificates found in the platform's native certificate
+/// store, returning those which could be read along with an error for each
+/// certificate, or part of the store, which could not.
+///
+/// Where [load_native_certs](fn.load_native_certs.html) fails on the first
+/// problem, this skips it and goes on: a malformed block in a PEM bundle,
+/// or a keychain entry whose trust settings cannot be read, costs only that
+/// certificate.
+///
+/// ```no_run
+/// let result = rustls_native_certs::load_native_certs_partial();
+/// for err in &result.errors {
+///     eprintln!("skipped: {}", err);
+/// }
+/// println!("{} roots", result.certs.len());
+/// ```
+pub fn load_native_certs_partial() -> CertificateResult {
+    native_cert_iter().collect()
+}
+
+/// Returns whether something stands in for the platform's store, or a
+/// fault is injected into loads of it.
+#[cfg(all(any(target_os = "macos", all(unix, not(target_vendor = "apple"))), not(feature = "mock-backend")))]
+fn is_stood_in() -> bool {
+    #[cfg(feature = "test-util")]
+    if fault::Fault::current().is_some() || overrides::Fixture::from_env().is_some() {
+        return true;
+    }
+
+    #[cfg(all(feature = "capture", feature = "test-util"))]
+    if capture::replay_path().is_some() {
+        return true;
+    }
+
+    false
+}
+
+/// Loads the root certificates the platform trusts for `purpose`,
+/// executing callbacks on the provided builder.
+///
+/// With `Purpose::ServerAuth`, this is [build_native_certs](fn.build_native_certs.html).
+/// For other purposes, trust is read from:
+///
+/// * on Windows, the extended key usages each root in the current user's
+///   "Root" store, which includes the Group Policy and enterprise ones,
+///   is enabled for, which reflect both its own properties and the
+///   restrictions in Microsoft's root program trust list;
+/// * on macOS, the trust settings for the matching policy (such as "SMIME"
+///   for email protection, or "CodeSigning"), as for TLS, where it is
+///   "sslServer".  A root whose trust settings only mention other policies,
+///   or other applications than this one, is not trusted for the purpose;
+/// * elsewhere, the bundle p11-kit extracts for the purpose, such as
+///   `/etc/pki/ca-trust/extracted/pem/email-ca-bundle.pem`, or failing that,
+///   the trust OpenSSL's "TRUSTED CERTIFICATE" format records for each root
+///   in `/etc/pki/ca-trust/extracted/openssl/ca-bundle.trust.crt`.  If
+///   neither exists, as on distributions which only ship roots for TLS, this
+///   fails with `ErrorKind::NotFound`.
+///
+/// Like [build_native_certs](fn.build_native_certs.html), this fails in a
+/// platform-specific way, expressed in a `std::io::Error`.
+pub fn build_native_certs_for<B: RootStoreBuilder>(purpose: Purpose, builder: &mut B) -> Result<(), io::Error> {
+    build_native_certs_in(purpose, &TrustDomain::ALL, &StoreAccess::default(), builder)
+}
+
+/// How a load reads the platform's store, as the
+/// [NativeCertLoader](struct.NativeCertLoader.html) options say.  Each
+/// option makes a difference only on its platform.
+#[derive(Clone, Debug)]
+pub(crate) struct StoreAccess {
+    /// Whether only what never prompts is read, on macOS, as
+    /// [NativeCertLoader::sandbox_safe](struct.NativeCertLoader.html#method.sandbox_safe)
+    /// says.
+    pub(crate) sandbox_safe: bool,
+    /// Whether a trust domain which cannot be read, on macOS, is skipped,
+    /// rather than failing the load.
+    pub(crate) skip_inaccessible: bool,
+    /// How long the trust domains may take to read, on macOS, if not
+    /// forever.
+    pub(crate) timeout: Option<Duration>,
+    /// The current user's stores read, on Windows, in order.
+    pub(crate) windows_stores: Vec<String>,
+    /// Which of the stores of each name are read, on Windows.
+    pub(crate) windows_location: WindowsLocation,
+    /// Whether the roots the AuthRoot list names which Windows has not
+    /// downloaded are reported.
+    pub(crate) windows_auth_root: bool,
+}
+
+impl Default for StoreAccess {
+    fn default() -> Self {
+        StoreAccess {
+            sandbox_safe: false,
+            skip_inaccessible: false,
+            timeout: None,
+            windows_stores: vec!["ROOT".to_owned()],
+            windows_location: WindowsLocation::Merged,
+            windows_auth_root: false,
+        }
+    }
+}
+
+/// Like [build_native_certs_for](fn.build_native_certs_for.html), but
+/// reading the trust settings of only `domains`, where the platform has
+/// them, as `access` says.
+pub(crate) fn build_native_certs_in<B: RootStoreBuilder>(purpose: Purpose,
+                                                         domains: &[TrustDomain],
+                                                         access: &StoreAccess,
+                                                         builder: &mut B) -> Result<(), io::Error> {
+    #[cfg(feature = "test-util")]
+    if let Some(fault) = fault::Fault::current() {
+        return fault.build_certs(builder, |builder| build_certs(purpose, domains, access, builder));
+    }
+
+    build_certs(purpose, domains, access, builder)
+}
+
+/// Loads the self-signed certificates without trust settings in the
+/// System keychain, on macOS, as
+/// [NativeCertLoader::include_system_keychain](struct.NativeCertLoader.html#method.include_system_keychain)
+/// says.  Elsewhere, and while something stands in for the store, this
+/// loads nothing.
+pub(crate) fn build_system_keychain_roots<B: RootStoreBuilder>(builder: &mut B) -> Result<(), io::Error> {
+    #[cfg(all(target_os = "macos", not(feature = "mock-backend")))]
+    if !is_stood_in() {
+        return platform::build_system_keychain_roots(builder);
+    }
+
+    let _ = builder;
+    Ok(())
+}
+
+/// Returns the DER encoding of each certificate configuration profiles
+/// installed, on macOS.  Elsewhere, and while something stands in for the
+/// store, there are none.
+#[cfg(feature = "metadata")]
+pub(crate) fn profile_certificates() -> &'static [Vec<u8>] {
+    #[cfg(all(target_os = "macos", not(feature = "mock-backend")))]
+    if !is_stood_in() {
+        return platform::profile_certificates();
+    }
+
+    &[]
+}
+
+/// Loads roots from the platform's store, or whatever stands in for it.
+/// Stand-ins have no trust domains.
+fn build_certs<B: RootStoreBuilder>(purpose: Purpose,
+                                    domains: &[TrustDomain],
+                                    access: &StoreAccess,
+                                    builder: &mut B) -> Result<(), io::Error> {
+    #[cfg(feature = "test-util")]
+    if let Some(fixture) = overrides::Fixture::from_env() {
+        let span = source_span!(fixture = fixture.path());
+        let _entered = span.enter();
+        debug!("loading roots from the override fixture {}", fixture.path().display());
+        return fixture.build_certs(builder);
+    }
+
+    #[cfg(all(feature = "capture", feature = "test-util"))]
+    if let Some(capture) = capture::replaying() {
+        let span = source_span!(capture = "replay");
+        let _entered = span.enter();
+        debug!("loading roots for {:?} from a capture replay", purpose);
+        return capture?.build_certs(purpose, builder);
+    }
+
+    build_platform_certs(purpose, domains, access, builder)
+}
+
+/// Loads roots from the platform's store itself.
+fn build_platform_certs<B: RootStoreBuilder>(purpose: Purpose,
+                                             domains: &[TrustDomain],
+                                             access: &StoreAccess,
+                                             builder: &mut B) -> Result<(), io::Error> {
+    #[cfg(all(target_os = "macos", not(feature = "mock-backend")))]
+    return match access.sandbox_safe {
+        true => platform::build_sandbox_safe(purpose, builder),
+        false => platform::build_native_certs_with(purpose, domains, access, builder),
+    };
+
+    #[cfg(all(windows, not(feature = "mock-backend")))]
+    {
+        let _ = domains;
+        platform::build_stores_certs(access, purpose, builder)
+    }
+
+    #[cfg(not(any(all(target_os = "macos", not(feature = "mock-backend")),
+                  all(windows, not(feature = "mock-backend")))))]
+    {
+        let _ = (domains, access);
+        platform::build_native_certs(purpose, builder)
+    }
+}
+
+/// The loads of the native store which return lists of DER encodings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum List {
+    Intermediates,
+    Distrusted,
+    Crls,
+}
+
+/// Does a list load from the platform's store, or whatever stands in for
+/// it, before any filtering.
+fn load_list(list: List) -> Result<Vec<Vec<u8>>, io::Error> {
+    #[cfg(feature = "test-util")]
+    if let Some(fault) = fault::Fault::current() {
+        return Err(fault.to_error());
+    }
+
+    #[cfg(feature = "test-util")]
+    if let Some(fixture) = overrides::Fixture::from_env() {
+        return match list {
+            List::Intermediates => fixture.load("intermediates", rustls_pemfile::certs),
+            List::Distrusted => fixture.load("distrusted", rustls_pemfile::certs),
+            List::Crls => fixture.load("crls", rustls_pemfile::crls),
+        };
+    }
+
+    #[cfg(all(feature = "capture", feature = "test-util"))]
+    if let Some(capture) = capture::replaying() {
+        return capture?.load_list(list);
+    }
+
+    load_platform_list(list)
+}
+
+/// Describes where the roots for `purpose` are loaded from, as
+/// [build_certs](fn.build_certs.html) chooses.
+pub(crate) fn describe_roots(purpose: Purpose) -> String {
+    describe_stand_in().unwrap_or_else(|| platform::describe_roots(purpose))
+}
+
+/// Describes where `list` is loaded from, as [load_list](fn.load_list.html)
+/// chooses.
+#[cfg(feature = "cli")]
+pub(crate) fn describe_list(list: List) -> String {
+    describe_stand_in().unwrap_or_else(|| platform::describe_list(list))
+}
+
+/// Describes whatever stands in for the platform's store, if anything does.
+fn describe_stand_in() -> Option<String> {
+    #[cfg(feature = "test-util")]
+    if let Some(fixture) = overrides::Fixture::from_env() {
+        return Some(format!("override fixture {}", fixture.path().display()));
+    }
+
+    #[cfg(all(feature = "capture", feature = "test-util"))]
+    if let Some(path) = capture::replay_path() {
+        return Some(format!("replay of capture {}", path.to_string_lossy()));
+    }
+
+    None
+}
+
+/// What was found at one place the platform's store is read from, as the
+/// `doctor` command reports it.
+#[cfg(feature = "cli")]
+pub(crate) struct Probe {
+    /// The place: a path, or the name of a store.
+    pub(crate) location: String,
+    /// How many certificates are there, or why they could not be read.
+    pub(crate) found: Result<usize, io::Error>,
+    /// The purposes whose roots are loaded from here.
+    pub(crate) used_for: Vec<Purpose>,
+}
+
+/// Returns the environment variables which change where certificates are
+/// loaded from.  Each names paths.
+#[cfg(feature = "cli")]
+pub(crate) fn environment() -> Vec<&'static str> {
+    let mut vars = Vec::new();
+    #[cfg(feature = "test-util")]
+    vars.push(overrides::OVERRIDE_VAR);
+    #[cfg(all(feature = "capture", feature = "test-util"))]
+    vars.push(capture::REPLAY_VAR);
+    vars.extend(platform::ENVIRONMENT);
+    vars
+}
+
+fn load_platform_list(list: List) -> Result<Vec<Vec<u8>>, io::Error> {
+    match list {
+        List::Intermediates => platform::load_intermediates(),
+        List::Distrusted => platform::load_distrusted(),
+        List::Crls => platform::load_crls(),
+    }
+}
+
+/// Loads the intermediate certificates found in the platform's native
+/// certificate store, returning their DER encodings.
+///
+/// These are not trust anchors: they are candidates a verifier may use to
+/// build a chain from a server's certificate to one of the roots from
+/// [build_native_certs](fn.build_native_certs.html), when the server has
+/// not sent them itself.  They come from:
+///
+/// * on Windows, the current user's "CA" (intermediate certification
+///   authorities) store;
+/// * on macOS, the certificates in the current user's keychain search list,
+///   and those with trust settings that do not make them roots, leaving out
+///   any the user has set to be distrusted;
+/// * elsewhere, the directories listed in the `RUSTLS_NATIVE_CERTS_INTERMEDIATE_DIRS`
+///   environment variable (separated like `PATH`).  Files may contain PEM
+///   or DER.
+///
+/// Self-issued certificates are left out, as are duplicates.
+///
+/// Like [build_native_certs](fn.build_native_certs.html), this fails in a
+/// platform-specific way, expressed in a `std::io::Error`.
+pub fn load_native_intermediates() -> Result<Vec<Vec<u8>>, io::Error> {
+    Ok(filter_intermediates(load_list(List::Intermediates)?))
+}
+
+/// Leaves self-issued certificates, and duplicates, out of `found`.
+fn filter_intermediates(found: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    dedup(found)
+        .into_iter()
+        .filter(|der| der::cert_fields(der).is_some_and(|fields| !fields.is_self_issued()))
+        .collect()
+}
+
+/// Leaves duplicates out of `found`, keeping the first of each.
+fn dedup(found: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    let mut unique: Vec<Vec<u8>> = Vec::new();
+    for der in found {
+        if !unique.contains(&der) {
+            unique.push(der);
+        }
+    }
+    unique
+}
+
+/// Loads the certificates the platform explicitly distrusts, returning
+/// their DER encodings.
+///
+/// Roots in this list are already left out by [build_native_certs](fn.build_native_certs.html);
+/// this is for verifiers with their own set of trust anchors, and for
+/// scanners, which want to honour the platform's distrust anyway.  They
+/// come from:
+///
+/// * on Windows, the current user's "Disallowed" (untrusted certificates)
+///   store;
+/// * on macOS, certificates whose trust settings deny them, with the same
+///   precedence between the user, admin and system domains as for roots.
+///   Roots Apple has withdrawn trust from for TLS, though they are still in
+///   the system's trust settings, are included too, as the system's trust
+///   evaluation rejects them.  The system keeps that current with its own
+///   updates, so a long-running process sees a withdrawal on its next load,
+///   with nothing fetched by the crate;
+/// * elsewhere, p11-kit's blocklist directories (`/etc/pki/ca-trust/source/blocklist`
+///   and the like, under their older "blacklist" names too), and the
+///   directories listed in the `RUSTLS_NATIVE_CERTS_DISTRUSTED_DIRS`
+///   environment variable (separated like `PATH`).  Files may contain PEM
+///   or DER.
+///
+/// Duplicates are left out.  Distrusted certificates need not be roots,
+/// nor even CAs.
+///
+/// Like [build_native_certs](fn.build_native_certs.html), this fails in a
+/// platform-specific way, expressed in a `std::io::Error`.
+pub fn load_native_distrusted() -> Result<Vec<Vec<u8>>, io::Error> {
+    Ok(dedup(load_list(List::Distrusted)?))
+}
+
+/// Returns the SHA-256 fingerprint of each certificate
+/// [load_native_distrusted](fn.load_native_distrusted.html) finds, in the
+/// same order, for auditing tools and tests which want to see what loads
+/// leave out.
+///
+/// The crate keeps no list of its own: this is what the platform
+/// distrusts at the time of the call, read as a load reads it.  On macOS,
+/// that includes the roots Apple has withdrawn trust from, as the system's
+/// trust evaluation knows them, so the list follows the system's updates
+/// rather than the crate's releases.
+///
+/// This fails as `load_native_distrusted` does.
+///
+/// *This function is available only if the crate is built with the "metadata" feature.*
+#[cfg(feature = "metadata")]
+pub fn distrusted_fingerprints() -> Result<Vec<[u8; 32]>, io::Error> {
+    Ok(load_native_distrusted()?
+        .iter()
+        .map(sha256::Sha256::digest)
+        .collect())
+}
+
+/// Loads the certificate revocation lists found in the platform's native
+/// certificate store, returning their DER encodings.
+///
+/// These come from:
+///
+/// * on Windows, the current user's "CA" and "Root" certificate stores;
+/// * on macOS, nowhere: the keychain does not expose CRLs through a public
+///   API, so this always returns an empty list;
+/// * elsewhere, `/etc/ssl/crls`, CRLs in OpenSSL's hashed certificate
+///   directory (named like `1a2b3c4d.r0`), and the directories listed in the
+///   `RUSTLS_NATIVE_CERTS_CRL_DIRS` environment variable (separated like
+///   `PATH`).  Files may contain PEM or DER.
+///
+/// Nothing here checks the CRLs' signatures or freshness: that is for the
+/// verifier which consumes them.
+///
+/// Like [build_native_certs](fn.build_native_certs.html), this fails in a
+/// platform-specific way, expressed in a `std::io::Error`.
+pub fn load_native_crls() -> Result<Vec<Vec<u8>>, io::Error> {
+    load_list(List::Crls)
+}
+
+/// Writes the root certificates found in the platform's native certificate
+/// store to `path` as a PEM bundle, for tools which take only the path of
+/// a CA bundle, such as curl's `--cacert`, git's `http.sslCAInfo`, or
+/// Python's `REQUESTS_CA_BUNDLE`.  Returns how many roots were written.
+///
+/// Duplicates are left out.  The file is replaced atomically, so a tool
+/// reading it never sees part of a bundle, and a failed load leaves any
+/// previous bundle in place.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let written = rustls_native_certs::export_pem_bundle("/tmp/roots.pem")?;
+/// println!("wrote {} roots", written);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Like [build_native_certs](fn.build_native_certs.html), this fails in a
+/// platform-specific way, expressed in a `std::io::Error`.
+pub fn export_pem_bundle(path: impl AsRef<std::path::Path>) -> Result<usize, io::Error> {
+    use std::io::Write;
+
+    let roots = dedup(NativeCertLoader::new().load()?);
+    replace::replace_file(path.as_ref(), |f| {
+        for der in &roots {
+            f.write_all(der::certificate_pem(der).as_bytes())?;
+        }
+        Ok(())
+    })?;
+    Ok(roots.len())
 }
@@ -0,0 +1,546 @@
+use std::collections::HashSet;
+use std::io::{BufRead, Error};
+use std::time::{Duration, SystemTime};
+
+use crate::iter::Collect;
+use crate::{der, CertSource, CertificateResult, CertificateSource, StoreAccess, PlatformTrust, Provenance, Purpose,
+            RootStoreBuilder};
+
+/// A level of trust settings on macOS, where each overrides those below it.
+///
+/// Elsewhere there are no such levels, so trust domains make no difference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TrustDomain {
+    /// The current user's trust settings.
+    User,
+    /// The trust settings an administrator, or an MDM profile, set for the
+    /// machine.
+    Admin,
+    /// The trust settings Apple ships with the system.
+    System,
+}
+
+impl TrustDomain {
+    /// Every domain, from the most to the least specific.
+    pub(crate) const ALL: [TrustDomain; 3] = [TrustDomain::User, TrustDomain::Admin, TrustDomain::System];
+}
+
+/// Which of the Windows certificate stores of a name are read: the current
+/// user's, which include the local machine's, or either on its own.
+///
+/// Elsewhere there is one store, so the location makes no difference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum WindowsLocation {
+    /// The current user's stores as CryptoAPI shows them, with the local
+    /// machine's certificates and those the user added together, as
+    /// schannel trusts them.
+    Merged,
+    /// The certificates the current user added, without the local
+    /// machine's.
+    CurrentUser,
+    /// The local machine's stores, which every user and service shares,
+    /// without what any user added.
+    LocalMachine,
+}
+
+/// Loads roots from the native store, with options
+/// [build_native_certs_for](fn.build_native_certs_for.html) does not take.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use rustls_native_certs::{NativeCertLoader, TrustDomain};
+///
+/// let roots = NativeCertLoader::new()
+///     .domains(&[TrustDomain::Admin, TrustDomain::System])
+///     .include_expired(false)
+///     .load()?;
+/// println!("{} roots", roots.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NativeCertLoader {
+    purpose: Purpose,
+    domains: Vec<TrustDomain>,
+    include_expired: bool,
+    include_host_restricted: bool,
+    include_error_conditional: bool,
+    now: Option<SystemTime>,
+    tolerate_errors: bool,
+    dedup_by_spki: bool,
+    require_roots: bool,
+    access: StoreAccess,
+    include_system_keychain: bool,
+    extra_anchors: Vec<CertSource>,
+}
+
+impl Default for NativeCertLoader {
+    fn default() -> Self {
+        NativeCertLoader {
+            purpose: Purpose::ServerAuth,
+            domains: TrustDomain::ALL.to_vec(),
+            include_expired: true,
+            include_host_restricted: true,
+            include_error_conditional: true,
+            now: None,
+            tolerate_errors: false,
+            dedup_by_spki: false,
+            require_roots: false,
+            access: StoreAccess::default(),
+            include_system_keychain: false,
+            extra_anchors: Vec::new(),
+        }
+    }
+}
+
+impl NativeCertLoader {
+    /// Returns a loader with the default options, which load what
+    /// [build_native_certs](fn.build_native_certs.html) does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the purpose roots are loaded for.  The default is
+    /// `Purpose::ServerAuth`.
+    pub fn purpose(mut self, purpose: Purpose) -> Self {
+        self.purpose = purpose;
+        self
+    }
+
+    /// Sets the trust domains whose settings are read, on macOS.  The
+    /// default is all of them; with none, no roots are loaded.
+    ///
+    /// A daemon which must not trust what a user adds reads only
+    /// `&[TrustDomain::Admin, TrustDomain::System]`.  Settings in a domain
+    /// left out are ignored entirely, so a root the user has denied is
+    /// then loaded if the other domains trust it.  Where the domains read
+    /// include the system's, and none has settings, the system's anchor
+    /// certificates are loaded, as by
+    /// [build_native_certs](fn.build_native_certs.html).
+    pub fn domains(mut self, domains: &[TrustDomain]) -> Self {
+        self.domains = domains.to_vec();
+        self
+    }
+
+    /// Sets the current user's certificate stores read, on Windows, in
+    /// order, by their system names.  The default is `&["ROOT"]`, the
+    /// trusted roots; others include "AuthRoot", the third-party roots,
+    /// "CA", the intermediates, and "TrustedPeople".  Each store's
+    /// certificates are loaded for the purposes their extended key usages
+    /// allow, as the "ROOT" store's are, and a certificate in more than one
+    /// is loaded from each.  With none, no roots are loaded.  Elsewhere,
+    /// this makes no difference.
+    pub fn windows_stores(mut self, stores: &[&str]) -> Self {
+        self.access.windows_stores = stores.iter().map(|&store| store.to_owned()).collect();
+        self
+    }
+
+    /// Sets which of the stores of each name are read, on Windows.  The
+    /// default is `WindowsLocation::Merged`, the current user's stores with
+    /// the local machine's in them, as schannel reads them.
+    ///
+    /// A service which must not trust what a user adds reads only
+    /// `WindowsLocation::LocalMachine`.  The roots Active Directory pushes
+    /// are read with each location: the merged and local machine's stores
+    /// include the Group Policy and enterprise stores, and with
+    /// `WindowsLocation::CurrentUser`, the current user's Group Policy store
+    /// is read as well.  Certificates in the "Disallowed" store are left
+    /// out wherever they are, as schannel never trusts them.  Elsewhere,
+    /// this makes no difference.
+    pub fn windows_location(mut self, location: WindowsLocation) -> Self {
+        self.access.windows_location = location;
+        self
+    }
+
+    /// Sets whether the roots Microsoft's root program trusts which Windows
+    /// has not downloaded yet are reported, on Windows.  The default is
+    /// `false`.
+    ///
+    /// Windows downloads each of these roots only when a chain first needs
+    /// it, so the stores often lack roots schannel would accept.  Those it
+    /// has downloaded are loaded with the "ROOT" store, which includes the
+    /// local machine's "AuthRoot" store.  With this, the roots the AuthRoot
+    /// list Windows last fetched names which it has not downloaded are
+    /// reported as one error skipped with `ErrorKind::NotFound`, as
+    /// [load_partial](#method.load_partial) returns, which lists their
+    /// SHA-1 thumbprints; the load itself downloads nothing.  Roots the
+    /// list disables are not reported.
+    ///
+    /// The list is the local machine's, so it is not read with
+    /// `WindowsLocation::CurrentUser`, whose stores hold no roots of
+    /// Microsoft's.  Elsewhere, this makes no difference.
+    pub fn windows_auth_root(mut self, include: bool) -> Self {
+        self.access.windows_auth_root = include;
+        self
+    }
+
+    /// Sets whether roots whose validity period has ended are loaded.  The
+    /// default is `true`, as platforms keep expired roots, and some
+    /// verifiers accept chains to them.  Roots whose expiry cannot be read
+    /// are loaded either way.
+    pub fn include_expired(mut self, include: bool) -> Self {
+        self.include_expired = include;
+        self
+    }
+
+    /// Sets whether roots the platform trusts only for some hosts are
+    /// loaded, as trust settings on macOS can restrict a root to hostnames.
+    /// The default is `true`, as on other platforms, where there are no such
+    /// roots; a builder given them learns their hosts through
+    /// [RootStoreBuilder::set_host_restrictions](trait.RootStoreBuilder.html#method.set_host_restrictions),
+    /// which most do not heed, so a verifier given a store of them trusts
+    /// them for every host.  Set this to `false` to leave them out.
+    pub fn include_host_restricted(mut self, include: bool) -> Self {
+        self.include_host_restricted = include;
+        self
+    }
+
+    /// Sets whether roots the platform trusts only where evaluation meets
+    /// an error it allows are loaded, as trust settings on macOS can trust
+    /// a root only if, say, it has expired.  The default is `true`; a
+    /// builder given them learns the errors through
+    /// [RootStoreBuilder::set_allowed_errors](trait.RootStoreBuilder.html#method.set_allowed_errors),
+    /// which most do not heed, so a verifier given a store of them trusts
+    /// them whatever it finds.  Set this to `false` to leave them out.
+    pub fn include_error_conditional(mut self, include: bool) -> Self {
+        self.include_error_conditional = include;
+        self
+    }
+
+    /// Sets the time at which roots are judged to have expired.  The
+    /// default is the time of each load.
+    pub fn now(mut self, now: SystemTime) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Sets whether a load which finds some roots, but fails partway
+    /// through, succeeds with the roots it found.  The default is `false`,
+    /// so such a load fails.  A load which finds no roots fails either way.
+    pub fn tolerate_errors(mut self, tolerate: bool) -> Self {
+        self.tolerate_errors = tolerate;
+        self
+    }
+
+    /// Sets whether roots with the same public key as one already loaded are
+    /// left out, as re-issued and cross-signed copies of a root are in some
+    /// stores.  The first root found with each SubjectPublicKeyInfo is kept.
+    /// The default is `false`, so every root is loaded.  Roots which cannot
+    /// be parsed are loaded either way.
+    pub fn dedup_by_spki(mut self, dedup: bool) -> Self {
+        self.dedup_by_spki = dedup;
+        self
+    }
+
+    /// Sets whether a load which succeeds without finding any roots fails,
+    /// with an error which converts into `Error::NoCertsFound` and says
+    /// where the roots were looked for.  The default is `false`, so such a
+    /// load succeeds, and the empty store fails every verification later,
+    /// where the cause is harder to see.  Roots left out by the other
+    /// options are not counted as found.
+    pub fn require_roots(mut self, require: bool) -> Self {
+        self.require_roots = require;
+        self
+    }
+
+    /// Sets whether the native store is read only through APIs which never
+    /// prompt the user for keychain access, and which work under the App
+    /// Sandbox.  On macOS, this loads only the system's anchor certificates,
+    /// the roots Apple ships, so roots the user or an administrator added,
+    /// and trust settings of every domain, are not read, and
+    /// [domains](#method.domains) makes no difference.  Elsewhere, where
+    /// reading the store never prompts, it makes no difference.  The default
+    /// is `false`.
+    pub fn sandbox_safe(mut self, sandbox_safe: bool) -> Self {
+        self.access.sandbox_safe = sandbox_safe;
+        self
+    }
+
+    /// Sets whether, on macOS, a trust domain which cannot be read, as when
+    /// its keychain is locked or access to it is denied, is skipped, and the
+    /// load goes on with the others, rather than failing.  A certificate
+    /// whose trust settings cannot be read is skipped too.  What is skipped
+    /// is given to the builder's
+    /// [skipped](trait.RootStoreBuilder.html#method.skipped), and
+    /// [load_partial](#method.load_partial) returns it.  A root the skipped
+    /// domain would have denied can then be loaded from a less specific
+    /// one.  The default is `false`; elsewhere, this makes no difference.
+    pub fn skip_inaccessible_domains(mut self, skip: bool) -> Self {
+        self.access.skip_inaccessible = skip;
+        self
+    }
+
+    /// Sets how long, on macOS, the trust domains may take to read, as
+    /// reading one can wait on a prompt to unlock its keychain.  A domain
+    /// not read in time fails the load with `ErrorKind::TimedOut`, or is
+    /// skipped, as [skip_inaccessible_domains](#method.skip_inaccessible_domains)
+    /// says, and the thread reading it is left to finish.  The default is
+    /// no limit; elsewhere, this makes no difference.
+    pub fn keychain_timeout(mut self, timeout: Duration) -> Self {
+        self.access.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets whether, on macOS, the self-signed certificates in the System
+    /// keychain which have no trust settings in any domain are loaded too,
+    /// after the native roots, as installers sometimes add roots without
+    /// trusting them.  They go through the same options as extra anchors.
+    /// The default is `false`, as the platform does not trust them for
+    /// being there; elsewhere, this makes no difference.
+    pub fn include_system_keychain(mut self, include: bool) -> Self {
+        self.include_system_keychain = include;
+        self
+    }
+
+    /// Sets roots of one's own to load after those of the native store, such
+    /// as an internal CA's, from files or from memory.  They go through the
+    /// same options as the native roots, and one already loaded, by the
+    /// native store or an earlier source, is left out, as is one the
+    /// platform denies, as trust settings on macOS can.  A source which
+    /// fails makes the load fail, as a failure of the native store does.
+    /// The default is none.
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// use rustls_native_certs::{CertSource, NativeCertLoader};
+    ///
+    /// let roots = NativeCertLoader::new()
+    ///     .extra_anchors(&[
+    ///         CertSource::PemFile("/etc/internal/ca.pem".into()),
+    ///         CertSource::Pem(include_bytes!("../integration-tests/test-ca.pem").to_vec()),
+    ///     ])
+    ///     .load()?;
+    /// println!("{} roots", roots.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extra_anchors(mut self, sources: &[CertSource]) -> Self {
+        self.extra_anchors = sources.to_vec();
+        self
+    }
+
+    /// Loads roots with these options, executing callbacks on the provided
+    /// builder.  PEM files are read here, rather than by the builder, and
+    /// each of their roots given to it as DER.
+    pub fn build<B: RootStoreBuilder>(&self, builder: &mut B) -> Result<(), Error> {
+        let mut filter = Filter {
+            inner: builder,
+            expired_before: match self.include_expired {
+                true => None,
+                false => Some(der::format_time(self.now.unwrap_or_else(SystemTime::now))),
+            },
+            seen_keys: match self.dedup_by_spki {
+                true => Some(HashSet::new()),
+                false => None,
+            },
+            loaded_ders: match self.extra_anchors.is_empty() && !self.include_system_keychain {
+                true => None,
+                false => Some(HashSet::new()),
+            },
+            extra: false,
+            include_host_restricted: self.include_host_restricted,
+            host_restricted: false,
+            include_error_conditional: self.include_error_conditional,
+            error_conditional: false,
+            denied: HashSet::new(),
+            loaded: 0,
+        };
+
+        let native = crate::build_native_certs_in(self.purpose, &self.domains, &self.access, &mut filter);
+        filter.extra = true;
+        let keychain = match self.include_system_keychain {
+            true => crate::build_system_keychain_roots(&mut filter),
+            false => Ok(()),
+        };
+        let extra = self.extra_anchors.build_certs(self.purpose, &mut filter);
+
+        match native.and(keychain).and(extra) {
+            Err(_) if self.tolerate_errors && filter.loaded > 0 => Ok(()),
+            Ok(()) if self.require_roots && filter.loaded == 0 => {
+                Err(crate::error::no_certs_found(crate::describe_roots(self.purpose)))
+            }
+            result => result,
+        }
+    }
+
+    /// Loads roots with these options, returning their DER encodings.
+    pub fn load(&self) -> Result<Vec<Vec<u8>>, Error> {
+        let mut roots = Collect(Vec::new());
+        self.build(&mut roots)?;
+        Ok(roots.0)
+    }
+
+    /// Loads roots with these options, returning their DER encodings along
+    /// with what the load skipped, as
+    /// [skip_inaccessible_domains](#method.skip_inaccessible_domains) says,
+    /// and the error it failed with, if any, last.
+    pub fn load_partial(&self) -> CertificateResult {
+        let mut roots = Partial(Vec::new());
+        if let Err(err) = self.build(&mut roots) {
+            roots.0.push(Err(err));
+        }
+        roots.0.into_iter().collect()
+    }
+}
+
+/// A builder which keeps the DER encodings of the roots it is given, and
+/// the errors of what the load skipped, in the order it was given them.
+struct Partial(Vec<Result<Vec<u8>, Error>>);
+
+impl RootStoreBuilder for Partial {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.0.push(Ok(der));
+        Ok(())
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        self.0.extend(rustls_pemfile::certs(rd)?.into_iter().map(Ok));
+        Ok(())
+    }
+
+    fn skipped(&mut self, error: Error) {
+        self.0.push(Err(error));
+    }
+}
+
+/// A builder which leaves out expired roots, those with a key already
+/// seen, those trusted only for some hosts, and extra anchors already
+/// loaded or which the platform denies, and counts those it passes on to `inner`.  Roots from PEM are
+/// passed on one by one, so each is looked at.
+struct Filter<'a, B> {
+    inner: &'a mut B,
+    /// When expired roots are left out, the time before which they expired,
+    /// in RFC 3339 form.
+    expired_before: Option<String>,
+    /// When roots are deduplicated, the SubjectPublicKeyInfo of each passed
+    /// on.
+    seen_keys: Option<HashSet<Vec<u8>>>,
+    /// When there are extra anchors, the DER of each root passed on.
+    loaded_ders: Option<HashSet<Vec<u8>>>,
+    /// Whether the roots now given are extra anchors.
+    extra: bool,
+    /// Whether roots trusted only for some hosts are passed on.
+    include_host_restricted: bool,
+    /// Whether the roots now given are trusted only for some hosts.
+    host_restricted: bool,
+    /// Whether roots trusted only where an error is allowed are passed on.
+    include_error_conditional: bool,
+    /// Whether the roots now given are trusted only where an error is
+    /// allowed.
+    error_conditional: bool,
+    /// The certificates the platform denied, which extra anchors may not
+    /// trust again.
+    denied: HashSet<Vec<u8>>,
+    loaded: usize,
+}
+
+impl<B: RootStoreBuilder> Filter<'_, B> {
+    fn is_expired(&self, der: &[u8]) -> bool {
+        let not_after = der::cert_fields(der).and_then(|fields| fields.not_after());
+        match (&self.expired_before, not_after) {
+            // RFC 3339 times in UTC compare correctly as strings.
+            (Some(now), Some(not_after)) => not_after < *now,
+            _ => false,
+        }
+    }
+
+    /// Returns whether a root with the same key as `der` was loaded.
+    fn is_duplicate(&self, der: &[u8]) -> bool {
+        match (&self.seen_keys, der::cert_fields(der)) {
+            (Some(seen), Some(fields)) => seen.contains(fields.public_key),
+            _ => false,
+        }
+    }
+
+    /// Returns whether `der` is an extra anchor which was already passed on.
+    fn is_loaded(&self, der: &[u8]) -> bool {
+        match &self.loaded_ders {
+            Some(loaded) => self.extra && loaded.contains(der),
+            None => false,
+        }
+    }
+}
+
+impl<B: RootStoreBuilder> RootStoreBuilder for Filter<'_, B> {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        if self.host_restricted && !self.include_host_restricted {
+            debug!("skipped {}: trusted only for some hosts", der::describe(&der));
+            return Ok(());
+        }
+        if self.error_conditional && !self.include_error_conditional {
+            debug!("skipped {}: trusted only where an error is allowed", der::describe(&der));
+            return Ok(());
+        }
+        if self.is_expired(&der) {
+            debug!("skipped {}: expired", der::describe(&der));
+            return Ok(());
+        }
+        if self.is_duplicate(&der) {
+            debug!("skipped {}: its key is another root's", der::describe(&der));
+            return Ok(());
+        }
+        if self.is_loaded(&der) {
+            debug!("skipped {}: already loaded", der::describe(&der));
+            return Ok(());
+        }
+        if self.extra && self.denied.contains(&der) {
+            debug!("skipped {}: the platform denies it", der::describe(&der));
+            return Ok(());
+        }
+        if let Some(loaded) = &mut self.loaded_ders {
+            loaded.insert(der.clone());
+        }
+        // The key is recorded only once the builder has taken the root, so
+        // that a root left out or rejected leaves its key to another.
+        let key = match self.seen_keys {
+            Some(_) => der::cert_fields(&der).map(|fields| fields.public_key.to_vec()),
+            None => None,
+        };
+        self.inner.load_der(der)?;
+        if let (Some(seen), Some(key)) = (&mut self.seen_keys, key) {
+            seen.insert(key);
+        }
+        self.loaded += 1;
+        Ok(())
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        let mut first_error = None;
+        for der in rustls_pemfile::certs(rd)? {
+            if let Err(err) = self.load_der(der) {
+                first_error = first_error.or(Some(err));
+            }
+        }
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn set_provenance(&mut self, provenance: &Provenance, trust: PlatformTrust) {
+        self.host_restricted = false;
+        self.error_conditional = false;
+        self.inner.set_provenance(provenance, trust);
+    }
+
+    fn set_host_restrictions(&mut self, hosts: &[String]) {
+        self.host_restricted = !hosts.is_empty();
+        self.inner.set_host_restrictions(hosts);
+    }
+
+    fn set_allowed_errors(&mut self, errors: &[i32]) {
+        self.error_conditional = !errors.is_empty();
+        self.inner.set_allowed_errors(errors);
+    }
+
+    fn denied(&mut self, der: &[u8]) {
+        self.denied.insert(der.to_vec());
+        self.inner.denied(der);
+    }
+
+    fn skipped(&mut self, error: Error) {
+        self.inner.skipped(error);
+    }
+}
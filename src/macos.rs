@@ -1,14 +1,102 @@
+use security_framework::certificate::SecCertificate;
+use security_framework::identity::SecIdentity;
+use security_framework::item::{ItemClass, ItemSearchOptions, Limit, Reference, SearchResult};
+use security_framework::policy::SecPolicy;
+use security_framework::secure_transport::SslProtocolSide;
+use security_framework::trust::SecTrust;
 use security_framework::trust_settings::{
     Domain,
     TrustSettings,
     TrustSettingsForCertificate
 };
 use std::io::{Error, ErrorKind};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 
-use crate::RootStoreBuilder;
+use core_foundation::array::{CFArray, CFArrayRef};
+use core_foundation::base::{CFType, TCFType};
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::number::CFNumber;
+use core_foundation::string::{CFString, CFStringRef};
+use security_framework_sys::base::SecPolicyRef;
+use security_framework_sys::certificate::{
+    kSecPolicyAppleCodeSigning,
+    kSecPolicyAppleSMIME,
+    kSecPolicyAppleSSL,
+    kSecPolicyAppleTimeStamping,
+};
+use security_framework_sys::trust_settings::{
+    kSecTrustSettingsResultDeny,
+    kSecTrustSettingsResultTrustAsRoot,
+    kSecTrustSettingsResultTrustRoot,
+    SecTrustSettingsCopyTrustSettings,
+};
+
+use crate::error::platform_error;
+use crate::trace::SourceSpan;
+use crate::{ClientIdentity, IdentitySource, StoreAccess, PlatformTrust, Provenance, Purpose, RootStoreBuilder,
+            TrustDomain};
+
+#[cfg(feature = "rustls")]
+use std::sync::Arc;
+
+#[cfg(feature = "rustls")]
+use rustls::internal::msgs::enums::SignatureAlgorithm;
+#[cfg(feature = "rustls")]
+use rustls::sign::{Signer, SigningKey};
+#[cfg(feature = "rustls")]
+use rustls::{SignatureScheme, TLSError};
+#[cfg(feature = "rustls")]
+use security_framework::key::{Algorithm, SecKey};
+
+#[cfg(feature = "rustls")]
+use crate::der::KeyType;
+#[cfg(feature = "rustls")]
+use crate::sign;
+
+/// Every certificate with trust settings, mapped to the setting which
+/// applies to it.  The map is ordered, by DER, so that loads give roots in
+/// the same order every time.
+type Settings = BTreeMap<Vec<u8>, Setting>;
+
+/// The trust setting which applies to a certificate.
+struct Setting {
+    trusted: TrustSettingsForCertificate,
+    /// The domain which gave the setting.
+    domain: TrustDomain,
+    /// The hosts the setting restricts a trusted certificate to, by
+    /// `kSecTrustSettingsPolicyString`, or none if it trusts it for any.
+    hosts: Vec<String>,
+    /// The errors whose allowance, by `kSecTrustSettingsAllowedError`, the
+    /// setting makes a trusted certificate's trust depend on, or none if it
+    /// is unconditional.
+    allowed_errors: Vec<i32>,
+}
 
-pub fn build_native_certs<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Error> {
+/// The trust setting a certificate has for a purpose in one domain, with
+/// the hosts and allowed errors it depends on, as in a `Setting`.
+type PolicySetting = (TrustSettingsForCertificate, Vec<String>, Vec<i32>);
+
+/// The certificates with trust settings in one domain, with the setting
+/// each has there.
+type DomainSettings = Vec<(Vec<u8>, Setting)>;
+
+/// Returns every certificate with trust settings for `purpose`, mapped to
+/// the setting which applies to it.
+///
+/// A certificate whose settings cannot be read is mapped to `Invalid`, so
+/// that settings from a less specific domain cannot trust it in place of
+/// those, and the first such error is left in `first_error`.  If `access`
+/// skips inaccessible domains, a domain which cannot be read, or is not
+/// read within its timeout, is left out, and its error, as those of its
+/// certificates, added to `skipped` instead.  Each domain is read within
+/// its span in `spans`.
+fn trust_settings(purpose: Purpose,
+                  domains: &[TrustDomain],
+                  spans: &[SourceSpan],
+                  access: &StoreAccess,
+                  first_error: &mut Option<Error>,
+                  skipped: &mut Vec<Error>) -> Result<Settings, Error> {
     // The various domains are designed to interact like this:
     //
     // "Per-user Trust Settings override locally administered
@@ -20,48 +108,982 @@ pub fn build_native_certs<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Er
     // overwrite existing elements, which mean User settings
     // trump Admin trump System, as desired.
 
-    let mut all_certs = HashMap::new();
+    #[cfg(feature = "test-util")]
+    if let Some(test_store) = crate::test_store::installed() {
+        return Ok(test_store.trust_settings());
+    }
+
+    // Reading a domain means asking for the settings of each of its
+    // certificates in turn, which is slow with a large admin keychain, so
+    // each domain is read on its own thread, and the results merged in
+    // order of precedence.
+    let read = move |domain, span: SourceSpan| {
+        let _entered = span.enter();
+        let mut domain_error = None;
+        (domain_trust_settings(purpose, domain, &mut domain_error), domain_error)
+    };
+    let per_domain: Vec<DomainRead> = match (domains, access.timeout) {
+        (_, Some(timeout)) => read_within(domains, spans, timeout, read),
+        ([domain], None) => vec![read(*domain, spans[0].clone())],
+        (_, None) => std::thread::scope(|scope| {
+            let threads: Vec<_> = domains.iter()
+                .zip(spans)
+                .map(|(&domain, span)| {
+                    let span = span.clone();
+                    scope.spawn(move || read(domain, span))
+                })
+                .collect();
+            threads.into_iter()
+                .map(|thread| thread.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect()
+        }),
+    };
+
+    let mut all_certs = BTreeMap::new();
+    for ((&domain, span), (certs, domain_error)) in domains.iter().zip(spans).zip(per_domain) {
+        let _entered = span.enter();
+        let certs = match certs {
+            Ok(certs) => certs,
+            Err(err) if access.skip_inaccessible => {
+                debug!("skipped the {:?} trust settings: {}", domain, err);
+                skipped.push(err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        for (der, setting) in certs {
+            all_certs.entry(der).or_insert(setting);
+        }
+        match domain_error {
+            Some(err) if access.skip_inaccessible => skipped.push(err),
+            Some(err) if first_error.is_none() => *first_error = Some(err),
+            _ => {}
+        }
+    }
+
+    Ok(all_certs)
+}
+
+/// What reading a domain's trust settings gave: the settings, and the first
+/// error with a certificate's.
+type DomainRead = (Result<DomainSettings, Error>, Option<Error>);
+
+/// Reads each of `domains`, within its span in `spans`, with `read` on a
+/// thread of its own, giving up on those not read within `timeout`, as when
+/// the keychain waits on a prompt to unlock it.  The threads of those are
+/// left to finish on their own.
+fn read_within<F>(domains: &[TrustDomain], spans: &[SourceSpan], timeout: Duration, read: F) -> Vec<DomainRead>
+    where F: Fn(TrustDomain, SourceSpan) -> DomainRead + Copy + Send + 'static
+{
+    use std::sync::mpsc::{self, RecvTimeoutError};
+
+    let deadline = Instant::now() + timeout;
+    let pending: Vec<_> = domains.iter()
+        .zip(spans)
+        .map(|(&domain, span)| {
+            let (sender, receiver) = mpsc::channel();
+            let span = span.clone();
+            std::thread::spawn(move || sender.send(read(domain, span)));
+            (domain, receiver)
+        })
+        .collect();
+
+    pending.into_iter()
+        .map(|(domain, receiver)| match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(read) => read,
+            Err(RecvTimeoutError::Timeout) => {
+                let message = format!("the {:?} trust settings were not read within {:?}", domain, timeout);
+                (Err(Error::new(ErrorKind::TimedOut, message)), None)
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let message = format!("reading the {:?} trust settings panicked", domain);
+                (Err(Error::other(message)), None)
+            }
+        })
+        .collect()
+}
+
+/// Returns the certificates with trust settings in `trust_domain` for
+/// `purpose`, with the setting each has there, as `trust_settings` reads
+/// each domain.
+fn domain_trust_settings(purpose: Purpose,
+                         trust_domain: TrustDomain,
+                         first_error: &mut Option<Error>) -> Result<DomainSettings, Error> {
+    debug!("reading the {:?} trust settings for {:?}", trust_domain, purpose);
+    let domain = security_domain(trust_domain);
+    let ts = TrustSettings::new(domain);
+    let iter = ts.iter()
+        .map_err(platform_error)?;
+
+    let mut certs = Vec::new();
+    for cert in iter {
+        let der = cert.to_der();
+
+        // If there are no specific trust settings, the default
+        // is to trust the certificate as a root cert.  Weird API but OK.
+        // The docs say:
+        //
+        // "Note that an empty Trust Settings array means "always trust this cert,
+        //  with a resulting kSecTrustSettingsResult of kSecTrustSettingsResultTrustRoot".
+        let (trusted, hosts, allowed_errors) = match policy_trust_settings(domain, &cert, purpose) {
+            Ok(Some(setting)) => setting,
+            Ok(None) => continue,
+            Err(err) => {
+                debug!("skipped {}: its {:?} trust settings cannot be read: {}",
+                       crate::der::describe(&der), trust_domain, err);
+                first_error.get_or_insert(err);
+                (TrustSettingsForCertificate::Invalid, Vec::new(), Vec::new())
+            }
+        };
+
+        certs.push((der, Setting { trusted, domain: trust_domain, hosts, allowed_errors }));
+    }
+
+    Ok(certs)
+}
+
+/// Returns the TLS trust settings of every certificate, failing if any
+/// cannot be read: a certificate skipped here could be one the user
+/// distrusts.
+fn complete_trust_settings() -> Result<Settings, Error> {
+    let mut first_error = None;
+    let all_certs = trust_settings(Purpose::ServerAuth, &TrustDomain::ALL, &domain_spans(&TrustDomain::ALL),
+                                   &StoreAccess::default(), &mut first_error, &mut Vec::new())?;
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(all_certs),
+    }
+}
 
-    for domain in &[Domain::User, Domain::Admin, Domain::System] {
-        let ts = TrustSettings::new(*domain);
-        let iter = ts.iter()
-            .map_err(|err| Error::new(ErrorKind::Other, err))?;
+/// Opens the span of each of `domains`, in which what is read from it, and
+/// what a load skips or denies of it, is reported.
+fn domain_spans(domains: &[TrustDomain]) -> Vec<SourceSpan> {
+    domains.iter()
+        .map(|domain| source_span!(trust_domain = domain))
+        .collect()
+}
+
+fn security_domain(domain: TrustDomain) -> Domain {
+    match domain {
+        TrustDomain::User => Domain::User,
+        TrustDomain::Admin => Domain::Admin,
+        TrustDomain::System => Domain::System,
+    }
+}
+
+/// Returns the name trust settings give the policy for `purpose`.
+fn policy_name(purpose: Purpose) -> &'static str {
+    match purpose {
+        Purpose::ServerAuth => "sslServer",
+        Purpose::EmailProtection => "SMIME",
+        Purpose::CodeSigning => "CodeSigning",
+        Purpose::TimeStamping => "TimeStamping",
+    }
+}
+
+/// Returns the OID of the Security framework's policy for `purpose`, as
+/// the `kSecPolicyOid` property of a `SecPolicy` gives it.
+fn policy_oid(purpose: Purpose) -> CFString {
+    // Safety: these are constant strings the framework exports.
+    unsafe {
+        CFString::wrap_under_get_rule(match purpose {
+            Purpose::ServerAuth => kSecPolicyAppleSSL,
+            Purpose::EmailProtection => kSecPolicyAppleSMIME,
+            Purpose::CodeSigning => kSecPolicyAppleCodeSigning,
+            Purpose::TimeStamping => kSecPolicyAppleTimeStamping,
+        })
+    }
+}
+
+#[link(name = "Security", kind = "framework")]
+extern "C" {
+    static kSecPolicyOid: CFStringRef;
+
+    fn SecPolicyCopyProperties(policy: SecPolicyRef) -> CFDictionaryRef;
+
+    fn SecTrustedApplicationCopyData(application: *const std::ffi::c_void,
+                                     data: *mut core_foundation::data::CFDataRef) -> i32;
+}
+
+/// Returns whether a usage constraint in trust settings applies to
+/// `purpose`.
+///
+/// The constraint's `kSecTrustSettingsPolicy` says which policy it is for;
+/// settings written by older tools name it in `kSecTrustSettingsPolicyName`
+/// instead.  A constraint with neither applies to every policy.
+///
+/// A constraint with a `kSecTrustSettingsApplication` applies only to that
+/// application, so it is left out unless that is this process's executable:
+/// a root trusted only for another application is not a root for this one.
+fn applies_to(entry: &CFDictionary, purpose: Purpose) -> bool {
+    let policy_key = CFString::from_static_string("kSecTrustSettingsPolicy");
+    let policy_name_key = CFString::from_static_string("kSecTrustSettingsPolicyName");
+    let application_key = CFString::from_static_string("kSecTrustSettingsApplication");
+
+    if let Some(application) = entry.find(application_key.as_CFTypeRef().cast()) {
+        if !is_current_application(*application) {
+            return false;
+        }
+    }
+
+    if let Some(policy) = entry.find(policy_key.as_CFTypeRef().cast()) {
+        // Safety: this key holds a policy, whose properties are ours to
+        // release, and whose OID property is a string.
+        let oid = unsafe {
+            let properties = SecPolicyCopyProperties((*policy).cast_mut().cast());
+            if properties.is_null() {
+                return false;
+            }
+            let properties = CFDictionary::<CFString, CFType>::wrap_under_create_rule(properties);
+            properties.find(CFString::wrap_under_get_rule(kSecPolicyOid))
+                .and_then(|oid| oid.downcast::<CFString>())
+        };
+        return oid.is_some_and(|oid| oid == policy_oid(purpose));
+    }
+
+    // Safety: this key holds a string.
+    let name = entry.find(policy_name_key.as_CFTypeRef().cast())
+        .map(|name| unsafe { CFString::wrap_under_get_rule((*name).cast()) });
+    match name {
+        Some(name) => name == CFString::new(policy_name(purpose)),
+        None => true,
+    }
+}
+
+/// Returns whether `application`, a `SecTrustedApplicationRef`, is this
+/// process's executable.  One whose path cannot be had is not.
+fn is_current_application(application: *const std::ffi::c_void) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    // Safety: `application` is a live trusted application, and on success
+    // the data, its path, is ours to release.
+    let path = unsafe {
+        let mut data = std::ptr::null();
+        if SecTrustedApplicationCopyData(application, &mut data) != 0 || data.is_null() {
+            return false;
+        }
+        core_foundation::data::CFData::wrap_under_create_rule(data)
+    };
+    let path = path.bytes();
+    let path = path.strip_suffix(&[0]).unwrap_or(path);
+
+    std::env::current_exe()
+        .and_then(std::fs::canonicalize)
+        .is_ok_and(|exe| exe.as_os_str().as_bytes() == path)
+}
+
+/// Returns the trust setting `domain` gives `cert` for `purpose`, from the
+/// first of its usage constraints which applies to the purpose's policy
+/// and gives a result, with the hosts it is restricted to.
+///
+/// Settings which only constrain other policies give `None`, so a root
+/// trusted only for, say, S/MIME is not trusted for TLS.  So do those whose
+/// results for the purpose are all `kSecTrustSettingsResultUnspecified`,
+/// which leaves the certificate out of the domain's settings, and its trust
+/// to a less specific domain, as macOS resolves it.  Only an empty list of
+/// settings trusts a certificate for everything.
+///
+/// A constraint with a `kSecTrustSettingsPolicyString`, such as a hostname
+/// for TLS, applies only to that host.  Where such constraints trust the
+/// certificate, and none without one gives a result, it is trusted, but
+/// only for their hosts.
+///
+/// A constraint with a `kSecTrustSettingsAllowedError` trusts only where
+/// evaluation meets that error, which it allows.  Where such constraints
+/// trust the certificate, and none without one gives a result, it is
+/// trusted, but with the errors they allow recorded, for the loader, and
+/// verifiers, to decide on.  A denial is a denial whatever it allows.
+fn policy_trust_settings(domain: Domain,
+                         cert: &SecCertificate,
+                         purpose: Purpose) -> Result<Option<PolicySetting>, Error> {
+    // Safety: `cert` is a live certificate, and on success the array is
+    // ours to release.
+    let settings = unsafe {
+        let mut array: CFArrayRef = std::ptr::null();
+        let status = SecTrustSettingsCopyTrustSettings(cert.as_concrete_TypeRef(), domain.into(), &mut array);
+        if status != 0 {
+            return Err(platform_error(security_framework::base::Error::from_code(status)));
+        }
+        CFArray::<CFDictionary>::wrap_under_create_rule(array)
+    };
+    if settings.is_empty() {
+        return Ok(Some((TrustSettingsForCertificate::TrustRoot, Vec::new(), Vec::new())));
+    }
+
+    let result_key = CFString::from_static_string("kSecTrustSettingsResult");
+    let policy_string_key = CFString::from_static_string("kSecTrustSettingsPolicyString");
+    let allowed_error_key = CFString::from_static_string("kSecTrustSettingsAllowedError");
+    let mut host_trust = None;
+    let mut hosts = Vec::new();
+    let mut conditional_trust = None;
+    let mut allowed_errors = Vec::new();
+
+    for entry in settings.iter() {
+        if !applies_to(&entry, purpose) {
+            continue;
+        }
 
-        for cert in iter {
-            let der = cert.to_der();
+        // Safety: this key holds a number.
+        let result = entry.find(result_key.as_CFTypeRef().cast())
+            .map(|num| unsafe { CFNumber::wrap_under_get_rule((*num).cast()) })
+            .and_then(|num| num.to_i64())
+            .unwrap_or_else(|| i64::from(kSecTrustSettingsResultTrustRoot));
+        let trusted = match result {
+            r if r == i64::from(kSecTrustSettingsResultTrustRoot) => TrustSettingsForCertificate::TrustRoot,
+            r if r == i64::from(kSecTrustSettingsResultTrustAsRoot) => TrustSettingsForCertificate::TrustAsRoot,
+            r if r == i64::from(kSecTrustSettingsResultDeny) => TrustSettingsForCertificate::Deny,
+            _ => continue, // invalid or unspecified: left to later constraints and domains
+        };
 
-            // If there are no specific trust settings, the default
-            // is to trust the certificate as a root cert.  Weird API but OK.
-            // The docs say:
-            //
-            // "Note that an empty Trust Settings array means "always trust this cert,
-            //  with a resulting kSecTrustSettingsResult of kSecTrustSettingsResultTrustRoot".
-            let trusted = ts.tls_trust_settings_for_certificate(&cert)
-                .map_err(|err| Error::new(ErrorKind::Other, err))?
-                .unwrap_or(TrustSettingsForCertificate::TrustRoot);
+        // Safety: this key holds a number.
+        let allowed_error = entry.find(allowed_error_key.as_CFTypeRef().cast())
+            .map(|num| unsafe { CFNumber::wrap_under_get_rule((*num).cast()) })
+            .and_then(|num| num.to_i32());
+        match allowed_error {
+            Some(_) if matches!(trusted, TrustSettingsForCertificate::Deny) => {}
+            Some(allowed_error) => {
+                conditional_trust.get_or_insert(trusted);
+                if !allowed_errors.contains(&allowed_error) {
+                    allowed_errors.push(allowed_error);
+                }
+                continue;
+            }
+            None => {}
+        }
 
-            all_certs.entry(der)
-                .or_insert(trusted);
+        // Safety: this key holds a string.
+        let host = entry.find(policy_string_key.as_CFTypeRef().cast())
+            .map(|host| unsafe { CFString::wrap_under_get_rule((*host).cast()) });
+        match host {
+            // A host denied is not trusted for it, whatever follows.
+            Some(_) if matches!(trusted, TrustSettingsForCertificate::Deny) => {}
+            Some(host) => {
+                host_trust.get_or_insert(trusted);
+                hosts.push(host.to_string());
+            }
+            None if matches!(trusted, TrustSettingsForCertificate::Deny) && !hosts.is_empty() => {
+                // Trusted for the hosts before, and denied for the rest.
+                break;
+            }
+            None => return Ok(Some((trusted, Vec::new(), Vec::new()))),
         }
     }
+    Ok(match (host_trust, conditional_trust) {
+        (Some(trusted), _) => Some((trusted, hosts, allowed_errors)),
+        (None, Some(trusted)) => Some((trusted, Vec::new(), allowed_errors)),
+        (None, None) => None,
+    })
+}
 
+/// Loads the roots `domains` trust for `purpose`, where settings in earlier
+/// domains win.  This stands in for the `build_native_certs` of the other
+/// backends, which have no domains.
+pub fn build_native_certs_in<B: RootStoreBuilder>(purpose: Purpose,
+                                                  domains: &[TrustDomain],
+                                                  builder: &mut B) -> Result<(), Error> {
+    build_native_certs_with(purpose, domains, &StoreAccess::default(), builder)
+}
+
+/// Loads the roots `domains` trust for `purpose`, reading the keychains as
+/// `access` says.  What a load which skips inaccessible domains skips is
+/// given to the builder's `skipped`.
+pub fn build_native_certs_with<B: RootStoreBuilder>(purpose: Purpose,
+                                                    domains: &[TrustDomain],
+                                                    access: &StoreAccess,
+                                                    builder: &mut B) -> Result<(), Error> {
     let mut first_error = None;
+    let mut skipped = Vec::new();
+    let spans = domain_spans(domains);
+    let all_certs = trust_settings(purpose, domains, &spans, access, &mut first_error, &mut skipped);
+    for err in skipped {
+        builder.skipped(err);
+    }
+    let all_certs = all_certs?;
+
+    if all_certs.is_empty() && first_error.is_none() && domains.contains(&TrustDomain::System)
+        && !test_store_installed() {
+        debug!("no trust settings found for {:?}; falling back to the system anchor certificates", purpose);
+        return build_anchor_certificates(purpose, builder);
+    }
 
     // Now we have all the certificates and an idea of whether
     // to use them.
-    for (der, trusted) in all_certs.drain() {
-        match trusted {
-            TrustSettingsForCertificate::TrustRoot |
-                TrustSettingsForCertificate::TrustAsRoot => {
-                match builder.load_der(der) {
-                    Err(err) => {
-                        first_error = first_error
-                            .or_else(|| Some(Error::new(ErrorKind::InvalidData, err)));
+    for (der, Setting { trusted, domain, hosts, allowed_errors }) in all_certs {
+        let _entered = domains.iter()
+            .zip(&spans)
+            .find(|&(&read, _)| read == domain)
+            .map(|(_, span)| span.enter());
+        let trust = match trusted {
+            TrustSettingsForCertificate::TrustRoot => PlatformTrust::TrustRoot,
+            TrustSettingsForCertificate::TrustAsRoot => PlatformTrust::TrustAsRoot,
+            TrustSettingsForCertificate::Deny => {
+                debug!("skipped {}: its {:?} trust settings deny it for {:?}",
+                       crate::der::describe(&der), domain, purpose);
+                builder.set_provenance(&Provenance::TrustSettings(domain), PlatformTrust::Deny);
+                builder.denied(&der);
+                continue;
+            }
+            _ => {
+                debug!("skipped {}: its {:?} trust settings give {:?} for {:?}",
+                       crate::der::describe(&der), domain, trusted, purpose);
+                continue; // discard
+            }
+        };
+        if domain == TrustDomain::System && trust == PlatformTrust::TrustRoot {
+            if let Some(err) = evaluation_rejects(&der, purpose) {
+                debug!("skipped {}: the system's trust evaluation rejects it for {:?}: {}",
+                       crate::der::describe(&der), purpose, err);
+                builder.set_provenance(&Provenance::TrustSettings(domain), PlatformTrust::Deny);
+                builder.denied(&der);
+                continue;
+            }
+        }
+        builder.set_provenance(&Provenance::TrustSettings(domain), trust);
+        if !hosts.is_empty() {
+            builder.set_host_restrictions(&hosts);
+        }
+        if !allowed_errors.is_empty() {
+            builder.set_allowed_errors(&allowed_errors);
+        }
+        if let Err(err) = builder.load_der(der) {
+            first_error = first_error
+                .or_else(|| Some(Error::new(ErrorKind::InvalidData, err)));
+        }
+    }
+
+    if let Some(err) = first_error {
+        Err(err)
+    } else {
+        Ok(())
+    }
+}
+
+/// Loads the system's anchor certificates alone, which reads no keychain of
+/// the user's, and so never prompts, nor fails under the App Sandbox.  While
+/// a [TestStore](struct.TestStore.html) is installed, its roots are loaded
+/// instead.
+pub fn build_sandbox_safe<B: RootStoreBuilder>(purpose: Purpose, builder: &mut B) -> Result<(), Error> {
+    if test_store_installed() {
+        return build_native_certs_in(purpose, &TrustDomain::ALL, builder);
+    }
+    build_anchor_certificates(purpose, builder)
+}
+
+/// Loads the system's anchor certificates, for sandbox-safe loads, and for
+/// machines whose trust settings enumerate nothing, as with a locked
+/// keychain or some MDM configurations.  The anchors are the roots Apple ships, trusted for
+/// every purpose unless a trust setting says otherwise.
+fn build_anchor_certificates<B: RootStoreBuilder>(purpose: Purpose, builder: &mut B) -> Result<(), Error> {
+    let span = source_span!(anchors = "system");
+    let _entered = span.enter();
+    debug!("loading the system anchor certificates for {:?}", purpose);
+    let anchors = SecTrust::copy_anchor_certificates()
+        .map_err(platform_error)?;
+
+    let mut first_error = None;
+    for cert in anchors {
+        let der = cert.to_der();
+        if let Some(err) = evaluation_rejects(&der, purpose) {
+            debug!("skipped {}: the system's trust evaluation rejects it for {:?}: {}",
+                   crate::der::describe(&der), purpose, err);
+            builder.set_provenance(&Provenance::TrustSettings(TrustDomain::System), PlatformTrust::Deny);
+            builder.denied(&der);
+            continue;
+        }
+        builder.set_provenance(&Provenance::TrustSettings(TrustDomain::System), PlatformTrust::TrustRoot);
+        if let Err(err) = builder.load_der(der) {
+            first_error = first_error
+                .or_else(|| Some(Error::new(ErrorKind::InvalidData, err)));
+        }
+    }
+
+    if let Some(err) = first_error {
+        Err(err)
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns why the system's trust evaluation rejects the root `der` for
+/// `purpose`, evaluated alone, or `None` if it accepts it.
+///
+/// Apple withdraws trust from some of the roots it ships, as listed in
+/// HT212865, without removing them from the system's trust settings: only
+/// evaluation knows, so it is asked rather than keeping a list which would
+/// go stale.  Expiry is left to the loader, so an expired root is not
+/// evaluated, as it would always be rejected; nor is a root which cannot be
+/// parsed, which the builder rejects in its turn.
+fn evaluation_rejects(der: &[u8], purpose: Purpose) -> Option<String> {
+    let not_after = crate::der::cert_fields(der)?.not_after()?;
+    if not_after < crate::der::format_time(std::time::SystemTime::now()) {
+        return None;
+    }
+    let cert = SecCertificate::from_der(der).ok()?;
+
+    let policy = match purpose {
+        Purpose::ServerAuth => SecPolicy::create_ssl(SslProtocolSide::SERVER, None),
+        _ => SecPolicy::create_x509(),
+    };
+    let mut trust = match SecTrust::create_with_certificates(&[cert], &[policy]) {
+        Ok(trust) => trust,
+        Err(err) => return Some(err.to_string()),
+    };
+    // Revocation is not what is asked, and a load should not wait on the
+    // network.
+    if let Err(err) = trust.set_network_fetch_allowed(false) {
+        return Some(err.to_string());
+    }
+    trust.evaluate_with_error().err().map(|err| err.to_string())
+}
+
+/// Returns whether a [TestStore](struct.TestStore.html) is installed, in
+/// place of whose trust settings the system's anchors are never loaded.
+fn test_store_installed() -> bool {
+    #[cfg(feature = "test-util")]
+    if crate::test_store::installed().is_some() {
+        return true;
+    }
+    false
+}
+
+/// Describes where the roots for `purpose` are read from.
+pub fn describe_roots(purpose: Purpose) -> String {
+    format!("trust settings in the user, admin and system domains, for {:?}, \
+             or the system anchor certificates where no domain has any", purpose)
+}
+
+/// Describes where `list` is read from.
+#[cfg(feature = "cli")]
+pub fn describe_list(list: crate::List) -> String {
+    match list {
+        crate::List::Intermediates => "keychain search list, and trust settings which do not make roots",
+        crate::List::Distrusted => "trust settings which deny",
+        crate::List::Crls => "nowhere: the keychain has no public API for CRLs",
+    }.to_owned()
+}
+
+/// This backend reads no environment variables.
+#[cfg(feature = "cli")]
+pub const ENVIRONMENT: &[&str] = &[];
+
+/// What to do when no roots are found.
+#[cfg(feature = "cli")]
+pub const REMEDY: &str = "\
+Check the trust settings with `security dump-trust-settings -s` for the
+system domain, and `-d` for the admin domain, where MDM profiles install
+roots.  A root added to a keychain by hand is not trusted until it is
+marked so in Keychain Access.";
+
+/// Probes the trust settings of each domain.
+#[cfg(feature = "cli")]
+pub fn probe_locations() -> Vec<crate::Probe> {
+    [(Domain::User, "user"), (Domain::Admin, "admin"), (Domain::System, "system")]
+        .iter()
+        .map(|&(domain, name)| crate::Probe {
+            location: format!("{} domain trust settings", name),
+            found: TrustSettings::new(domain).iter().map(|iter| iter.count()).map_err(platform_error),
+            used_for: Purpose::ALL.to_vec(),
+        })
+        .collect()
+}
+
+/// Watches the keychains and trust settings, until dropped.
+#[cfg(feature = "watch")]
+pub struct Watcher {
+    _files: Option<crate::fs_watch::Watcher>,
+    _keychain: KeychainSubscription,
+}
+
+/// Calls `on_change` whenever a keychain item is added, deleted or
+/// updated, the keychain search list changes, or trust settings change, as
+/// the keychain reports through `SecKeychainAddCallback`; and whenever the
+/// directories holding the user's and the system's keychains, or the admin
+/// trust settings, are modified, for changes made behind its back.
+///
+/// While a [TestStore](struct.TestStore.html) is installed, its keychain is
+/// not watched, so this fails.
+#[cfg(feature = "watch")]
+pub fn watch(on_change: impl FnMut() + Send + 'static) -> Result<Watcher, Error> {
+    #[cfg(feature = "test-util")]
+    if crate::test_store::installed().is_some() {
+        return Err(Error::new(ErrorKind::Unsupported,
+                              "a test store's keychain is not watched for changes"));
+    }
+
+    // Both the keychain's thread and the file watcher's call it.
+    let on_change = std::sync::Arc::new(std::sync::Mutex::new(on_change));
+    let keychain = KeychainSubscription::new(on_change.clone())?;
+
+    let mut dirs = vec![
+        std::path::PathBuf::from("/Library/Keychains"),
+        std::path::PathBuf::from("/Library/Security/Trust Settings"),
+    ];
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(std::path::Path::new(&home).join("Library/Keychains"));
+    }
+
+    let files = crate::fs_watch::watch_dirs(dirs.iter().map(|dir| dir.as_path()), move || {
+        (on_change.lock().unwrap_or_else(|err| err.into_inner()))();
+    });
+    let files = match files {
+        Ok(files) => Some(files),
+        Err(err) => {
+            debug!("watching only keychain events: the keychain directories cannot be watched: {}", err);
+            None
+        }
+    };
+
+    Ok(Watcher { _files: files, _keychain: keychain })
+}
+
+/// The keychain events a watch is told of: `kSecAddEvent`,
+/// `kSecDeleteEvent`, `kSecUpdateEvent`, `kSecKeychainListChangedEvent` and
+/// `kSecTrustSettingsChangedEvent`, as masks.
+#[cfg(feature = "watch")]
+const KEYCHAIN_EVENTS: u32 = 1 << 3 | 1 << 4 | 1 << 5 | 1 << 11 | 1 << 12;
+
+#[cfg(feature = "watch")]
+type KeychainCallback = extern "C" fn(event: u32, info: *mut std::ffi::c_void,
+                                      context: *mut std::ffi::c_void) -> i32;
+
+#[cfg(feature = "watch")]
+extern "C" {
+    fn SecKeychainAddCallback(callback: KeychainCallback, mask: u32, context: *mut std::ffi::c_void) -> i32;
+}
+
+/// A live watch's callback.
+#[cfg(feature = "watch")]
+type KeychainSubscriber = std::sync::Arc<std::sync::Mutex<dyn FnMut() + Send>>;
+
+/// The callbacks of the live watches, by the number each was given.
+#[cfg(feature = "watch")]
+static KEYCHAIN_SUBSCRIBERS: std::sync::Mutex<BTreeMap<u64, KeychainSubscriber>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
+/// A watch's place among those the keychain's callback calls, until
+/// dropped.
+///
+/// The keychain identifies a callback only by its function, so one is
+/// registered for the whole process, the first time a watch starts, and
+/// stays registered; it calls whichever watches are live.
+#[cfg(feature = "watch")]
+struct KeychainSubscription(u64);
+
+#[cfg(feature = "watch")]
+impl KeychainSubscription {
+    fn new(on_change: KeychainSubscriber) -> Result<Self, Error> {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::OnceLock;
+
+        // The keychain delivers events through the run loop of the thread
+        // which registered for them, so that thread runs one for good.
+        static REGISTERED: OnceLock<i32> = OnceLock::new();
+        let status = *REGISTERED.get_or_init(|| {
+            let (tx, rx) = std::sync::mpsc::channel();
+            let spawned = std::thread::Builder::new()
+                .name("rustls-native-certs keychain events".to_owned())
+                .spawn(move || {
+                    let status = unsafe {
+                        SecKeychainAddCallback(keychain_event, KEYCHAIN_EVENTS, std::ptr::null_mut())
+                    };
+                    let _ = tx.send(status);
+                    if status == 0 {
+                        core_foundation::runloop::CFRunLoop::run_current();
                     }
-                    _ => {}
-                };
-            },
-            _ => {} // discard
+                });
+            match spawned {
+                Ok(_) => rx.recv().unwrap_or(security_framework_sys::base::errSecInternalComponent),
+                Err(_) => security_framework_sys::base::errSecInternalComponent,
+            }
+        });
+        if status != 0 {
+            return Err(platform_error(security_framework::base::Error::from_code(status)));
+        }
+
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        KEYCHAIN_SUBSCRIBERS.lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(id, on_change);
+        Ok(KeychainSubscription(id))
+    }
+}
+
+#[cfg(feature = "watch")]
+impl Drop for KeychainSubscription {
+    fn drop(&mut self) {
+        KEYCHAIN_SUBSCRIBERS.lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(&self.0);
+    }
+}
+
+/// Calls every live watch's callback, for any event.  A callback which
+/// panics does not unwind into the keychain.
+#[cfg(feature = "watch")]
+extern "C" fn keychain_event(event: u32, _info: *mut std::ffi::c_void, _context: *mut std::ffi::c_void) -> i32 {
+    debug!("keychain event {}", event);
+    let subscribers: Vec<_> = KEYCHAIN_SUBSCRIBERS.lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .values()
+        .cloned()
+        .collect();
+    for on_change in subscribers {
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            (on_change.lock().unwrap_or_else(|err| err.into_inner()))();
+        }));
+    }
+    0
+}
+
+pub fn load_intermediates() -> Result<Vec<Vec<u8>>, Error> {
+    let mut certs = Vec::new();
+    let mut denied = Vec::new();
+
+    for (der, setting) in complete_trust_settings()? {
+        match setting.trusted {
+            TrustSettingsForCertificate::Deny => denied.push(der),
+            TrustSettingsForCertificate::TrustRoot |
+                TrustSettingsForCertificate::TrustAsRoot => {}
+            _ => certs.push(der),
+        }
+    }
+
+    let mut options = ItemSearchOptions::new();
+    options.class(ItemClass::certificate())
+        .load_refs(true)
+        .limit(Limit::All);
+    search_keychains(&mut options);
+    match options.search() {
+        Ok(results) => {
+            for result in results {
+                if let SearchResult::Ref(Reference::Certificate(cert)) = result {
+                    certs.push(cert.to_der());
+                }
+            }
+        }
+        Err(err) if err.code() == ERR_SEC_ITEM_NOT_FOUND => {}
+        Err(err) => return Err(platform_error(err)),
+    }
+
+    certs.retain(|der| !denied.contains(der));
+    Ok(certs)
+}
+
+/// Certificates the user or an administrator has set to be denied are
+/// found, and roots in the system's trust settings which Apple has withdrawn
+/// trust from for TLS, as the system's trust evaluation rejects them.
+pub fn load_distrusted() -> Result<Vec<Vec<u8>>, Error> {
+    Ok(complete_trust_settings()?
+        .into_iter()
+        .filter(|(der, setting)| match setting.trusted {
+            TrustSettingsForCertificate::Deny => true,
+            TrustSettingsForCertificate::TrustRoot => {
+                setting.domain == TrustDomain::System
+                    && evaluation_rejects(der, Purpose::ServerAuth).is_some()
+            }
+            _ => false,
+        })
+        .map(|(der, _)| der)
+        .collect())
+}
+
+/// The keychain has no public API for CRLs.
+pub fn load_crls() -> Result<Vec<Vec<u8>>, Error> {
+    Ok(Vec::new())
+}
+
+/// A keychain identity, through which its private key is found.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "rustls"), allow(dead_code))]
+pub struct NativeKey(SecIdentity);
+
+#[cfg(feature = "rustls")]
+pub fn signing_key(key: &NativeKey, key_type: KeyType) -> Result<Box<dyn SigningKey>, Error> {
+    let identity = key.0.clone();
+    Ok(Box::new(SecKeySigningKey {
+        key: sign::LazyKey::new(move || identity.private_key().map_err(platform_error)),
+        key_type,
+    }))
+}
+
+/// A `rustls::sign::SigningKey` which signs using `SecKeyCreateSignature`,
+/// so the key never leaves the keychain or Secure Enclave.
+#[cfg(feature = "rustls")]
+struct SecKeySigningKey {
+    key: Arc<sign::LazyKey<SecKey>>,
+    key_type: KeyType,
+}
+
+#[cfg(feature = "rustls")]
+impl SigningKey for SecKeySigningKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        sign::choose_scheme(self.key_type, offered)
+            .map(|scheme| Box::new(SecKeySigner { key: self.key.clone(), scheme }) as Box<dyn Signer>)
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        sign::algorithm(self.key_type)
+    }
+}
+
+#[cfg(feature = "rustls")]
+struct SecKeySigner {
+    key: Arc<sign::LazyKey<SecKey>>,
+    scheme: SignatureScheme,
+}
+
+#[cfg(feature = "rustls")]
+impl Signer for SecKeySigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, TLSError> {
+        // The "Message" algorithms hash the input themselves, and produce
+        // ECDSA signatures in the DER encoding TLS uses.
+        let algorithm = match self.scheme {
+            SignatureScheme::RSA_PSS_SHA256 => Algorithm::RSASignatureMessagePSSSHA256,
+            SignatureScheme::RSA_PSS_SHA384 => Algorithm::RSASignatureMessagePSSSHA384,
+            SignatureScheme::RSA_PSS_SHA512 => Algorithm::RSASignatureMessagePSSSHA512,
+            SignatureScheme::RSA_PKCS1_SHA256 => Algorithm::RSASignatureMessagePKCS1v15SHA256,
+            SignatureScheme::RSA_PKCS1_SHA384 => Algorithm::RSASignatureMessagePKCS1v15SHA384,
+            SignatureScheme::RSA_PKCS1_SHA512 => Algorithm::RSASignatureMessagePKCS1v15SHA512,
+            SignatureScheme::ECDSA_NISTP256_SHA256 => Algorithm::ECDSASignatureMessageX962SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384 => Algorithm::ECDSASignatureMessageX962SHA384,
+            _ => return Err(TLSError::General("unsupported signature scheme".into())),
+        };
+
+        self.key.get()?
+            .create_signature(algorithm, message)
+            .map_err(|err| TLSError::General(format!("SecKeyCreateSignature failed: {}", err)))
+    }
+
+    fn get_scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
+
+pub fn export_pkcs12(key: &NativeKey, chain: &[Vec<u8>], password: &str) -> Result<Vec<u8>, Error> {
+    use core_foundation::base::CFType;
+    use core_foundation::data::{CFData, CFDataRef};
+    use security_framework_sys::import_export::{
+        kSecFormatPKCS12,
+        SecItemExport,
+        SecItemImportExportKeyParameters,
+        SEC_KEY_IMPORT_EXPORT_PARAMS_VERSION,
+    };
+
+    let mut items: Vec<CFType> = vec![key.0.as_CFType()];
+    for der in chain.iter().skip(1) {
+        items.push(SecCertificate::from_der(der).map_err(platform_error)?.as_CFType());
+    }
+    let items = CFArray::from_CFTypes(&items);
+    let passphrase = CFString::new(password);
+    let params = SecItemImportExportKeyParameters {
+        version: SEC_KEY_IMPORT_EXPORT_PARAMS_VERSION,
+        flags: 0,
+        passphrase: passphrase.as_CFTypeRef(),
+        alertTitle: std::ptr::null(),
+        alertPrompt: std::ptr::null(),
+        accessRef: std::ptr::null_mut(),
+        keyUsage: std::ptr::null(),
+        keyAttributes: std::ptr::null(),
+    };
+
+    // Safety: `items` and `params` outlive the call, and on success the
+    // data is ours to release.
+    unsafe {
+        let mut data: CFDataRef = std::ptr::null();
+        let status = SecItemExport(items.as_CFTypeRef(), kSecFormatPKCS12, 0, &params, &mut data);
+        if status != 0 {
+            return Err(platform_error(security_framework::base::Error::from_code(status)));
+        }
+        Ok(CFData::wrap_under_create_rule(data).bytes().to_vec())
+    }
+}
+
+/// Loads every certificate in the keychain file at `path`, which holds no
+/// trust settings of its own, as roots for every purpose.
+pub fn build_keychain_certs(path: &std::path::Path, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+    let span = source_span!(keychain = path);
+    let _entered = span.enter();
+    let certs = keychain_certificates(path)?;
+    builder.set_provenance(&Provenance::File(path.to_owned()), PlatformTrust::InStore);
+    load_ders(certs, builder)
+}
+
+/// The keychain installers add machine-wide certificates to.
+const SYSTEM_KEYCHAIN: &str = "/Library/Keychains/System.keychain";
+
+/// Loads the self-signed certificates in the System keychain which have no
+/// trust settings in any domain, as installers sometimes leave them.  Those
+/// with settings are left to the trust settings, which may not trust them.
+///
+/// While a [TestStore](struct.TestStore.html) is installed, nothing is
+/// loaded.
+pub fn build_system_keychain_roots<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Error> {
+    if test_store_installed() {
+        return Ok(());
+    }
+
+    let path = std::path::Path::new(SYSTEM_KEYCHAIN);
+    let span = source_span!(keychain = path);
+    let _entered = span.enter();
+    let mut with_settings = std::collections::HashSet::new();
+    for &trust_domain in &TrustDomain::ALL {
+        let iter = TrustSettings::new(security_domain(trust_domain)).iter()
+            .map_err(platform_error)?;
+        with_settings.extend(iter.map(|cert| cert.to_der()));
+    }
+
+    let certs = keychain_certificates(path)?
+        .into_iter()
+        .filter(|der| {
+            crate::der::cert_fields(der).is_some_and(|fields| fields.is_self_issued())
+                && !with_settings.contains(der)
+        })
+        .collect();
+
+    debug!("loading the self-signed certificates without trust settings in {}", SYSTEM_KEYCHAIN);
+    builder.set_provenance(&Provenance::File(path.to_owned()), PlatformTrust::InStore);
+    load_ders(certs, builder)
+}
+
+/// Returns the DER encoding of every certificate in the keychain file at
+/// `path`.
+fn keychain_certificates(path: &std::path::Path) -> Result<Vec<Vec<u8>>, Error> {
+    use security_framework::os::macos::item::ItemSearchOptionsExt;
+    use security_framework::os::macos::keychain::SecKeychain;
+
+    // Opening a keychain which does not exist succeeds, and the search
+    // then fails, so look for the file first.
+    std::fs::metadata(path)?;
+    let keychain = SecKeychain::open(path).map_err(platform_error)?;
+
+    let mut options = ItemSearchOptions::new();
+    options.class(ItemClass::certificate())
+        .load_refs(true)
+        .limit(Limit::All)
+        .keychains(std::slice::from_ref(&keychain));
+    let results = match options.search() {
+        Ok(results) => results,
+        Err(err) if err.code() == ERR_SEC_ITEM_NOT_FOUND => return Ok(Vec::new()),
+        Err(err) => return Err(platform_error(err)),
+    };
+
+    Ok(results.into_iter()
+        .filter_map(|result| match result {
+            SearchResult::Ref(Reference::Certificate(cert)) => Some(cert.to_der()),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Gives each of `certs` to `builder`.  One which fails does not stop the
+/// others being loaded, but the first failure is returned.
+fn load_ders(certs: Vec<Vec<u8>>, builder: &mut (impl RootStoreBuilder + ?Sized)) -> Result<(), Error> {
+    let mut first_error = None;
+    for der in certs {
+        if let Err(err) = builder.load_der(der) {
+            first_error = first_error
+                .or_else(|| Some(Error::new(ErrorKind::InvalidData, err)));
         }
     }
 
@@ -71,3 +1093,282 @@ pub fn build_native_certs<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Er
         Ok(())
     }
 }
+
+/// The payload types with which configuration profiles install
+/// certificates.
+#[cfg(feature = "metadata")]
+const CERTIFICATE_PAYLOADS: &[&str] = &["com.apple.security.root", "com.apple.security.pkcs1",
+                                        "com.apple.security.pem"];
+
+/// Returns the DER encoding of each certificate the installed configuration
+/// profiles hold, as MDM installs roots, by asking `profiles show`.  Run as
+/// a user other than root, it reports only that user's profiles.
+///
+/// `profiles` is run once, by the first metadata load, and what it lists is
+/// kept for the life of the process, rather than spawning it on every load.
+/// A failure to list them is logged, and none are returned.  While a
+/// [TestStore](struct.TestStore.html) is installed, none are.
+#[cfg(feature = "metadata")]
+pub fn profile_certificates() -> &'static [Vec<u8>] {
+    use std::sync::OnceLock;
+
+    static LISTED: OnceLock<Vec<Vec<u8>>> = OnceLock::new();
+    if test_store_installed() {
+        return &[];
+    }
+    LISTED.get_or_init(list_profile_certificates)
+}
+
+#[cfg(feature = "metadata")]
+fn list_profile_certificates() -> Vec<Vec<u8>> {
+    use core_foundation::data::CFData;
+    use core_foundation::propertylist::{create_with_data, kCFPropertyListImmutable, CFPropertyList};
+
+    let output = match std::process::Command::new("/usr/bin/profiles")
+        .args(["show", "-output", "stdout-xml"])
+        .output() {
+        Ok(output) if output.status.success() => output.stdout,
+        Ok(output) => {
+            debug!("the configuration profiles cannot be listed: profiles exited with {}", output.status);
+            return Vec::new();
+        }
+        Err(err) => {
+            debug!("the configuration profiles cannot be listed: {}", err);
+            return Vec::new();
+        }
+    };
+    let plist = match create_with_data(CFData::from_buffer(&output), kCFPropertyListImmutable) {
+        Ok((plist, _)) => unsafe { CFPropertyList::wrap_under_create_rule(plist) },
+        Err(err) => {
+            debug!("the configuration profiles cannot be read: {}", err);
+            return Vec::new();
+        }
+    };
+
+    let mut certs = Vec::new();
+    payload_certificates(&plist.into_CFType(), &mut certs);
+    certs
+}
+
+/// Adds the certificate of each certificate payload in `value`, the
+/// profiles listed or any part of them, to `certs`.
+#[cfg(feature = "metadata")]
+fn payload_certificates(value: &CFType, certs: &mut Vec<Vec<u8>>) {
+    use core_foundation::data::CFData;
+
+    if let Some(array) = value.downcast::<CFArray>() {
+        for item in array.iter() {
+            payload_certificates(&unsafe { CFType::wrap_under_get_rule(*item) }, certs);
+        }
+        return;
+    }
+    let dict = match value.downcast::<CFDictionary>() {
+        Some(dict) => dict,
+        None => return,
+    };
+
+    let mut payload_type = None;
+    let mut content = None;
+    let (keys, values) = dict.get_keys_and_values();
+    for (key, value) in keys.into_iter().zip(values) {
+        let (key, value) = unsafe { (CFType::wrap_under_get_rule(key), CFType::wrap_under_get_rule(value)) };
+        match key.downcast::<CFString>().map(|key| key.to_string()).as_deref() {
+            Some("PayloadType") => payload_type = value.downcast::<CFString>().map(|t| t.to_string()),
+            Some("PayloadContent") => content = value.downcast::<CFData>().map(|data| data.bytes().to_vec()),
+            _ => {}
+        }
+        payload_certificates(&value, certs);
+    }
+
+    match (payload_type, content) {
+        (Some(payload_type), Some(content)) if CERTIFICATE_PAYLOADS.contains(&payload_type.as_str()) => {
+            if content.starts_with(b"-----BEGIN") {
+                certs.extend(rustls_pemfile::certs(&mut &content[..]).unwrap_or_default());
+            } else {
+                certs.push(content);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Limits a keychain search to the test keychain while a [TestStore](struct.TestStore.html)
+/// is installed.  Otherwise the user's keychain search list is searched.
+fn search_keychains(_options: &mut ItemSearchOptions) {
+    #[cfg(feature = "test-util")]
+    if let Some(test_store) = crate::test_store::installed() {
+        use security_framework::os::macos::item::ItemSearchOptionsExt;
+
+        _options.keychains(std::slice::from_ref(&test_store.keychain));
+    }
+}
+
+/// A temporary keychain, and trust settings kept in memory, standing in
+/// for the user's.
+///
+/// Trust settings cannot be changed without the user authorizing it, so
+/// roots and distrusted certificates are recorded here instead, and apply
+/// to every purpose.
+#[cfg(feature = "test-util")]
+pub struct TestStore {
+    keychain: security_framework::os::macos::keychain::SecKeychain,
+    trust: Vec<(Vec<u8>, TrustSettingsForCertificate)>,
+}
+
+#[cfg(feature = "test-util")]
+impl TestStore {
+    pub fn new() -> Result<Self, Error> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!("rustls-native-certs-{}-{}.keychain",
+                                                     std::process::id(),
+                                                     COUNT.fetch_add(1, Ordering::Relaxed)));
+        let keychain = security_framework::os::macos::keychain::CreateOptions::new()
+            .password("rustls-native-certs")
+            .prompt_user(false)
+            .create(path)
+            .map_err(platform_error)?;
+        Ok(TestStore { keychain, trust: Vec::new() })
+    }
+
+    pub fn add_root(&mut self, der: &[u8]) -> Result<(), Error> {
+        SecCertificate::from_der(der).map_err(platform_error)?;
+        self.trust.push((der.to_vec(), TrustSettingsForCertificate::TrustRoot));
+        Ok(())
+    }
+
+    pub fn add_intermediate(&mut self, der: &[u8]) -> Result<(), Error> {
+        SecCertificate::from_der(der)
+            .and_then(|cert| cert.add_to_keychain(Some(self.keychain.clone())))
+            .map_err(platform_error)
+    }
+
+    pub fn add_distrusted(&mut self, der: &[u8]) -> Result<(), Error> {
+        SecCertificate::from_der(der).map_err(platform_error)?;
+        self.trust.push((der.to_vec(), TrustSettingsForCertificate::Deny));
+        Ok(())
+    }
+
+    pub fn add_identity_pkcs12(&mut self, pkcs12: &[u8], password: &str) -> Result<(), Error> {
+        security_framework::os::macos::import_export::ImportOptions::new()
+            .passphrase(password)
+            .keychain(&self.keychain)
+            .import(pkcs12)
+            .map_err(platform_error)
+    }
+
+    /// Returns the recorded trust settings, as the user's.  As there, the
+    /// first recorded for a certificate wins.
+    fn trust_settings(&self) -> Settings {
+        let mut all_certs = BTreeMap::new();
+        for (der, trusted) in &self.trust {
+            all_certs.entry(der.clone())
+                .or_insert(Setting {
+                    trusted: *trusted,
+                    domain: TrustDomain::User,
+                    hosts: Vec::new(),
+                    allowed_errors: Vec::new(),
+                });
+        }
+        all_certs
+    }
+}
+
+/// Deleting the keychain also takes it off the user's search list, to
+/// which creating it added it.
+#[cfg(feature = "test-util")]
+impl Drop for TestStore {
+    fn drop(&mut self) {
+        #[link(name = "Security", kind = "framework")]
+        extern "C" {
+            fn SecKeychainDelete(keychain: security_framework_sys::base::SecKeychainRef) -> i32;
+        }
+
+        // Safety: the keychain is live, and no longer used once deleted.
+        unsafe {
+            SecKeychainDelete(self.keychain.as_concrete_TypeRef());
+        }
+    }
+}
+
+/// `errSecItemNotFound`, which a keychain search returns when nothing matches.
+const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+pub fn load_client_identities() -> Result<Vec<ClientIdentity>, Error> {
+    let mut identities = Vec::new();
+    search_identities(&mut identities, false, IdentitySource::Keychain)?;
+    search_identities(&mut identities, true, IdentitySource::SmartCard)?;
+    Ok(identities)
+}
+
+/// Adds the identities in the keychain search list, or with `token`, those
+/// on smart cards and other CryptoTokenKit tokens, to `identities`.
+///
+/// Searching only reads certificates and attributes, so it doesn't prompt:
+/// a token asks for its PIN when the key is first used to sign.
+fn search_identities(identities: &mut Vec<ClientIdentity>,
+                     token: bool,
+                     source: IdentitySource) -> Result<(), Error> {
+    let mut options = ItemSearchOptions::new();
+    options.class(ItemClass::identity())
+        .load_refs(true)
+        .limit(Limit::All);
+    if token {
+        // Tokens are hardware, which a test store cannot stand in for.
+        #[cfg(feature = "test-util")]
+        if crate::test_store::installed().is_some() {
+            return Ok(());
+        }
+        options.access_group_token();
+    }
+    search_keychains(&mut options);
+    let results = match options.search() {
+        Ok(results) => results,
+        Err(err) if err.code() == ERR_SEC_ITEM_NOT_FOUND => return Ok(()),
+        Err(err) => return Err(platform_error(err)),
+    };
+
+    for result in results {
+        let identity_ref = match result {
+            SearchResult::Ref(Reference::Identity(identity)) => identity,
+            _ => continue,
+        };
+        let cert = identity_ref.certificate()
+            .map_err(platform_error)?;
+        let der = cert.to_der();
+        if identities.iter().any(|seen| seen.certificate() == der.as_slice()) {
+            continue;
+        }
+
+        let identity = ClientIdentity::new(chain_for(&cert),
+                                           Some(cert.subject_summary()),
+                                           source.clone());
+        identities.push(identity.with_native_key(NativeKey(identity_ref)));
+    }
+    Ok(())
+}
+
+/// Builds the chain for `cert` from the keychains, returning the DER
+/// encodings of its certificates, starting with `cert` itself.
+///
+/// The chain is the one built while evaluating trust, whether or not it
+/// turns out to be trusted; if evaluation fails outright, it is just `cert`.
+fn chain_for(cert: &SecCertificate) -> Vec<Vec<u8>> {
+    let built = SecTrust::create_with_certificates(std::slice::from_ref(cert), &[SecPolicy::create_x509()])
+        .map(|trust| {
+            let _ = trust.evaluate_with_error();
+            #[allow(deprecated)]
+            (0..trust.certificate_count())
+                .filter_map(|i| trust.certificate_at_index(i))
+                .map(|cert| cert.to_der())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    if built.is_empty() {
+        vec![cert.to_der()]
+    } else {
+        built
+    }
+}
@@ -0,0 +1,329 @@
+#[cfg(feature = "metadata")]
+use std::io::{BufRead, Error, ErrorKind};
+use std::path::PathBuf;
+
+use crate::TrustDomain;
+#[cfg(feature = "metadata")]
+use crate::{der, sha256::Sha256, RootStoreBuilder};
+
+/// Where in the native store a root was found.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Provenance {
+    /// A PEM or DER file, as on Linux, or a fixture named by
+    /// `RUSTLS_NATIVE_CERTS_OVERRIDE`.
+    File(PathBuf),
+    /// The trust settings of a domain, on macOS.
+    TrustSettings(TrustDomain),
+    /// A Windows certificate store, by name, such as "ROOT".
+    WindowsStore(String),
+    /// Nowhere the load can say, as for the mock backend and loads which
+    /// replay a capture.
+    Unknown,
+}
+
+/// The trust the platform gives a root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PlatformTrust {
+    /// The platform records no setting of its own: the root is trusted for
+    /// being in the store, or, in a bundle which records trust for each
+    /// purpose, for being recorded as trusted for the load's purpose.
+    InStore,
+    /// A trust setting on macOS trusts the root as a root.
+    TrustRoot,
+    /// A trust setting on macOS trusts the certificate as a root, though it
+    /// is not self-signed.
+    TrustAsRoot,
+    /// A trust setting on macOS denies the certificate, or the Windows
+    /// "Disallowed" store holds it.  Only certificates
+    /// reported as denied, as by [CertStore::denied](struct.CertStore.html#method.denied),
+    /// have it.
+    Deny,
+}
+
+/// A root from the native store, with where it was found and the trust
+/// the platform gives it.
+///
+/// With the "x509" feature, it is displayed as its subject, issuer and
+/// expiry, and its `Debug` form gives those in place of the DER bytes.
+///
+/// *This type is available only if the crate is built with the "metadata" feature.*
+#[cfg(feature = "metadata")]
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "x509"), derive(Debug))]
+pub struct NativeCert {
+    der: Vec<u8>,
+    provenance: Provenance,
+    trust: PlatformTrust,
+    hosts: Vec<String>,
+    managed: bool,
+    allowed_errors: Vec<i32>,
+    fingerprint: [u8; 32],
+}
+
+#[cfg(feature = "metadata")]
+impl NativeCert {
+    pub(crate) fn new(der: Vec<u8>, provenance: Provenance, trust: PlatformTrust) -> Self {
+        NativeCert {
+            fingerprint: Sha256::digest(&der),
+            der,
+            provenance,
+            trust,
+            hosts: Vec::new(),
+            managed: false,
+            allowed_errors: Vec::new(),
+        }
+    }
+
+    pub(crate) fn with_host_restrictions(mut self, hosts: Vec<String>) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
+    pub(crate) fn with_managed(mut self, managed: bool) -> Self {
+        self.managed = managed;
+        self
+    }
+
+    pub(crate) fn with_allowed_errors(mut self, allowed_errors: Vec<i32>) -> Self {
+        self.allowed_errors = allowed_errors;
+        self
+    }
+
+    /// Reads the first certificate in `pem`, for appending anchors of one's
+    /// own to those loaded.  It has the provenance `Provenance::Unknown`,
+    /// and the trust `PlatformTrust::InStore`.
+    ///
+    /// Fails with `ErrorKind::InvalidData` if `pem` holds no certificate,
+    /// or is malformed before the first.
+    pub fn from_pem(pem: &[u8]) -> Result<Self, Error> {
+        let mut rd = pem;
+        loop {
+            match rustls_pemfile::read_one(&mut rd)? {
+                Some(rustls_pemfile::Item::X509Certificate(der)) => {
+                    return Ok(NativeCert::new(der, Provenance::Unknown, PlatformTrust::InStore));
+                }
+                Some(_) => continue,
+                None => return Err(Error::new(ErrorKind::InvalidData, "no certificate found in PEM")),
+            }
+        }
+    }
+
+    /// Returns the certificate as a PEM "CERTIFICATE" block, as other
+    /// tools take it.
+    pub fn to_pem(&self) -> String {
+        der::certificate_pem(&self.der)
+    }
+
+    /// Returns the DER encoding of the certificate.
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// Returns the DER encoding of the certificate.
+    pub fn into_der(self) -> Vec<u8> {
+        self.der
+    }
+
+    /// Returns where the certificate was found.
+    pub fn provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
+    /// Returns the trust domain whose settings trusted the certificate, on
+    /// macOS, or `None` elsewhere.  A root from `TrustDomain::System` is one
+    /// Apple ships; one from the user or admin domain was added locally.
+    pub fn trust_domain(&self) -> Option<TrustDomain> {
+        match self.provenance {
+            Provenance::TrustSettings(domain) => Some(domain),
+            _ => None,
+        }
+    }
+
+    /// Returns the trust the platform gives the certificate.
+    pub fn trust(&self) -> PlatformTrust {
+        self.trust
+    }
+
+    /// Returns the hosts the platform trusts the certificate for, where it
+    /// trusts it only for some, as trust settings on macOS can restrict a
+    /// root to hostnames.  This is empty for a root trusted for any host.
+    pub fn host_restrictions(&self) -> &[String] {
+        &self.hosts
+    }
+
+    /// Returns the errors the platform's trust in the certificate depends
+    /// on, as `OSStatus` codes, where trust settings on macOS trust it only
+    /// when evaluation meets one of them, which they then allow, as with
+    /// `kSecTrustSettingsAllowedError`.  A verifier can honour such trust
+    /// only where its own evaluation fails with one of these errors, such as
+    /// `CSSMERR_TP_CERT_EXPIRED`.  This is empty for a root trusted whatever
+    /// evaluation finds.
+    pub fn allowed_errors(&self) -> &[i32] {
+        &self.allowed_errors
+    }
+
+    /// Returns whether a configuration profile installed the certificate,
+    /// on macOS, as MDM installs the roots of a managed machine, as against
+    /// one added by hand.  Profiles are listed with `profiles show`, which
+    /// reports the computer's profiles only to root, so a load by another
+    /// user finds only those installed for that user.  They are listed once
+    /// per process, by its first metadata load, so a profile installed later
+    /// is not seen.  This is always `false` elsewhere.
+    pub fn is_managed(&self) -> bool {
+        self.managed
+    }
+
+    /// Returns the SHA-256 digest of the certificate's DER encoding, as
+    /// fingerprints are usually given.
+    pub fn fingerprint_sha256(&self) -> [u8; 32] {
+        self.fingerprint
+    }
+
+    /// Returns the SHA-256 digest of the certificate's DER-encoded
+    /// SubjectPublicKeyInfo, as public key pins are given, or `None` if the
+    /// certificate cannot be parsed.  Unlike the fingerprint, it is the same
+    /// for every certificate issued for the key.
+    pub fn spki_sha256(&self) -> Option<[u8; 32]> {
+        let fields = der::cert_fields(&self.der)?;
+        Some(Sha256::digest(fields.public_key))
+    }
+}
+
+/// Loads the root certificates found in the platform's native
+/// certificate store, each with where it was found, the trust the
+/// platform gives it, and its fingerprint.
+///
+/// This function fails in the same way as
+/// [build_native_certs](fn.build_native_certs.html).
+///
+/// *This function is available only if the crate is built with the "metadata" feature.*
+#[cfg(feature = "metadata")]
+pub fn load_native_certs_with_metadata() -> Result<Vec<NativeCert>, Error> {
+    load_with_denied().map(|(certs, _)| certs)
+}
+
+/// Loads the roots with metadata, and the certificates the load found
+/// denied.
+#[cfg(feature = "metadata")]
+pub(crate) fn load_with_denied() -> Result<(Vec<NativeCert>, Vec<NativeCert>), Error> {
+    let mut certs = WithMetadata {
+        certs: Vec::new(),
+        provenance: Provenance::Unknown,
+        trust: PlatformTrust::InStore,
+        hosts: Vec::new(),
+        allowed_errors: Vec::new(),
+        denied: Vec::new(),
+    };
+    crate::build_native_certs(&mut certs)?;
+
+    let managed = crate::profile_certificates();
+    let roots = certs.certs.into_iter()
+        .map(|cert| {
+            let is_managed = managed.contains(&cert.der);
+            cert.with_managed(is_managed)
+        })
+        .collect();
+    Ok((roots, certs.denied))
+}
+
+/// A builder which keeps each root, and each certificate denied, with the
+/// provenance last set.
+#[cfg(feature = "metadata")]
+struct WithMetadata {
+    certs: Vec<NativeCert>,
+    provenance: Provenance,
+    trust: PlatformTrust,
+    hosts: Vec<String>,
+    allowed_errors: Vec<i32>,
+    denied: Vec<NativeCert>,
+}
+
+#[cfg(feature = "metadata")]
+impl RootStoreBuilder for WithMetadata {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.certs.push(NativeCert::new(der, self.provenance.clone(), self.trust)
+            .with_host_restrictions(self.hosts.clone())
+            .with_allowed_errors(self.allowed_errors.clone()));
+        Ok(())
+    }
+
+    fn denied(&mut self, der: &[u8]) {
+        self.denied.push(NativeCert::new(der.to_vec(), self.provenance.clone(), PlatformTrust::Deny));
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        for der in rustls_pemfile::certs(rd)? {
+            self.load_der(der)?;
+        }
+        Ok(())
+    }
+
+    fn set_provenance(&mut self, provenance: &Provenance, trust: PlatformTrust) {
+        self.provenance = provenance.clone();
+        self.trust = trust;
+        self.hosts.clear();
+        self.allowed_errors.clear();
+    }
+
+    fn set_host_restrictions(&mut self, hosts: &[String]) {
+        self.hosts = hosts.to_vec();
+    }
+
+    fn set_allowed_errors(&mut self, errors: &[i32]) {
+        self.allowed_errors = errors.to_vec();
+    }
+}
+
+/// Displays the certificate as its subject, issuer and expiry, like
+/// `CN=Example Root, O=Example (issued by CN=Example Root, O=Example,
+/// expires 2038-01-19T03:14:07Z)`.  Parts which cannot be parsed are shown
+/// as "(unparsable)".
+#[cfg(feature = "x509")]
+impl std::fmt::Display for NativeCert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let summary = Summary::new(&self.der);
+        write!(f, "{} (issued by {}, expires {})", summary.subject, summary.issuer, summary.not_after)
+    }
+}
+
+#[cfg(feature = "x509")]
+impl std::fmt::Debug for NativeCert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let summary = Summary::new(&self.der);
+        let fingerprint: String = self.fingerprint.iter().map(|b| format!("{:02x}", b)).collect();
+        f.debug_struct("NativeCert")
+            .field("subject", &summary.subject)
+            .field("issuer", &summary.issuer)
+            .field("not_after", &summary.not_after)
+            .field("sha256", &fingerprint)
+            .field("provenance", &self.provenance)
+            .field("trust", &self.trust)
+            .field("host_restrictions", &self.hosts)
+            .field("managed", &self.managed)
+            .field("allowed_errors", &self.allowed_errors)
+            .finish()
+    }
+}
+
+/// The fields of a certificate people read, as text.
+#[cfg(feature = "x509")]
+struct Summary {
+    subject: String,
+    issuer: String,
+    not_after: String,
+}
+
+#[cfg(feature = "x509")]
+impl Summary {
+    fn new(cert: &[u8]) -> Self {
+        let fields = der::cert_fields(cert);
+        let text = |value: Option<String>| value.unwrap_or_else(|| "(unparsable)".to_owned());
+        Summary {
+            subject: text(fields.as_ref().and_then(|fields| der::name_to_string(fields.subject))),
+            issuer: text(fields.as_ref().and_then(|fields| der::name_to_string(fields.issuer))),
+            not_after: text(fields.as_ref().and_then(|fields| fields.not_after())),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::Error;
+
+use crate::{CertificateSource, Fault, Purpose, RootStoreBuilder};
+
+/// What a [MockCertificateSource](struct.MockCertificateSource.html)
+/// reports a certificate as.
+///
+/// *This type is available only if the crate is built with the "test-util" feature.*
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TrustStatus {
+    /// A root trusted for the given purposes.
+    Trusted(Vec<Purpose>),
+    /// An intermediate: kept in the store, but not a trust anchor.
+    Intermediate,
+    /// Explicitly distrusted.  A certificate which is also added as a root
+    /// is left out of the roots, as the platforms do.
+    Distrusted,
+}
+
+/// An in-memory [CertificateSource](trait.CertificateSource.html) with
+/// fixed contents, for testing code which handles trust without touching
+/// the real store.
+///
+/// *This type is available only if the crate is built with the "test-util" feature.*
+#[derive(Clone, Debug, Default)]
+pub struct MockCertificateSource {
+    certs: Vec<(Vec<u8>, TrustStatus)>,
+    crls: Vec<Vec<u8>>,
+    fault: Option<Fault>,
+}
+
+impl MockCertificateSource {
+    /// Returns a source with no certificates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a certificate, given as DER, with the given status.
+    pub fn with_der(mut self, der: impl Into<Vec<u8>>, status: TrustStatus) -> Self {
+        self.certs.push((der.into(), status));
+        self
+    }
+
+    /// Adds every certificate in `pem`, with the given status.
+    ///
+    /// # Panics
+    ///
+    /// If `pem` cannot be read as PEM.
+    pub fn with_pem(mut self, pem: &[u8], status: TrustStatus) -> Self {
+        let ders = rustls_pemfile::certs(&mut &pem[..]).expect("invalid PEM fixture");
+        for der in ders {
+            self.certs.push((der, status.clone()));
+        }
+        self
+    }
+
+    /// Adds every certificate in `pem` as a root trusted for TLS servers,
+    /// which is what [build_native_certs](fn.build_native_certs.html) loads.
+    ///
+    /// # Panics
+    ///
+    /// If `pem` cannot be read as PEM.
+    pub fn with_root_pem(self, pem: &[u8]) -> Self {
+        self.with_pem(pem, TrustStatus::Trusted(vec![Purpose::ServerAuth]))
+    }
+
+    /// Adds a certificate revocation list, given as DER.
+    pub fn with_crl(mut self, der: impl Into<Vec<u8>>) -> Self {
+        self.crls.push(der.into());
+        self
+    }
+
+    /// Makes every load from this source fail with `fault`.
+    pub fn with_fault(mut self, fault: Fault) -> Self {
+        self.fault = Some(fault);
+        self
+    }
+
+    fn check_fault(&self) -> Result<(), Error> {
+        match self.fault {
+            Some(fault) => Err(fault.to_error()),
+            None => Ok(()),
+        }
+    }
+
+    fn build_roots(&self, purpose: Purpose, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+        let distrusted = self.with_status(|status| *status == TrustStatus::Distrusted);
+        let roots = self.with_status(|status| matches!(status, TrustStatus::Trusted(purposes)
+                                                       if purposes.contains(&purpose)));
+        for der in roots {
+            if !distrusted.contains(&der) {
+                builder.load_der(der)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn with_status(&self, wanted: impl Fn(&TrustStatus) -> bool) -> Vec<Vec<u8>> {
+        let mut found: Vec<Vec<u8>> = Vec::new();
+        for (der, status) in &self.certs {
+            if wanted(status) && !found.contains(der) {
+                found.push(der.clone());
+            }
+        }
+        found
+    }
+}
+
+impl CertificateSource for MockCertificateSource {
+    fn build_certs(&self, purpose: Purpose, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+        match self.fault {
+            Some(fault) => fault.build_certs(builder, |builder| self.build_roots(purpose, builder)),
+            None => self.build_roots(purpose, builder),
+        }
+    }
+
+    fn intermediates(&self) -> Result<Vec<Vec<u8>>, Error> {
+        self.check_fault()?;
+        Ok(self.with_status(|status| *status == TrustStatus::Intermediate))
+    }
+
+    fn distrusted(&self) -> Result<Vec<Vec<u8>>, Error> {
+        self.check_fault()?;
+        Ok(self.with_status(|status| *status == TrustStatus::Distrusted))
+    }
+
+    fn crls(&self) -> Result<Vec<Vec<u8>>, Error> {
+        self.check_fault()?;
+        Ok(self.crls.clone())
+    }
+}
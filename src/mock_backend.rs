@@ -0,0 +1,128 @@
+//! A backend which holds its certificates in memory, in place of the
+//! platform's store, so the crate builds without any platform libraries.
+
+use std::io::Error;
+use std::sync::RwLock;
+
+use crate::{CertificateSource, ClientIdentity, MockCertificateSource, Purpose, RootStoreBuilder};
+
+static SOURCE: RwLock<Option<MockCertificateSource>> = RwLock::new(None);
+
+/// Makes `source` what every load of the native store returns, in place of
+/// the platform's store.  Until this is called the store is empty.
+///
+/// Any [CertWatcher](struct.CertWatcher.html) is told of the change.
+///
+/// *This function is available only if the crate is built with the "mock-backend" feature.*
+pub fn install_mock_backend(source: MockCertificateSource) {
+    *SOURCE.write().unwrap_or_else(|err| err.into_inner()) = Some(source);
+    #[cfg(feature = "watch")]
+    notify::changed();
+}
+
+fn with_source<T>(load: impl FnOnce(&MockCertificateSource) -> Result<T, Error>) -> Result<T, Error> {
+    let source = SOURCE.read().unwrap_or_else(|err| err.into_inner());
+    load(source.as_ref().unwrap_or(&MockCertificateSource::new()))
+}
+
+pub fn build_native_certs<B: RootStoreBuilder>(purpose: Purpose, builder: &mut B) -> Result<(), Error> {
+    with_source(|source| source.build_certs(purpose, builder))
+}
+
+pub fn load_intermediates() -> Result<Vec<Vec<u8>>, Error> {
+    with_source(MockCertificateSource::intermediates)
+}
+
+pub fn load_distrusted() -> Result<Vec<Vec<u8>>, Error> {
+    with_source(MockCertificateSource::distrusted)
+}
+
+pub fn load_crls() -> Result<Vec<Vec<u8>>, Error> {
+    with_source(MockCertificateSource::crls)
+}
+
+pub fn describe_roots(_purpose: Purpose) -> String {
+    "mock backend".to_owned()
+}
+
+#[cfg(feature = "cli")]
+pub fn describe_list(_list: crate::List) -> String {
+    "mock backend".to_owned()
+}
+
+#[cfg(feature = "cli")]
+pub const ENVIRONMENT: &[&str] = &[];
+
+#[cfg(feature = "cli")]
+pub const REMEDY: &str = "Give the mock backend roots with `install_mock_backend`.";
+
+/// The mock backend is held in memory, so has nowhere to probe.
+#[cfg(feature = "cli")]
+pub fn probe_locations() -> Vec<crate::Probe> {
+    Vec::new()
+}
+
+/// The mock backend holds no client identities.
+pub fn load_client_identities() -> Result<Vec<ClientIdentity>, Error> {
+    Ok(Vec::new())
+}
+
+/// No keys are held by the mock backend, so none can be handed out.
+#[derive(Clone)]
+pub enum NativeKey {}
+
+#[cfg(feature = "rustls")]
+pub fn signing_key(key: &NativeKey, _key_type: crate::der::KeyType)
+    -> Result<Box<dyn rustls::sign::SigningKey>, Error>
+{
+    match *key {}
+}
+
+pub fn export_pkcs12(key: &NativeKey, _chain: &[Vec<u8>], _password: &str) -> Result<Vec<u8>, Error> {
+    match *key {}
+}
+
+#[cfg(feature = "watch")]
+pub use self::notify::{watch, Watcher};
+
+/// Change notifications, which are sent whenever a source is installed.
+#[cfg(feature = "watch")]
+mod notify {
+    use std::io::Error;
+    use std::sync::Mutex;
+
+    type Callback = Box<dyn FnMut() + Send>;
+
+    static WATCHERS: Mutex<Vec<(u64, Callback)>> = Mutex::new(Vec::new());
+    static NEXT_ID: Mutex<u64> = Mutex::new(0);
+
+    pub struct Watcher {
+        id: u64,
+    }
+
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            WATCHERS.lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .retain(|(id, _)| *id != self.id);
+        }
+    }
+
+    pub fn watch(on_change: impl FnMut() + Send + 'static) -> Result<Watcher, Error> {
+        let id = {
+            let mut next = NEXT_ID.lock().unwrap_or_else(|err| err.into_inner());
+            *next += 1;
+            *next
+        };
+        WATCHERS.lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push((id, Box::new(on_change)));
+        Ok(Watcher { id })
+    }
+
+    pub(super) fn changed() {
+        for (_, on_change) in WATCHERS.lock().unwrap_or_else(|err| err.into_inner()).iter_mut() {
+            on_change();
+        }
+    }
+}
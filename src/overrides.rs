@@ -0,0 +1,84 @@
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::{PlatformTrust, Provenance, RootStoreBuilder};
+
+/// Reads the DER encodings of one kind of object from PEM.
+type PemReader = fn(&mut dyn std::io::BufRead) -> Result<Vec<Vec<u8>>, Error>;
+
+pub(crate) const OVERRIDE_VAR: &str = "RUSTLS_NATIVE_CERTS_OVERRIDE";
+
+/// A fixture named by `RUSTLS_NATIVE_CERTS_OVERRIDE`, which replaces the
+/// platform's store for every load.
+///
+/// The fixture is a file of PEM roots, or a directory whose files are
+/// roots, each in PEM or DER.  A directory may also have `intermediates`,
+/// `distrusted` and `crls` subdirectories, laid out the same way, for the
+/// other loads; without them those loads find nothing.
+pub(crate) struct Fixture(PathBuf);
+
+impl Fixture {
+    pub(crate) fn from_env() -> Option<Self> {
+        std::env::var_os(OVERRIDE_VAR)
+            .filter(|path| !path.is_empty())
+            .map(|path| Fixture(path.into()))
+    }
+
+    /// Returns the path of the fixture.
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Loads the fixture's roots.  They stand for the roots of every
+    /// purpose.
+    pub(crate) fn build_certs(&self, builder: &mut impl RootStoreBuilder) -> Result<(), Error> {
+        builder.set_provenance(&Provenance::File(self.0.clone()), PlatformTrust::InStore);
+        if self.0.is_dir() {
+            for contents in read_files(&self.0)? {
+                if contents.starts_with(&[0x30]) {
+                    builder.load_der(contents)?;
+                } else {
+                    builder.load_pem_file(&mut &contents[..])?;
+                }
+            }
+            Ok(())
+        } else {
+            let contents = std::fs::read(&self.0)?;
+            builder.load_pem_file(&mut &contents[..])
+                .map_err(|_| Error::new(ErrorKind::InvalidData,
+                                        format!("Could not load PEM file {:?}", self.0)))
+        }
+    }
+
+    /// Loads the objects in the fixture's `subdir`, which `pem` extracts
+    /// from files not in DER.
+    pub(crate) fn load(&self, subdir: &str, pem: PemReader) -> Result<Vec<Vec<u8>>, Error> {
+        let dir = self.0.join(subdir);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut found = Vec::new();
+        for contents in read_files(&dir)? {
+            if contents.starts_with(&[0x30]) {
+                found.push(contents);
+            } else {
+                found.extend(pem(&mut &contents[..])?);
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Returns the contents of the files directly in `dir`, in order of name.
+fn read_files(dir: &Path) -> Result<Vec<Vec<u8>>, Error> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    paths.into_iter()
+        .filter(|path| path.is_file())
+        .map(std::fs::read)
+        .collect()
+}
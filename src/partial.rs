@@ -0,0 +1,81 @@
+use std::io::{Error, ErrorKind};
+use std::iter::FromIterator;
+
+/// The roots a load found, along with what it skipped, as returned by
+/// [load_native_certs_partial](fn.load_native_certs_partial.html).
+///
+/// It can also be collected from a [NativeCertIter](struct.NativeCertIter.html).
+#[derive(Debug, Default)]
+pub struct CertificateResult {
+    /// The DER encodings of the roots found, in the order the store gave
+    /// them.
+    pub certs: Vec<Vec<u8>>,
+    /// What was skipped, and why.
+    pub errors: Vec<LoadError>,
+}
+
+impl CertificateResult {
+    /// Returns whether the load skipped nothing.
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl FromIterator<Result<Vec<u8>, Error>> for CertificateResult {
+    fn from_iter<I: IntoIterator<Item = Result<Vec<u8>, Error>>>(iter: I) -> Self {
+        let mut result = CertificateResult::default();
+        for item in iter {
+            match item {
+                Ok(der) => result.certs.push(der),
+                Err(error) => result.errors.push(LoadError {
+                    position: result.certs.len(),
+                    error,
+                }),
+            }
+        }
+        result
+    }
+}
+
+/// A certificate, or part of the store, which a load skipped.
+#[derive(Debug)]
+pub struct LoadError {
+    position: usize,
+    error: Error,
+}
+
+impl LoadError {
+    /// Returns how many roots were found before this error, which places
+    /// it among the roots in `CertificateResult::certs`.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the kind of the error: `InvalidData` where a certificate
+    /// could not be parsed, and otherwise why the store could not be read.
+    pub fn kind(&self) -> ErrorKind {
+        self.error.kind()
+    }
+
+    /// Returns the error which made the load skip something.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Returns the error which made the load skip something.
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "after {} roots: {}", self.position, self.error)
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,74 @@
+// On-disk copies of a `Snapshot`.
+//
+// The file format is:
+//
+// * the 8-byte magic `RNCSNAP1`,
+// * the SHA-256 digest of the remainder of the file,
+// * each certificate, as a big-endian `u32` length followed by its DER encoding.
+//
+// Files are replaced atomically, so a crash while saving leaves the
+// previous copy intact; the digest catches any other corruption.
+
+use std::convert::TryInto;
+use std::fs;
+use std::io::{Error, ErrorKind, Write};
+use std::path::Path;
+
+use crate::sha256::Sha256;
+use crate::snapshot::Snapshot;
+use crate::RootStoreBuilder;
+
+const MAGIC: &[u8; 8] = b"RNCSNAP1";
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("invalid snapshot file: {}", msg))
+}
+
+/// Writes `snapshot` to `path`, replacing any existing file.
+pub fn save(path: &Path, snapshot: &Snapshot) -> Result<(), Error> {
+    let mut body = Vec::new();
+    for der in snapshot.ders() {
+        let len: u32 = der.len()
+            .try_into()
+            .map_err(|_| invalid("certificate too large"))?;
+        body.extend_from_slice(&len.to_be_bytes());
+        body.extend_from_slice(der);
+    }
+
+    crate::replace::replace_file(path, |f| {
+        f.write_all(MAGIC)?;
+        f.write_all(&Sha256::digest(&body))?;
+        f.write_all(&body)
+    })
+}
+
+/// Reads a snapshot previously written by `save`.
+pub fn load(path: &Path) -> Result<Snapshot, Error> {
+    let contents = fs::read(path)?;
+
+    if contents.len() < MAGIC.len() + 32 || &contents[..MAGIC.len()] != MAGIC {
+        return Err(invalid("bad header"));
+    }
+
+    let (digest, mut body) = contents[MAGIC.len()..].split_at(32);
+    if Sha256::digest(body).as_slice() != digest {
+        return Err(invalid("digest mismatch"));
+    }
+
+    let mut snapshot = Snapshot::default();
+    while !body.is_empty() {
+        if body.len() < 4 {
+            return Err(invalid("truncated length"));
+        }
+        let (len, rest) = body.split_at(4);
+        let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+        if rest.len() < len {
+            return Err(invalid("truncated certificate"));
+        }
+        let (der, rest) = rest.split_at(len);
+        snapshot.load_der(der.to_vec())?;
+        body = rest;
+    }
+
+    Ok(snapshot)
+}
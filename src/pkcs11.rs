@@ -0,0 +1,435 @@
+// Client identities held on PKCS#11 tokens, such as smart cards.
+//
+// Tokens are selected by PKCS#11 URI (RFC 7512).  Modules are loaded once
+// and never finalized, since other parts of the process may share them.
+
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use cryptoki::context::{CInitializeArgs, CInitializeFlags, Pkcs11};
+use cryptoki::error::{Error as Pkcs11Error, RvError};
+use cryptoki::object::{Attribute, AttributeType, ObjectClass, ObjectHandle};
+use cryptoki::session::Session;
+use cryptoki::slot::Slot;
+use cryptoki::types::AuthPin;
+
+use crate::der;
+use crate::{ClientIdentity, IdentitySource};
+
+/// The module used when a URI names neither `module-path` nor `module-name`:
+/// p11-kit's proxy, which fronts every module configured on the system.
+const DEFAULT_MODULES: &[&str] = &["p11-kit-proxy.so", "libp11-kit.so.0"];
+
+/// The parts of a PKCS#11 URI this crate understands.
+#[derive(Default)]
+struct Uri {
+    token: Option<String>,
+    manufacturer: Option<String>,
+    serial: Option<String>,
+    model: Option<String>,
+    slot_id: Option<u64>,
+    object: Option<Vec<u8>>,
+    id: Option<Vec<u8>>,
+    module_path: Option<PathBuf>,
+    module_name: Option<String>,
+    pin: Option<String>,
+}
+
+fn invalid_uri(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("invalid PKCS#11 URI: {}", msg))
+}
+
+fn percent_decode(value: &str) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(value.len());
+    let mut bytes = value.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next(), bytes.next()];
+            let hex = match hex {
+                [Some(hi), Some(lo)] => [hi, lo],
+                _ => return Err(invalid_uri(format!("truncated escape in {:?}", value))),
+            };
+            let hex = std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| invalid_uri(format!("bad escape in {:?}", value)))?;
+            out.push(hex);
+        } else {
+            out.push(b);
+        }
+    }
+    Ok(out)
+}
+
+fn percent_decode_str(value: &str) -> Result<String, Error> {
+    String::from_utf8(percent_decode(value)?)
+        .map_err(|_| invalid_uri(format!("{:?} is not UTF-8", value)))
+}
+
+impl Uri {
+    fn parse(uri: &str) -> Result<Self, Error> {
+        let rest = uri.strip_prefix("pkcs11:")
+            .ok_or_else(|| invalid_uri(format!("{:?} does not start with \"pkcs11:\"", uri)))?;
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (rest, ""),
+        };
+
+        let mut parsed = Uri::default();
+        for attr in path.split(';').filter(|attr| !attr.is_empty()) {
+            let (name, value) = attr.split_once('=')
+                .ok_or_else(|| invalid_uri(format!("attribute {:?} has no value", attr)))?;
+            match name {
+                "token" => parsed.token = Some(percent_decode_str(value)?),
+                "manufacturer" => parsed.manufacturer = Some(percent_decode_str(value)?),
+                "serial" => parsed.serial = Some(percent_decode_str(value)?),
+                "model" => parsed.model = Some(percent_decode_str(value)?),
+                "slot-id" => parsed.slot_id = Some(value.parse()
+                    .map_err(|_| invalid_uri(format!("bad slot-id {:?}", value)))?),
+                "object" => parsed.object = Some(percent_decode(value)?),
+                "id" => parsed.id = Some(percent_decode(value)?),
+                // Certificates and their keys are always what is looked for.
+                "type" => {}
+                _ => return Err(invalid_uri(format!("unsupported attribute {:?}", name))),
+            }
+        }
+
+        for attr in query.split('&').filter(|attr| !attr.is_empty()) {
+            let (name, value) = attr.split_once('=')
+                .ok_or_else(|| invalid_uri(format!("attribute {:?} has no value", attr)))?;
+            match name {
+                "module-path" => parsed.module_path = Some(PathBuf::from(percent_decode_str(value)?)),
+                "module-name" => parsed.module_name = Some(percent_decode_str(value)?),
+                "pin-value" => parsed.pin = Some(percent_decode_str(value)?),
+                "pin-source" => {
+                    let source = percent_decode_str(value)?;
+                    let path = source.strip_prefix("file:").unwrap_or(&source);
+                    let pin = std::fs::read_to_string(path)?;
+                    parsed.pin = Some(pin.trim_end_matches(&['\r', '\n'][..]).to_string());
+                }
+                _ => return Err(invalid_uri(format!("unsupported attribute {:?}", name))),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    fn modules(&self) -> Vec<PathBuf> {
+        match (&self.module_path, &self.module_name) {
+            (Some(path), _) => vec![path.clone()],
+            (None, Some(name)) => vec![PathBuf::from(format!("{}.so", name))],
+            (None, None) => DEFAULT_MODULES.iter().map(PathBuf::from).collect(),
+        }
+    }
+}
+
+fn pkcs11_error(err: Pkcs11Error) -> Error {
+    Error::other(format!("PKCS#11: {}", err))
+}
+
+/// Returns an initialized context for the first of `modules` which loads.
+fn context(modules: &[PathBuf]) -> Result<Pkcs11, Error> {
+    static LOADED: Mutex<Vec<(PathBuf, Pkcs11)>> = Mutex::new(Vec::new());
+    let mut loaded = LOADED.lock().unwrap_or_else(|err| err.into_inner());
+
+    let mut first_error = None;
+    for module in modules {
+        if let Some((_, ctx)) = loaded.iter().find(|(path, _)| path == module) {
+            return Ok(ctx.clone());
+        }
+
+        let ctx = match Pkcs11::new(module) {
+            Ok(ctx) => ctx,
+            Err(err) => {
+                first_error = first_error.or(Some(err));
+                continue;
+            }
+        };
+        match ctx.initialize(CInitializeArgs::new(CInitializeFlags::OS_LOCKING_OK)) {
+            Ok(()) | Err(Pkcs11Error::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => {}
+            Err(err) => return Err(pkcs11_error(err)),
+        }
+        loaded.push((module.clone(), ctx.clone()));
+        return Ok(ctx);
+    }
+
+    Err(first_error.map_or_else(|| Error::new(ErrorKind::NotFound, "no PKCS#11 module given"),
+                                pkcs11_error))
+}
+
+/// Loads the client identities on the PKCS#11 tokens matching `uri`.
+///
+/// `uri` is a PKCS#11 URI, as described in RFC 7512, such as
+/// `pkcs11:token=My%20Card?module-path=/usr/lib/opensc-pkcs11.so`.  The
+/// `token`, `manufacturer`, `serial`, `model` and `slot-id` attributes select
+/// tokens, and `object` and `id` select certificates on them.  The module
+/// is taken from `module-path` or `module-name`, defaulting to p11-kit's
+/// proxy module, which makes every module configured for p11-kit available.
+///
+/// Listing identities does not log in to the token.  The PIN, if any, is
+/// taken from the `pin-value` or `pin-source` attribute when the identity's
+/// [signing_key](struct.ClientIdentity.html#method.signing_key) first signs.
+///
+/// *This function is available only on Unix platforms other than macOS,
+/// and only if the crate is built with the "pkcs11" feature.*
+pub fn load_pkcs11_identities(uri: &str) -> Result<Vec<ClientIdentity>, Error> {
+    let uri = Uri::parse(uri)?;
+    let ctx = context(&uri.modules())?;
+    let pin = uri.pin.as_ref().map(|pin| Arc::new(AuthPin::from(pin.as_str())));
+
+    let mut identities = Vec::new();
+    for slot in ctx.get_slots_with_token().map_err(pkcs11_error)? {
+        let info = ctx.get_token_info(slot).map_err(pkcs11_error)?;
+        let selected = uri.slot_id.is_none_or(|id| id == slot.id()) &&
+            uri.token.as_deref().is_none_or(|token| token == info.label()) &&
+            uri.manufacturer.as_deref().is_none_or(|m| m == info.manufacturer_id()) &&
+            uri.serial.as_deref().is_none_or(|serial| serial == info.serial_number()) &&
+            uri.model.as_deref().is_none_or(|model| model == info.model());
+        if !selected {
+            continue;
+        }
+
+        let session = ctx.open_ro_session(slot).map_err(pkcs11_error)?;
+        let certs = certificates(&session)?;
+        let visible_keys = session.find_objects(&[Attribute::Class(ObjectClass::PRIVATE_KEY)])
+            .map_err(pkcs11_error)?
+            .into_iter()
+            .map(|key| object_id(&session, key))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for cert in &certs {
+            let wanted = !cert.id.is_empty() &&
+                uri.id.as_ref().is_none_or(|id| *id == cert.id) &&
+                uri.object.as_ref().is_none_or(|label| *label == cert.label);
+            // Private keys are usually hidden until the user logs in, in
+            // which case every certificate with an ID is assumed to have one.
+            let has_key = visible_keys.contains(&cert.id) ||
+                (visible_keys.is_empty() && info.login_required());
+            if !wanted || !has_key {
+                continue;
+            }
+
+            let label = String::from_utf8(cert.label.clone())
+                .ok()
+                .filter(|label| !label.is_empty());
+            let source = IdentitySource::Pkcs11Token(info.label().to_string());
+            let key = TokenKey {
+                ctx: ctx.clone(),
+                slot,
+                id: cert.id.clone(),
+                pin: pin.clone(),
+            };
+            identities.push(ClientIdentity::new(build_chain(&cert.der, &certs), label, source)
+                                .with_native_key(crate::platform::NativeKey::Pkcs11(key)));
+        }
+    }
+
+    Ok(identities)
+}
+
+struct TokenCert {
+    der: Vec<u8>,
+    id: Vec<u8>,
+    label: Vec<u8>,
+}
+
+fn certificates(session: &Session) -> Result<Vec<TokenCert>, Error> {
+    let handles = session.find_objects(&[Attribute::Class(ObjectClass::CERTIFICATE)])
+        .map_err(pkcs11_error)?;
+
+    let mut certs = Vec::new();
+    for handle in handles {
+        let mut cert = TokenCert { der: Vec::new(), id: Vec::new(), label: Vec::new() };
+        let attrs = session.get_attributes(handle, &[AttributeType::Value, AttributeType::Id, AttributeType::Label])
+            .map_err(pkcs11_error)?;
+        for attr in attrs {
+            match attr {
+                Attribute::Value(der) => cert.der = der,
+                Attribute::Id(id) => cert.id = id,
+                Attribute::Label(label) => cert.label = label,
+                _ => {}
+            }
+        }
+        if !cert.der.is_empty() {
+            certs.push(cert);
+        }
+    }
+    Ok(certs)
+}
+
+fn object_id(session: &Session, object: ObjectHandle) -> Result<Vec<u8>, Error> {
+    let attrs = session.get_attributes(object, &[AttributeType::Id])
+        .map_err(pkcs11_error)?;
+    Ok(attrs.into_iter()
+        .find_map(|attr| match attr {
+            Attribute::Id(id) => Some(id),
+            _ => None,
+        })
+        .unwrap_or_default())
+}
+
+/// Returns `leaf` followed by whichever of the certificates in `pool`
+/// issued it, and so on up to a self-issued certificate.
+fn build_chain(leaf: &[u8], pool: &[TokenCert]) -> Vec<Vec<u8>> {
+    const MAX_DEPTH: usize = 8;
+
+    let mut chain = vec![leaf.to_vec()];
+    while chain.len() < MAX_DEPTH {
+        let current = match der::cert_fields(chain.last().unwrap()) {
+            Some(fields) if !fields.is_self_issued() => fields,
+            _ => break,
+        };
+        let issuer = pool.iter()
+            .map(|cert| &cert.der)
+            .find(|cert| der::cert_fields(cert)
+                .is_some_and(|fields| fields.subject == current.issuer));
+        match issuer {
+            Some(issuer) if !chain.contains(issuer) => chain.push(issuer.clone()),
+            _ => break,
+        }
+    }
+    chain
+}
+
+/// The private key of a token-resident identity, which is found (logging
+/// in if there is a PIN) only when it is needed for signing.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "rustls"), allow(dead_code))]
+pub struct TokenKey {
+    ctx: Pkcs11,
+    slot: Slot,
+    id: Vec<u8>,
+    pin: Option<Arc<AuthPin>>,
+}
+
+#[cfg(feature = "rustls")]
+pub use self::signing::signing_key;
+
+#[cfg(feature = "rustls")]
+mod signing {
+    use std::io::{Error, ErrorKind};
+    use std::sync::{Arc, Mutex};
+
+    use cryptoki::error::{Error as Pkcs11Error, RvError};
+    use cryptoki::mechanism::rsa::{PkcsMgfType, PkcsPssParams};
+    use cryptoki::mechanism::{Mechanism, MechanismType};
+    use cryptoki::object::{Attribute, ObjectClass, ObjectHandle};
+    use cryptoki::session::{Session, UserType};
+    use ring::digest;
+    use rustls::internal::msgs::enums::SignatureAlgorithm;
+    use rustls::sign::{Signer, SigningKey};
+    use rustls::{SignatureScheme, TLSError};
+
+    use super::{pkcs11_error, TokenKey};
+    use crate::der::{self, KeyType};
+    use crate::sign;
+
+    pub fn signing_key(key: &TokenKey, key_type: KeyType) -> Result<Box<dyn SigningKey>, Error> {
+        let key = key.clone();
+        Ok(Box::new(TokenSigningKey {
+            key: sign::LazyKey::new(move || open(&key)),
+            key_type,
+        }))
+    }
+
+    /// A session on the token, logged in if there is a PIN, and the private
+    /// key found through it.
+    struct OpenKey {
+        session: Mutex<Session>,
+        handle: ObjectHandle,
+    }
+
+    fn open(key: &TokenKey) -> Result<OpenKey, Error> {
+        let session = key.ctx.open_ro_session(key.slot).map_err(pkcs11_error)?;
+        if let Some(pin) = &key.pin {
+            match session.login(UserType::User, Some(pin)) {
+                Ok(()) | Err(Pkcs11Error::Pkcs11(RvError::UserAlreadyLoggedIn, _)) => {}
+                Err(err) => return Err(pkcs11_error(err)),
+            }
+        }
+
+        let template = [
+            Attribute::Class(ObjectClass::PRIVATE_KEY),
+            Attribute::Id(key.id.clone()),
+        ];
+        let handle = session.find_objects(&template)
+            .map_err(pkcs11_error)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, match key.pin {
+                Some(_) => "no private key found on the PKCS#11 token",
+                None => "no private key found on the PKCS#11 token; it may need a pin-value or pin-source",
+            }))?;
+
+        Ok(OpenKey {
+            session: Mutex::new(session),
+            handle,
+        })
+    }
+
+    /// A `rustls::sign::SigningKey` which signs on a PKCS#11 token.
+    struct TokenSigningKey {
+        key: Arc<sign::LazyKey<OpenKey>>,
+        key_type: KeyType,
+    }
+
+    impl SigningKey for TokenSigningKey {
+        fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+            sign::choose_scheme(self.key_type, offered)
+                .map(|scheme| Box::new(TokenSigner {
+                    key: self.key.clone(),
+                    scheme,
+                }) as Box<dyn Signer>)
+        }
+
+        fn algorithm(&self) -> SignatureAlgorithm {
+            sign::algorithm(self.key_type)
+        }
+    }
+
+    struct TokenSigner {
+        key: Arc<sign::LazyKey<OpenKey>>,
+        scheme: SignatureScheme,
+    }
+
+    fn pss(hash_alg: MechanismType, mgf: PkcsMgfType, s_len: u64) -> PkcsPssParams {
+        PkcsPssParams { hash_alg, mgf, s_len: s_len.into() }
+    }
+
+    impl Signer for TokenSigner {
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>, TLSError> {
+            // Many tokens only implement raw ECDSA, so hash for them.
+            let (mechanism, input) = match self.scheme {
+                SignatureScheme::RSA_PSS_SHA256 => (Mechanism::Sha256RsaPkcsPss(
+                    pss(MechanismType::SHA256, PkcsMgfType::MGF1_SHA256, 32)), message.to_vec()),
+                SignatureScheme::RSA_PSS_SHA384 => (Mechanism::Sha384RsaPkcsPss(
+                    pss(MechanismType::SHA384, PkcsMgfType::MGF1_SHA384, 48)), message.to_vec()),
+                SignatureScheme::RSA_PSS_SHA512 => (Mechanism::Sha512RsaPkcsPss(
+                    pss(MechanismType::SHA512, PkcsMgfType::MGF1_SHA512, 64)), message.to_vec()),
+                SignatureScheme::RSA_PKCS1_SHA256 => (Mechanism::Sha256RsaPkcs, message.to_vec()),
+                SignatureScheme::RSA_PKCS1_SHA384 => (Mechanism::Sha384RsaPkcs, message.to_vec()),
+                SignatureScheme::RSA_PKCS1_SHA512 => (Mechanism::Sha512RsaPkcs, message.to_vec()),
+                SignatureScheme::ECDSA_NISTP256_SHA256 =>
+                    (Mechanism::Ecdsa, digest::digest(&digest::SHA256, message).as_ref().to_vec()),
+                SignatureScheme::ECDSA_NISTP384_SHA384 =>
+                    (Mechanism::Ecdsa, digest::digest(&digest::SHA384, message).as_ref().to_vec()),
+                _ => return Err(TLSError::General("unsupported signature scheme".into())),
+            };
+
+            let key = self.key.get()?;
+            let session = key.session.lock().unwrap_or_else(|err| err.into_inner());
+            let signature = session.sign(&mechanism, key.handle, &input)
+                .map_err(|err| TLSError::General(format!("PKCS#11 signing failed: {}", err)))?;
+
+            match mechanism {
+                Mechanism::Ecdsa => Ok(der::ecdsa_signature(&signature)),
+                _ => Ok(signature),
+            }
+        }
+
+        fn get_scheme(&self) -> SignatureScheme {
+            self.scheme
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::{BufRead, Error};
+#[cfg(feature = "webpki")]
+use std::io::ErrorKind;
+
+use rustls_pki_types::CertificateDer;
+#[cfg(feature = "webpki")]
+use rustls_pki_types::TrustAnchor;
+
+use crate::RootStoreBuilder;
+
+/// Collects roots as `CertificateDer`s, for TLS stacks which take them.
+///
+/// *This implementation is available only if the crate is built with the "pki-types" feature.*
+impl RootStoreBuilder for Vec<CertificateDer<'static>> {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.push(CertificateDer::from(der));
+        Ok(())
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        self.extend(rustls_pemfile::certs(rd)?.into_iter().map(CertificateDer::from));
+        Ok(())
+    }
+}
+
+/// Loads root certificates found in the platform's native certificate
+/// store, as the `CertificateDer`s of rustls-pki-types.
+///
+/// Unlike [load_native_certs](fn.load_native_certs.html), this ties
+/// callers to no version of rustls: rustls 0.22 and later, webpki and
+/// other stacks built on rustls-pki-types take these directly.  This fails
+/// in a platform-specific way, expressed in a `std::io::Error`, if any of
+/// the store cannot be loaded; use a [NativeCertLoader](struct.NativeCertLoader.html),
+/// which builds a `Vec<CertificateDer>` too, to tolerate that.
+///
+/// *This function is available only if the crate is built with the "pki-types" feature.*
+pub fn load_native_certs_der() -> Result<Vec<CertificateDer<'static>>, Error> {
+    let mut roots = Vec::new();
+    crate::build_native_certs(&mut roots)?;
+    Ok(roots)
+}
+
+/// The trust anchors parsed from the native certificate store, as returned
+/// by [load_native_trust_anchors](fn.load_native_trust_anchors.html).
+///
+/// *This type is available only if the crate is built with the "webpki" feature.*
+#[cfg(feature = "webpki")]
+#[derive(Debug)]
+pub struct NativeTrustAnchors {
+    anchors: Vec<TrustAnchor<'static>>,
+    errors: Vec<Error>,
+}
+
+#[cfg(feature = "webpki")]
+impl NativeTrustAnchors {
+    /// Returns the trust anchors.
+    pub fn anchors(&self) -> &[TrustAnchor<'static>] {
+        &self.anchors
+    }
+
+    /// Returns the trust anchors, to build a verifier from.
+    pub fn into_anchors(self) -> Vec<TrustAnchor<'static>> {
+        self.anchors
+    }
+
+    /// Returns why each root left out could not be parsed.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+}
+
+/// Loads root certificates found in the platform's native certificate
+/// store, parsed by rustls-webpki into the trust anchors of
+/// rustls-pki-types.
+///
+/// A verifier is built from trust anchors, so parsing the roots here
+/// spares it a second pass over them.  Roots which cannot be parsed are
+/// left out, and each is reported in [errors](struct.NativeTrustAnchors.html#method.errors)
+/// with `ErrorKind::InvalidData`.  Failing to read the store is an error,
+/// expressed in a `std::io::Error`.
+///
+/// *This function is available only if the crate is built with the "webpki" feature.*
+#[cfg(feature = "webpki")]
+pub fn load_native_trust_anchors() -> Result<NativeTrustAnchors, Error> {
+    let mut loaded = NativeTrustAnchors {
+        anchors: Vec::new(),
+        errors: Vec::new(),
+    };
+    for (i, root) in load_native_certs_der()?.into_iter().enumerate() {
+        match webpki102::anchor_from_trusted_cert(&root) {
+            Ok(anchor) => loaded.anchors.push(anchor.to_owned()),
+            Err(err) => loaded.errors.push(Error::new(ErrorKind::InvalidData,
+                                                      format!("root {} could not be parsed as a trust anchor: {}",
+                                                              i, err))),
+        }
+    }
+    Ok(loaded)
+}
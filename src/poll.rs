@@ -0,0 +1,36 @@
+use std::io::Error;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::snapshot::Snapshot;
+
+/// Watches the platform's native certificate store by reloading it periodically.
+///
+/// The polling thread stops when this is dropped.
+pub struct PollingWatcher {
+    _stop: mpsc::Sender<()>,
+}
+
+/// Calls `on_change` whenever the contents of the platform's native
+/// certificate store differ from when they were last checked, checking
+/// every `interval`.
+pub fn watch(interval: Duration, mut on_change: impl FnMut() + Send + 'static) -> Result<PollingWatcher, Error> {
+    let (stop, stopped) = mpsc::channel::<()>();
+    let load = || Snapshot::load().unwrap_or_else(|(partial, _)| partial);
+    let mut last = load();
+
+    thread::Builder::new()
+        .name("rustls-native-certs poller".into())
+        .spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let current = load();
+                if current != last {
+                    last = current;
+                    on_change();
+                }
+            }
+        })?;
+
+    Ok(PollingWatcher { _stop: stop })
+}
@@ -0,0 +1,70 @@
+/// A use of certificates which platforms record trust in separately.
+///
+/// A root may be trusted to identify TLS servers but not to vouch for
+/// email, for example, so pass the purpose the roots are for to
+/// [build_native_certs_for](fn.build_native_certs_for.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Purpose {
+    /// Authenticating TLS servers.  This is what
+    /// [build_native_certs](fn.build_native_certs.html) loads roots for.
+    ServerAuth,
+    /// Protecting email with S/MIME.
+    EmailProtection,
+    /// Signing code, such as executables and packages.
+    CodeSigning,
+    /// Issuing RFC 3161 timestamps, as a time-stamping authority does.
+    TimeStamping,
+}
+
+impl Purpose {
+    /// Every purpose, in the order of their declaration.
+    #[cfg(any(feature = "capture", feature = "cli", feature = "serde"))]
+    pub(crate) const ALL: [Purpose; 4] = [
+        Purpose::ServerAuth,
+        Purpose::EmailProtection,
+        Purpose::CodeSigning,
+        Purpose::TimeStamping,
+    ];
+
+    /// Returns the name by which this purpose is written in captures, and
+    /// given to the `native-certs` command.
+    #[cfg(any(feature = "capture", feature = "cli", feature = "serde"))]
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Purpose::ServerAuth => "server-auth",
+            Purpose::EmailProtection => "email-protection",
+            Purpose::CodeSigning => "code-signing",
+            Purpose::TimeStamping => "time-stamping",
+        }
+    }
+
+    /// Returns the purpose with the given [name](#method.name).
+    #[cfg(any(feature = "capture", feature = "cli"))]
+    pub(crate) fn from_name(name: &str) -> Option<Purpose> {
+        Purpose::ALL.iter().copied().find(|purpose| purpose.name() == name)
+    }
+
+    /// Returns the extended key usage identifying this purpose, in dotted
+    /// decimal form, as Windows gives it.
+    #[cfg_attr(any(not(windows), feature = "mock-backend"), allow(dead_code))]
+    pub(crate) fn oid(self) -> &'static str {
+        match self {
+            Purpose::ServerAuth => "1.3.6.1.5.5.7.3.1",
+            Purpose::EmailProtection => "1.3.6.1.5.5.7.3.4",
+            Purpose::CodeSigning => "1.3.6.1.5.5.7.3.3",
+            Purpose::TimeStamping => "1.3.6.1.5.5.7.3.8",
+        }
+    }
+
+    /// Returns the contents of the DER encoding of [oid](#method.oid).
+    #[cfg_attr(any(not(unix), target_vendor = "apple", feature = "mock-backend"), allow(dead_code))]
+    pub(crate) fn oid_der(self) -> &'static [u8] {
+        match self {
+            Purpose::ServerAuth => &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01],
+            Purpose::EmailProtection => &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x04],
+            Purpose::CodeSigning => &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x03],
+            Purpose::TimeStamping => &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x08],
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::io::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use rustls::{
+    Certificate,
+    ClientConfig,
+    RootCertStore,
+    ServerCertVerified,
+    ServerCertVerifier,
+    TLSError,
+    WebPKIVerifier,
+};
+
+use crate::persist;
+use crate::snapshot::Snapshot;
+use crate::{CertWatcher, CertWatcherBuilder, ChangeEvent};
+
+/// A `rustls::RootCertStore` loaded from the platform's native certificate
+/// store, which is reloaded whenever that store changes.
+///
+/// Each call to [current](#method.current) or [client_config](#method.client_config)
+/// observes the latest snapshot, so long-running processes pick up newly
+/// installed or removed roots without restarting.  Configs and connections
+/// created earlier keep the snapshot they were built with.
+///
+/// If a reload fails, or finds the store empty, the previous snapshot is
+/// kept: these usually mean the store was caught partway through an update,
+/// and swapping in an empty or incomplete trust set would break every new
+/// connection until the next reload.
+///
+/// *This type is available only if the crate is built with the "rustls" and "watch" features.*
+pub struct ReloadableRoots {
+    current: Arc<ArcSwap<RootCertStore>>,
+    _watcher: CertWatcher,
+}
+
+impl ReloadableRoots {
+    /// Loads root certificates found in the platform's native certificate
+    /// store, and starts watching it for changes.
+    ///
+    /// This fails if the initial load fails without producing any certificates.
+    pub fn new() -> Result<Self, Error> {
+        Self::builder().build()
+    }
+
+    /// Returns a builder for a `ReloadableRoots` with non-default options.
+    pub fn builder() -> ReloadableRootsBuilder {
+        ReloadableRootsBuilder::default()
+    }
+    /// Returns the most recently loaded snapshot of the native roots.
+    pub fn current(&self) -> Arc<RootCertStore> {
+        self.current.load_full()
+    }
+
+    /// Returns a new `rustls::ClientConfig` whose `root_store` is the most
+    /// recently loaded snapshot of the native roots.
+    pub fn client_config(&self) -> ClientConfig {
+        let mut config = ClientConfig::new();
+        config.root_store = RootCertStore::clone(&self.current.load());
+        config
+    }
+
+    /// Returns a `rustls::ServerCertVerifier` which validates server
+    /// certificates against the most recently loaded native roots.
+    pub fn verifier(&self) -> Arc<ReloadingVerifier> {
+        Arc::new(ReloadingVerifier {
+            current: self.current.clone(),
+            inner: WebPKIVerifier::new(),
+        })
+    }
+
+    /// Returns a new `rustls::ClientConfig` which uses [verifier](#method.verifier).
+    ///
+    /// Unlike [client_config](#method.client_config), a single config obtained
+    /// from here can be kept for the life of the process: each handshake
+    /// validates against whatever roots are current at that moment.
+    pub fn live_client_config(&self) -> ClientConfig {
+        let mut config = ClientConfig::new();
+        config.dangerous()
+            .set_certificate_verifier(self.verifier());
+        config
+    }
+}
+
+/// Options for a [ReloadableRoots](struct.ReloadableRoots.html).
+///
+/// *This type is available only if the crate is built with the "rustls" and "watch" features.*
+#[derive(Clone, Debug, Default)]
+pub struct ReloadableRootsBuilder {
+    watcher: CertWatcherBuilder,
+    snapshot_file: Option<PathBuf>,
+}
+
+impl ReloadableRootsBuilder {
+    /// Watches the store for changes using the given options.
+    pub fn watcher(mut self, watcher: CertWatcherBuilder) -> Self {
+        self.watcher = watcher;
+        self
+    }
+
+    /// Keeps a copy of the last good set of roots in the file at `path`.
+    ///
+    /// The file is rewritten after each successful load.  If, at startup, the
+    /// native store fails to load or is empty -- for example because the
+    /// process restarted in the middle of an operating system update to the
+    /// store -- the roots are instead taken from this file, and loading the
+    /// native store is retried in the background until it succeeds.
+    ///
+    /// The file carries an integrity hash, and is ignored if that does not match.
+    /// Failures to write the file are also ignored.
+    pub fn snapshot_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.snapshot_file = Some(path.into());
+        self
+    }
+
+    /// Loads root certificates found in the platform's native certificate
+    /// store, and starts watching it for changes.
+    ///
+    /// See [ReloadableRoots::new](struct.ReloadableRoots.html#method.new) for how this can fail.
+    pub fn build(self) -> Result<ReloadableRoots, Error> {
+        let snapshot_file = self.snapshot_file.map(Arc::new);
+        let save = {
+            let snapshot_file = snapshot_file.clone();
+            move |snapshot: &Snapshot| {
+                if let Some(path) = &snapshot_file {
+                    let _ = persist::save(path, snapshot);
+                }
+            }
+        };
+
+        let (initial, from_file) = match Snapshot::load() {
+            Ok(live) if !live.is_empty() => {
+                save(&live);
+                (live, false)
+            }
+            live => match snapshot_file.as_deref().map(|path| persist::load(path)) {
+                Some(Ok(saved)) if !saved.is_empty() => (saved, true),
+                _ => match live {
+                    Ok(empty) => (empty, false),
+                    Err((partial, _)) if !partial.is_empty() => (partial, false),
+                    Err((_, err)) => return Err(err),
+                },
+            },
+        };
+
+        let current = Arc::new(ArcSwap::from_pointee(root_store(&initial)));
+        let subscriber = {
+            let current = current.clone();
+            let save = save.clone();
+            // The watcher reports changes from the snapshot served here,
+            // and the load it reports is the one taken.
+            Box::new(move |event: &ChangeEvent, live: &Snapshot| {
+                if let ChangeEvent::Changed(_) = event {
+                    current.store(Arc::new(root_store(live)));
+                    save(live);
+                }
+                true
+            })
+        };
+        let watcher = self.watcher.start(initial, vec![subscriber])?;
+
+        if from_file {
+            let current = Arc::downgrade(&current);
+            thread::Builder::new()
+                .name("rustls-native-certs retry".into())
+                .spawn(move || retry_live_load(&current, save))?;
+        }
+
+        Ok(ReloadableRoots {
+            current,
+            _watcher: watcher,
+        })
+    }
+}
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Tries to load the native store until that succeeds, or until the
+/// `ReloadableRoots` owning `current` is dropped.
+fn retry_live_load(current: &Weak<ArcSwap<RootCertStore>>, save: impl Fn(&Snapshot)) {
+    loop {
+        thread::sleep(RETRY_INTERVAL);
+
+        let current = match current.upgrade() {
+            Some(current) => current,
+            None => return,
+        };
+
+        if let Ok(live) = Snapshot::load() {
+            if !live.is_empty() {
+                current.store(Arc::new(root_store(&live)));
+                save(&live);
+                return;
+            }
+        }
+    }
+}
+
+fn root_store(snapshot: &Snapshot) -> RootCertStore {
+    let mut store = RootCertStore::empty();
+    for der in snapshot.ders() {
+        let _ = store.add(&Certificate(der.to_vec()));
+    }
+    store
+}
+
+/// A `rustls::ServerCertVerifier` backed by a [ReloadableRoots](struct.ReloadableRoots.html).
+///
+/// This performs the same checks as `rustls::WebPKIVerifier`, but ignores the
+/// `root_store` of the `ClientConfig` it is used with: instead, it validates
+/// against the snapshot of native roots current at the time of each handshake.
+/// Roots stop being updated once the `ReloadableRoots` it came from is dropped.
+///
+/// *This type is available only if the crate is built with the "rustls" and "watch" features.*
+pub struct ReloadingVerifier {
+    current: Arc<ArcSwap<RootCertStore>>,
+    inner: WebPKIVerifier,
+}
+
+impl ServerCertVerifier for ReloadingVerifier {
+    fn verify_server_cert(
+        &self,
+        _roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        dns_name: webpki::DNSNameRef,
+        ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        self.inner.verify_server_cert(&self.current.load(), presented_certs, dns_name, ocsp_response)
+    }
+}
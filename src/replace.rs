@@ -0,0 +1,50 @@
+//! Atomic replacement of the files the crate writes, such as exported
+//! bundles and snapshots.
+
+use std::fs::{self, File};
+use std::io::Error;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Replaces the file at `path` with what `write` writes, so that a reader
+/// sees either the old file or the whole of the new one.
+///
+/// What `write` writes goes to a temporary file beside `path`, whose name is
+/// unique to this process and call, so that concurrent writers to one path
+/// do not write to each other's file.  It is synced before being renamed
+/// over `path`, and the directory after, so that the rename survives a
+/// crash.  If anything fails, the temporary file is removed.
+pub(crate) fn replace_file(path: &Path, write: impl FnOnce(&mut File) -> Result<(), Error>) -> Result<(), Error> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(format!(".{}.{}.tmp", std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed)));
+    let tmp_path = Path::new(&tmp_name);
+
+    let mut f = File::create_new(tmp_path)?;
+    let result = write(&mut f).and_then(|()| f.sync_all());
+    drop(f);
+    let result = result.and_then(|()| fs::rename(tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(tmp_path);
+    }
+    result?;
+
+    sync_parent(path)
+}
+
+/// Syncs the directory holding `path`, so that a rename into it is durable.
+/// Elsewhere, as on Windows, a directory cannot be opened to be synced.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> Result<(), Error> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> Result<(), Error> {
+    Ok(())
+}
@@ -1,4 +1,6 @@
 use rustls::RootCertStore;
+#[cfg(feature = "webpki-roots")]
+use std::collections::HashSet;
 use std::io::{Error, ErrorKind};
 use std::io::BufRead;
 use crate::RootStoreBuilder;
@@ -24,7 +26,7 @@ pub type PartialResult<T, E> = Result<T, (Option<T>, E)>;
 pub fn load_native_certs() -> PartialResult<RootCertStore, Error> {
     struct RootCertStoreLoader {
         store: RootCertStore,
-    };
+    }
     impl RootStoreBuilder for RootCertStoreLoader {
         fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
             self.store.add(&rustls::Certificate(der))
@@ -33,7 +35,7 @@ pub fn load_native_certs() -> PartialResult<RootCertStore, Error> {
         fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
             self.store.add_pem_file(rd)
                 .map(|_| ())
-                .map_err(|()| Error::new(ErrorKind::InvalidData, format!("could not load PEM file")))
+                .map_err(|()| Error::new(ErrorKind::InvalidData, "could not load PEM file"))
         }
     }
     let mut loader = RootCertStoreLoader {
@@ -45,3 +47,152 @@ pub fn load_native_certs() -> PartialResult<RootCertStore, Error> {
         Ok(()) => Ok(loader.store),
     }
 }
+
+/// Like [load_native_certs](fn.load_native_certs.html), but loading on
+/// tokio's blocking thread pool, so as not to hold up the executor while
+/// the store is read.
+///
+/// This must be called from within a tokio runtime.  If the runtime shuts
+/// down before the load finishes, this fails with no roots.
+///
+/// ```no_run
+/// # async fn run() {
+/// let roots = rustls_native_certs::load_native_certs_async()
+///     .await
+///     .expect("could not load platform certs");
+/// # }
+/// ```
+///
+/// *This function is available only if the crate is built with the "rustls" and "tokio" features.*
+#[cfg(feature = "tokio")]
+pub async fn load_native_certs_async() -> PartialResult<RootCertStore, Error> {
+    match tokio::task::spawn_blocking(load_native_certs).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => Err((None, Error::other(err))),
+    }
+}
+
+/// Loads root certificates found in the platform's native certificate
+/// store, together with the Mozilla roots bundled in the webpki-roots
+/// crate, for minimal containers and CI images which ship no CA
+/// certificates.
+///
+/// Where the native store is empty, or cannot be read, the roots are the
+/// Mozilla roots alone; a failure is not returned, as there are roots
+/// either way.  A Mozilla root with the same subject and key as a native
+/// root is not added again, nor is one the platform explicitly distrusts,
+/// as [load_native_distrusted](fn.load_native_distrusted.html) finds them.
+/// Because the Mozilla roots are otherwise always added, a root an
+/// administrator only removed from the native store is trusted anyway:
+/// call [load_native_certs](fn.load_native_certs.html) where such removals
+/// must hold.
+///
+/// ```no_run
+/// let mut config = rustls::ClientConfig::new();
+/// config.root_store = rustls_native_certs::load_native_certs_or_fallback();
+/// ```
+///
+/// *This function is available only if the crate is built with the "webpki-roots" feature.*
+#[cfg(feature = "webpki-roots")]
+pub fn load_native_certs_or_fallback() -> RootCertStore {
+    let mut roots = match load_native_certs() {
+        Ok(roots) => roots,
+        Err((roots, err)) => {
+            debug!("adding Mozilla's roots to {} native roots: {}",
+                   roots.as_ref().map_or(0, RootCertStore::len), err);
+            roots.unwrap_or_else(RootCertStore::empty)
+        }
+    };
+
+    let native: HashSet<(Vec<u8>, Vec<u8>)> = roots.roots
+        .iter()
+        .map(|root| {
+            let anchor = root.to_trust_anchor();
+            (anchor.subject.to_vec(), anchor.spki.to_vec())
+        })
+        .collect();
+    let distrusted: HashSet<(Vec<u8>, Vec<u8>)> = match crate::load_native_distrusted() {
+        Ok(distrusted) => distrusted.iter()
+            .filter_map(|der| {
+                let fields = crate::der::cert_fields(der)?;
+                Some((crate::der::contents(fields.subject)?.to_vec(),
+                      crate::der::contents(fields.public_key)?.to_vec()))
+            })
+            .collect(),
+        Err(err) => {
+            debug!("adding all of Mozilla's roots, as the distrusted certificates cannot be read: {}", err);
+            HashSet::new()
+        }
+    };
+    for anchor in webpki_roots::TLS_SERVER_ROOTS.0 {
+        let key = (anchor.subject.to_vec(), anchor.spki.to_vec());
+        if !native.contains(&key) && !distrusted.contains(&key) {
+            roots.roots.push(rustls::OwnedTrustAnchor::from_trust_anchor(anchor));
+        }
+    }
+    roots
+}
+
+/// A `rustls::RootCertStore` loaded from the native certificate store, as
+/// returned by [load_native_root_store](fn.load_native_root_store.html).
+///
+/// *This type is available only if the crate is built with the "rustls" feature.*
+#[derive(Clone)]
+pub struct NativeRootStore {
+    roots: RootCertStore,
+    rejected: usize,
+}
+
+impl NativeRootStore {
+    /// Returns the roots rustls accepted.
+    pub fn roots(&self) -> &RootCertStore {
+        &self.roots
+    }
+
+    /// Returns the roots rustls accepted, for use in a `ClientConfig`.
+    pub fn into_roots(self) -> RootCertStore {
+        self.roots
+    }
+
+    /// Returns how many of the native store's roots rustls rejected, as it
+    /// does those it cannot parse.
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+}
+
+/// Loads root certificates found in the platform's native certificate
+/// store into a `rustls::RootCertStore`, counting those rustls rejects.
+///
+/// Unlike [load_native_certs](fn.load_native_certs.html), roots which
+/// rustls cannot parse don't fail the load: some stores carry a few, and
+/// most callers want the rest.  Failing to read the store is still an
+/// error, expressed in a `std::io::Error`.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let loaded = rustls_native_certs::load_native_root_store()?;
+/// if loaded.rejected() > 0 {
+///     eprintln!("rustls rejected {} native roots", loaded.rejected());
+/// }
+/// let mut config = rustls::ClientConfig::new();
+/// config.root_store = loaded.into_roots();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// *This function is available only if the crate is built with the "rustls" feature.*
+pub fn load_native_root_store() -> Result<NativeRootStore, Error> {
+    let mut loaded = NativeRootStore {
+        roots: RootCertStore::empty(),
+        rejected: 0,
+    };
+    for der in crate::NativeCertLoader::new().load()? {
+        if let Err(err) = loaded.roots.add(&rustls::Certificate(der)) {
+            debug!("rustls rejected a root: {:?}", err);
+            loaded.rejected += 1;
+        }
+    }
+    Ok(loaded)
+}
@@ -0,0 +1,47 @@
+//! Builders for the root stores of the rustls versions other than the 0.19
+//! the `rustls` feature builds against.
+
+use std::io::{BufRead, Error, ErrorKind};
+
+use crate::RootStoreBuilder;
+
+/// Adds each root to a rustls 0.21 `RootCertStore`.  A root rustls cannot
+/// parse fails the load with `ErrorKind::InvalidData`.
+///
+/// *This implementation is available only if the crate is built with the "rustls-021" feature.*
+#[cfg(feature = "rustls-021")]
+impl RootStoreBuilder for rustls021::RootCertStore {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.add(&rustls021::Certificate(der))
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        for der in rustls_pemfile::certs(rd)? {
+            self.load_der(der)?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds each root to a rustls 0.22 `RootCertStore`.  A root rustls cannot
+/// parse fails the load with `ErrorKind::InvalidData`.
+///
+/// rustls 0.23 and later take the `CertificateDer`s a `Vec` builds with the
+/// `pki-types` feature.
+///
+/// *This implementation is available only if the crate is built with the "rustls-022" feature.*
+#[cfg(feature = "rustls-022")]
+impl RootStoreBuilder for rustls022::RootCertStore {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.add(rustls022::pki_types::CertificateDer::from(der))
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        for der in rustls_pemfile::certs(rd)? {
+            self.load_der(der)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+//! Serde support, written against `serde_core` so that it is the same
+//! `Serialize` and `Deserialize` as `serde` re-exports.
+//!
+//! Enums are written as the names the `native-certs` command uses, and
+//! provenance as one string, like `file:/etc/ssl/cert.pem`, so that a cached
+//! store reads well as JSON.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde_core::de::{self, Deserialize, Deserializer, Visitor};
+use serde_core::ser::{self, Serialize, Serializer};
+
+use crate::{PlatformTrust, Provenance, Purpose, TrustDomain};
+
+/// A type written as one of a fixed set of names.
+trait Named: Sized + Copy + 'static {
+    const ALL: &'static [Self];
+    const EXPECTING: &'static str;
+
+    fn name(self) -> &'static str;
+}
+
+impl Named for Purpose {
+    const ALL: &'static [Self] = &Purpose::ALL;
+    const EXPECTING: &'static str = "a purpose, such as \"server-auth\"";
+
+    fn name(self) -> &'static str {
+        Purpose::name(self)
+    }
+}
+
+impl Named for TrustDomain {
+    const ALL: &'static [Self] = &TrustDomain::ALL;
+    const EXPECTING: &'static str = "a trust domain: \"user\", \"admin\" or \"system\"";
+
+    fn name(self) -> &'static str {
+        match self {
+            TrustDomain::User => "user",
+            TrustDomain::Admin => "admin",
+            TrustDomain::System => "system",
+        }
+    }
+}
+
+impl Named for PlatformTrust {
+    const ALL: &'static [Self] = &[
+        PlatformTrust::InStore,
+        PlatformTrust::TrustRoot,
+        PlatformTrust::TrustAsRoot,
+        PlatformTrust::Deny,
+    ];
+    const EXPECTING: &'static str = "a platform trust: \"in-store\", \"trust-root\", \"trust-as-root\" or \"deny\"";
+
+    fn name(self) -> &'static str {
+        match self {
+            PlatformTrust::InStore => "in-store",
+            PlatformTrust::TrustRoot => "trust-root",
+            PlatformTrust::TrustAsRoot => "trust-as-root",
+            PlatformTrust::Deny => "deny",
+        }
+    }
+}
+
+fn from_name<T: Named>(name: &str) -> Option<T> {
+    T::ALL.iter().copied().find(|value| value.name() == name)
+}
+
+/// Parses a string with the function it holds, which returns `None` for
+/// anything but what the message it holds describes.
+struct StrVisitor<F>(&'static str, F);
+
+impl<'de, T, F: FnOnce(&str) -> Option<T>> Visitor<'de> for StrVisitor<F> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<T, E> {
+        let expecting = self.0;
+        (self.1)(s).ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &expecting))
+    }
+}
+
+macro_rules! named {
+    ($($ty:ty),*) => {$(
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(Named::name(*self))
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_str(StrVisitor(<$ty as Named>::EXPECTING, from_name::<$ty>))
+            }
+        }
+    )*};
+}
+
+named!(Purpose, TrustDomain, PlatformTrust);
+
+impl Provenance {
+    fn to_text(&self) -> Option<String> {
+        Some(match self {
+            Provenance::File(path) => format!("file:{}", path.to_str()?),
+            Provenance::TrustSettings(domain) => format!("trust-settings:{}", domain.name()),
+            Provenance::WindowsStore(name) => format!("windows-store:{}", name),
+            Provenance::Unknown => "unknown".to_owned(),
+        })
+    }
+
+    fn from_text(text: &str) -> Option<Self> {
+        if text == "unknown" {
+            return Some(Provenance::Unknown);
+        }
+        let (kind, location) = text.split_once(':')?;
+        match kind {
+            "file" => Some(Provenance::File(PathBuf::from(location))),
+            "trust-settings" => from_name(location).map(Provenance::TrustSettings),
+            "windows-store" => Some(Provenance::WindowsStore(location.to_owned())),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Provenance {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let text = self.to_text()
+            .ok_or_else(|| <S::Error as ser::Error>::custom("path is not valid UTF-8"))?;
+        serializer.serialize_str(&text)
+    }
+}
+
+impl<'de> Deserialize<'de> for Provenance {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(StrVisitor("a provenance, such as \"file:/etc/ssl/cert.pem\"",
+                                                Provenance::from_text))
+    }
+}
+
+#[cfg(feature = "metadata")]
+mod native_cert {
+    use std::fmt;
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde_core::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+    use serde_core::ser::{Serialize, SerializeStruct, Serializer};
+
+    use crate::{NativeCert, PlatformTrust, Provenance};
+
+    const FIELDS: &[&str] = &["der", "provenance", "trust", "host_restrictions", "managed", "allowed_errors"];
+
+    /// The DER is written as base64 in human-readable formats, as the
+    /// `native-certs` command's JSON has it, and as bytes in the others.
+    /// The fields after `trust` are left out after the last which is not
+    /// empty, or `false`, so formats which read fields in order find those
+    /// written.
+    impl Serialize for NativeCert {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let human_readable = serializer.is_human_readable();
+            let hosts = self.host_restrictions();
+            let allowed_errors = self.allowed_errors();
+            let optional = [!hosts.is_empty(), self.is_managed(), !allowed_errors.is_empty()];
+            let written = optional.iter().rposition(|&set| set).map_or(0, |last| last + 1);
+
+            let mut cert = serializer.serialize_struct("NativeCert", FIELDS.len() - optional.len() + written)?;
+            if human_readable {
+                cert.serialize_field("der", &STANDARD.encode(self.der()))?;
+            } else {
+                cert.serialize_field("der", &Bytes(self.der()))?;
+            }
+            cert.serialize_field("provenance", self.provenance())?;
+            cert.serialize_field("trust", &self.trust())?;
+            if written > 0 {
+                cert.serialize_field("host_restrictions", hosts)?;
+            } else {
+                cert.skip_field("host_restrictions")?;
+            }
+            if written > 1 {
+                cert.serialize_field("managed", &self.is_managed())?;
+            } else {
+                cert.skip_field("managed")?;
+            }
+            if written > 2 {
+                cert.serialize_field("allowed_errors", allowed_errors)?;
+            } else {
+                cert.skip_field("allowed_errors")?;
+            }
+            cert.end()
+        }
+    }
+
+    struct Bytes<'a>(&'a [u8]);
+
+    impl Serialize for Bytes<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    /// The DER, given as base64 or as bytes.
+    struct Der(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for Der {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct DerVisitor;
+
+            impl<'de> Visitor<'de> for DerVisitor {
+                type Value = Der;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("a DER-encoded certificate, as bytes or base64")
+                }
+
+                fn visit_str<E: de::Error>(self, s: &str) -> Result<Der, E> {
+                    STANDARD.decode(s)
+                        .map(Der)
+                        .map_err(|_| E::invalid_value(de::Unexpected::Str(s), &self))
+                }
+
+                fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Der, E> {
+                    Ok(Der(bytes.to_vec()))
+                }
+
+                fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Der, E> {
+                    Ok(Der(bytes))
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Der, A::Error> {
+                    let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                    while let Some(byte) = seq.next_element()? {
+                        bytes.push(byte);
+                    }
+                    Ok(Der(bytes))
+                }
+            }
+
+            if deserializer.is_human_readable() {
+                deserializer.deserialize_str(DerVisitor)
+            } else {
+                deserializer.deserialize_byte_buf(DerVisitor)
+            }
+        }
+    }
+
+    /// The fingerprint is not written, but worked out again from the DER,
+    /// so a cache cannot give a certificate another's.
+    impl<'de> Deserialize<'de> for NativeCert {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct CertVisitor;
+
+            impl<'de> Visitor<'de> for CertVisitor {
+                type Value = NativeCert;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("a certificate with its provenance and trust")
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<NativeCert, A::Error> {
+                    let missing = |i| de::Error::invalid_length(i, &self);
+                    let der: Der = seq.next_element()?.ok_or_else(|| missing(0))?;
+                    let provenance = seq.next_element()?.ok_or_else(|| missing(1))?;
+                    let trust = seq.next_element()?.ok_or_else(|| missing(2))?;
+                    let hosts = seq.next_element()?.unwrap_or_default();
+                    let managed = seq.next_element()?.unwrap_or_default();
+                    let allowed_errors = seq.next_element()?.unwrap_or_default();
+                    Ok(NativeCert::new(der.0, provenance, trust)
+                        .with_host_restrictions(hosts)
+                        .with_managed(managed)
+                        .with_allowed_errors(allowed_errors))
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<NativeCert, A::Error> {
+                    let mut der: Option<Der> = None;
+                    let mut provenance: Option<Provenance> = None;
+                    let mut trust: Option<PlatformTrust> = None;
+                    let mut hosts: Option<Vec<String>> = None;
+                    let mut managed: Option<bool> = None;
+                    let mut allowed_errors: Option<Vec<i32>> = None;
+                    while let Some(key) = map.next_key::<String>()? {
+                        match key.as_str() {
+                            "der" if der.is_none() => der = Some(map.next_value()?),
+                            "provenance" if provenance.is_none() => provenance = Some(map.next_value()?),
+                            "trust" if trust.is_none() => trust = Some(map.next_value()?),
+                            "host_restrictions" if hosts.is_none() => hosts = Some(map.next_value()?),
+                            "managed" if managed.is_none() => managed = Some(map.next_value()?),
+                            "allowed_errors" if allowed_errors.is_none() => allowed_errors = Some(map.next_value()?),
+                            "der" | "provenance" | "trust" | "host_restrictions" | "managed" | "allowed_errors" => {
+                                return Err(de::Error::custom(format!("duplicate field `{}`", key)))
+                            }
+                            _ => return Err(de::Error::unknown_field(&key, FIELDS)),
+                        }
+                    }
+                    Ok(NativeCert::new(der.ok_or_else(|| de::Error::missing_field("der"))?.0,
+                                       provenance.ok_or_else(|| de::Error::missing_field("provenance"))?,
+                                       trust.ok_or_else(|| de::Error::missing_field("trust"))?)
+                        .with_host_restrictions(hosts.unwrap_or_default())
+                        .with_managed(managed.unwrap_or_default())
+                        .with_allowed_errors(allowed_errors.unwrap_or_default()))
+                }
+            }
+
+            deserializer.deserialize_struct("NativeCert", FIELDS, CertVisitor)
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! SHA-256, for fingerprints and the other digests the crate takes.
+//!
+//! On macOS it comes from CommonCrypto, which every process there already
+//! links, so that Apple-only users build no hashing crate; elsewhere it
+//! comes from the `sha2` crate.
+
+/// A SHA-256 digest being computed.
+#[derive(Clone)]
+pub(crate) struct Sha256(Inner);
+
+#[cfg(not(target_os = "macos"))]
+type Inner = sha2::Sha256;
+
+#[cfg(target_os = "macos")]
+type Inner = commoncrypto::Context;
+
+impl Sha256 {
+    pub(crate) fn new() -> Self {
+        #[cfg(not(target_os = "macos"))]
+        return Sha256(sha2::Digest::new());
+
+        #[cfg(target_os = "macos")]
+        Sha256(commoncrypto::Context::new())
+    }
+
+    pub(crate) fn update(&mut self, data: impl AsRef<[u8]>) {
+        #[cfg(not(target_os = "macos"))]
+        sha2::Digest::update(&mut self.0, data);
+
+        #[cfg(target_os = "macos")]
+        self.0.update(data.as_ref());
+    }
+
+    pub(crate) fn finalize(self) -> [u8; 32] {
+        #[cfg(not(target_os = "macos"))]
+        return sha2::Digest::finalize(self.0).into();
+
+        #[cfg(target_os = "macos")]
+        self.0.finalize()
+    }
+
+    /// Returns the digest of `data`.
+    pub(crate) fn digest(data: impl AsRef<[u8]>) -> [u8; 32] {
+        let mut digest = Sha256::new();
+        digest.update(data);
+        digest.finalize()
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod commoncrypto {
+    use std::os::raw::c_int;
+
+    /// `CC_SHA256_CTX`.
+    #[derive(Clone)]
+    #[repr(C)]
+    pub(super) struct Context {
+        count: [u32; 2],
+        hash: [u32; 8],
+        wbuf: [u32; 16],
+    }
+
+    extern "C" {
+        fn CC_SHA256_Init(c: *mut Context) -> c_int;
+        fn CC_SHA256_Update(c: *mut Context, data: *const u8, len: u32) -> c_int;
+        fn CC_SHA256_Final(md: *mut u8, c: *mut Context) -> c_int;
+    }
+
+    impl Context {
+        pub(super) fn new() -> Self {
+            let mut context = Context { count: [0; 2], hash: [0; 8], wbuf: [0; 16] };
+            unsafe { CC_SHA256_Init(&mut context) };
+            context
+        }
+
+        pub(super) fn update(&mut self, data: &[u8]) {
+            // CC_LONG, the length, is 32 bits.
+            for chunk in data.chunks(u32::MAX as usize) {
+                unsafe { CC_SHA256_Update(self, chunk.as_ptr(), chunk.len() as u32) };
+            }
+        }
+
+        pub(super) fn finalize(mut self) -> [u8; 32] {
+            let mut md = [0; 32];
+            unsafe { CC_SHA256_Final(md.as_mut_ptr(), &mut self) };
+            md
+        }
+    }
+}
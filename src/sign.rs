@@ -0,0 +1,74 @@
+use std::io::Error;
+use std::sync::{Arc, Mutex};
+
+use rustls::internal::msgs::enums::SignatureAlgorithm;
+use rustls::{SignatureScheme, TLSError};
+
+use crate::der::KeyType;
+
+/// The schemes a key of each type can sign with, most preferred first.
+///
+/// This is the same order rustls uses for keys it holds itself.
+fn schemes(key_type: KeyType) -> &'static [SignatureScheme] {
+    match key_type {
+        KeyType::Rsa => &[
+            SignatureScheme::RSA_PSS_SHA512,
+            SignatureScheme::RSA_PSS_SHA384,
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA512,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA256,
+        ],
+        KeyType::EcdsaP256 => &[SignatureScheme::ECDSA_NISTP256_SHA256],
+        KeyType::EcdsaP384 => &[SignatureScheme::ECDSA_NISTP384_SHA384],
+    }
+}
+
+/// Picks the scheme a key of type `key_type` should use from those `offered`.
+pub(crate) fn choose_scheme(key_type: KeyType, offered: &[SignatureScheme]) -> Option<SignatureScheme> {
+    schemes(key_type)
+        .iter()
+        .find(|scheme| offered.contains(scheme))
+        .copied()
+}
+
+pub(crate) fn algorithm(key_type: KeyType) -> SignatureAlgorithm {
+    match key_type {
+        KeyType::Rsa => SignatureAlgorithm::RSA,
+        KeyType::EcdsaP256 | KeyType::EcdsaP384 => SignatureAlgorithm::ECDSA,
+    }
+}
+
+/// A platform key which is opened the first time something is signed with
+/// it, rather than when its `SigningKey` is made.
+///
+/// Opening a key on a smart card or token can prompt for a PIN, and rustls
+/// asks for a `SigningKey` before it knows whether the server will ask for
+/// a client certificate at all.  If opening fails, the next signature tries
+/// again, so a cancelled prompt is not fatal to later connections.
+pub(crate) struct LazyKey<K> {
+    open: Box<dyn Fn() -> Result<K, Error> + Send + Sync>,
+    key: Mutex<Option<Arc<K>>>,
+}
+
+impl<K> LazyKey<K> {
+    pub(crate) fn new(open: impl Fn() -> Result<K, Error> + Send + Sync + 'static) -> Arc<Self> {
+        Arc::new(LazyKey {
+            open: Box::new(open),
+            key: Mutex::new(None),
+        })
+    }
+
+    /// Returns the key, opening it if this is the first use.
+    pub(crate) fn get(&self) -> Result<Arc<K>, TLSError> {
+        let mut key = self.key.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(key) = &*key {
+            return Ok(key.clone());
+        }
+
+        let opened = Arc::new((self.open)()
+            .map_err(|err| TLSError::General(format!("cannot open private key: {}", err)))?);
+        *key = Some(opened.clone());
+        Ok(opened)
+    }
+}
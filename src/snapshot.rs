@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Error};
+
+use crate::sha256::Sha256;
+use crate::RootStoreBuilder;
+
+/// Every certificate in the platform's native certificate store at some
+/// point in time, keyed by the SHA-256 fingerprint of its DER encoding.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Snapshot(BTreeMap<[u8; 32], Vec<u8>>);
+
+impl Snapshot {
+    /// Loads a snapshot of the platform's native certificate store.
+    ///
+    /// If this fails partway, the error is returned along with a snapshot of
+    /// the certificates that did load.
+    pub(crate) fn load() -> Result<Self, (Self, Error)> {
+        let mut snapshot = Snapshot::default();
+        match crate::build_native_certs(&mut snapshot) {
+            Ok(()) => Ok(snapshot),
+            Err(err) => Err((snapshot, err)),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the DER encodings of the certificates, in order of fingerprint.
+    #[cfg(feature = "rustls")]
+    pub(crate) fn ders(&self) -> impl Iterator<Item = &[u8]> {
+        self.0.values().map(Vec::as_slice)
+    }
+
+    /// Returns the changes needed to turn `older` into this snapshot.
+    pub(crate) fn diff(&self, older: &Snapshot) -> StoreDiff {
+        diff_maps(&self.0, &older.0)
+    }
+}
+
+/// Returns the keys added to `older`, and removed from it, to get `newer`.
+pub(crate) fn diff_maps<V>(newer: &BTreeMap<[u8; 32], V>, older: &BTreeMap<[u8; 32], V>) -> StoreDiff {
+    let only_in = |a: &BTreeMap<[u8; 32], V>, b: &BTreeMap<[u8; 32], V>| a.keys()
+        .filter(|key| !b.contains_key(*key))
+        .copied()
+        .collect();
+
+    StoreDiff {
+        added: only_in(newer, older),
+        removed: only_in(older, newer),
+    }
+}
+
+impl RootStoreBuilder for Snapshot {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.0.insert(Sha256::digest(&der), der);
+        Ok(())
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        for der in rustls_pemfile::certs(rd)? {
+            self.load_der(der)?;
+        }
+        Ok(())
+    }
+}
+
+/// The difference between two loads of the platform's native certificate
+/// store.
+///
+/// Certificates are identified by the SHA-256 fingerprint of their DER
+/// encoding.  Only anchors the platform trusts are ever loaded, so a root
+/// which loses its trust appears in `removed`, and one which gains it
+/// appears in `added`.
+///
+/// It is what a [CertWatcher](struct.CertWatcher.html) reports, and what
+/// [CertStore::diff](struct.CertStore.html#method.diff) returns.
+///
+/// *This type is available only if the crate is built with the "watch" or "metadata" feature.*
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StoreDiff {
+    /// Fingerprints of certificates present only in the newer load, in
+    /// ascending order.
+    pub added: Vec<[u8; 32]>,
+    /// Fingerprints of certificates present only in the older load, in
+    /// ascending order.
+    pub removed: Vec<[u8; 32]>,
+}
+
+impl StoreDiff {
+    /// Returns true if the two loads contained the same certificates.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
@@ -0,0 +1,383 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::iter::Collect;
+use crate::{PlatformTrust, Provenance, Purpose, RootStoreBuilder, TrustDomain};
+
+/// Somewhere certificates are loaded from, such as the platform's native
+/// store.
+///
+/// Code which handles trust can be written against this trait rather than
+/// the crate's free functions, so its tests can substitute a source with
+/// known contents for the machine's own store.
+pub trait CertificateSource {
+    /// Loads the root certificates trusted for `purpose`, executing
+    /// callbacks on the provided builder.  See
+    /// [build_native_certs_for](fn.build_native_certs_for.html).
+    fn build_certs(&self, purpose: Purpose, builder: &mut dyn RootStoreBuilder) -> Result<(), Error>;
+
+    /// Returns the DER encodings of intermediate certificates.  See
+    /// [load_native_intermediates](fn.load_native_intermediates.html).
+    fn intermediates(&self) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Returns the DER encodings of explicitly distrusted certificates.
+    /// See [load_native_distrusted](fn.load_native_distrusted.html).
+    fn distrusted(&self) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Returns the DER encodings of certificate revocation lists.  See
+    /// [load_native_crls](fn.load_native_crls.html).
+    fn crls(&self) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(Vec::new())
+    }
+}
+
+/// The platform's native certificate store, as a [CertificateSource](trait.CertificateSource.html).
+///
+/// Each method calls the crate function of the same purpose.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NativeCertificateSource;
+
+impl CertificateSource for NativeCertificateSource {
+    fn build_certs(&self, purpose: Purpose, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+        crate::build_native_certs_for(purpose, &mut DynBuilder(builder))
+    }
+
+    fn intermediates(&self) -> Result<Vec<Vec<u8>>, Error> {
+        crate::load_native_intermediates()
+    }
+
+    fn distrusted(&self) -> Result<Vec<Vec<u8>>, Error> {
+        crate::load_native_distrusted()
+    }
+
+    fn crls(&self) -> Result<Vec<Vec<u8>>, Error> {
+        crate::load_native_crls()
+    }
+}
+
+/// What [load_from](fn.load_from.html) found in a source: its roots for
+/// one purpose, and its intermediates, distrusted certificates and CRLs,
+/// each as DER encodings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadedCertificates {
+    roots: Vec<Vec<u8>>,
+    intermediates: Vec<Vec<u8>>,
+    distrusted: Vec<Vec<u8>>,
+    crls: Vec<Vec<u8>>,
+}
+
+impl LoadedCertificates {
+    /// Returns the roots, in the order the source gave them.
+    pub fn roots(&self) -> &[Vec<u8>] {
+        &self.roots
+    }
+
+    /// Returns the intermediates, as from [load_native_intermediates](fn.load_native_intermediates.html).
+    pub fn intermediates(&self) -> &[Vec<u8>] {
+        &self.intermediates
+    }
+
+    /// Returns the distrusted certificates, as from [load_native_distrusted](fn.load_native_distrusted.html).
+    pub fn distrusted(&self) -> &[Vec<u8>] {
+        &self.distrusted
+    }
+
+    /// Returns the certificate revocation lists, as from [load_native_crls](fn.load_native_crls.html).
+    pub fn crls(&self) -> &[Vec<u8>] {
+        &self.crls
+    }
+}
+
+/// Loads everything `source` has, for `purpose`, through the same
+/// processing as the crate's loads of the native store.
+///
+/// This process:
+///
+/// * leaves out duplicates, in each of the loads;
+/// * leaves out self-issued certificates from the intermediates, as
+///   [load_native_intermediates](fn.load_native_intermediates.html) does;
+/// * leaves out distrusted certificates from the roots and intermediates,
+///   so a source which reports a certificate both ways is not trusted;
+/// * reads the roots from PEM where the source gives them so.
+///
+/// Choosing roots by purpose is the source's job, as in
+/// [CertificateSource::build_certs](trait.CertificateSource.html#tymethod.build_certs).
+///
+/// Unlike [build_native_certs](fn.build_native_certs.html), which can load
+/// some roots and then fail, this fails if any load fails.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use rustls_native_certs::{load_from, NativeCertificateSource, Purpose};
+///
+/// let loaded = load_from(NativeCertificateSource, Purpose::ServerAuth)?;
+/// println!("{} roots", loaded.roots().len());
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_from(source: impl CertificateSource, purpose: Purpose) -> Result<LoadedCertificates, Error> {
+    let distrusted = crate::dedup(source.distrusted()?);
+
+    let mut roots = Collect(Vec::new());
+    source.build_certs(purpose, &mut roots)?;
+    let roots = crate::dedup(roots.0)
+        .into_iter()
+        .filter(|der| {
+            let trusted = !distrusted.contains(der);
+            if !trusted {
+                debug!("left out {}: distrusted", crate::der::describe(der));
+            }
+            trusted
+        })
+        .collect();
+
+    let intermediates = crate::filter_intermediates(source.intermediates()?)
+        .into_iter()
+        .filter(|der| !distrusted.contains(der))
+        .collect();
+
+    Ok(LoadedCertificates {
+        roots,
+        intermediates,
+        distrusted,
+        crls: crate::dedup(source.crls()?),
+    })
+}
+
+/// A known place to load roots from, for deployments which know where
+/// their certificates are, such as containers with a mounted bundle, rather
+/// than asking the platform.
+///
+/// Each is a [CertificateSource](trait.CertificateSource.html), as is a
+/// slice of them, which loads from each in turn.  It has no intermediates,
+/// distrusted certificates or CRLs.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CertSource {
+    /// A PEM bundle, whose certificates are roots for every purpose.
+    PemFile(PathBuf),
+    /// A directory of certificates, one or more to a file in PEM or DER,
+    /// as `SSL_CERT_DIR` names, whose certificates are roots for every
+    /// purpose.  Its files are read in order of name, and its
+    /// subdirectories are not read.
+    Directory(PathBuf),
+    /// The current user's Windows certificate store of this name, such as
+    /// "ROOT" or "CA", whose certificates are roots for the purposes their
+    /// extended key usages allow.  Loads fail with `ErrorKind::Unsupported`
+    /// elsewhere.
+    WindowsStore(String),
+    /// The trust settings of one domain on macOS, read as
+    /// [build_native_certs_for](fn.build_native_certs_for.html) reads them.
+    /// Loads fail with `ErrorKind::Unsupported` elsewhere.
+    TrustSettings(TrustDomain),
+    /// A keychain file on macOS, such as a test keychain or one an
+    /// application keeps for itself, whose certificates are roots for
+    /// every purpose: a keychain holds no trust settings.  Loads fail with
+    /// `ErrorKind::Unsupported` elsewhere.
+    Keychain(PathBuf),
+    /// PEM held in memory, such as an internal CA's certificate built into
+    /// the program, whose certificates are roots for every purpose.
+    Pem(Vec<u8>),
+    /// A DER-encoded certificate held in memory, which is a root for every
+    /// purpose.
+    Der(Vec<u8>),
+}
+
+impl CertificateSource for CertSource {
+    fn build_certs(&self, purpose: Purpose, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+        match self {
+            CertSource::PemFile(path) => {
+                let span = source_span!(bundle = path);
+                let _entered = span.enter();
+                load_pem_file(path, builder)
+            }
+            CertSource::Directory(path) => {
+                let span = source_span!(directory = path);
+                let _entered = span.enter();
+                load_directory(path, builder)
+            }
+            CertSource::WindowsStore(name) => {
+                #[cfg(all(windows, not(feature = "mock-backend")))]
+                return crate::platform::build_store_certs(name, purpose, &mut DynBuilder(builder));
+
+                #[cfg(not(all(windows, not(feature = "mock-backend"))))]
+                {
+                    let _ = (name, purpose);
+                    Err(Error::new(ErrorKind::Unsupported, "Windows certificate stores are only available on Windows"))
+                }
+            }
+            CertSource::TrustSettings(domain) => {
+                #[cfg(all(target_os = "macos", not(feature = "mock-backend")))]
+                return crate::platform::build_native_certs_in(purpose, &[*domain], &mut DynBuilder(builder));
+
+                #[cfg(not(all(target_os = "macos", not(feature = "mock-backend"))))]
+                {
+                    let _ = (domain, purpose);
+                    Err(Error::new(ErrorKind::Unsupported, "trust settings are only available on macOS"))
+                }
+            }
+            CertSource::Keychain(path) => {
+                #[cfg(all(target_os = "macos", not(feature = "mock-backend")))]
+                return crate::platform::build_keychain_certs(path, builder);
+
+                #[cfg(not(all(target_os = "macos", not(feature = "mock-backend"))))]
+                {
+                    let _ = path;
+                    Err(Error::new(ErrorKind::Unsupported, "keychains are only available on macOS"))
+                }
+            }
+            CertSource::Pem(pem) => {
+                builder.set_provenance(&Provenance::Unknown, PlatformTrust::InStore);
+                builder.load_pem_file(&mut pem.as_slice())
+            }
+            CertSource::Der(der) => {
+                builder.set_provenance(&Provenance::Unknown, PlatformTrust::InStore);
+                builder.load_der(der.clone())
+            }
+        }
+    }
+}
+
+/// Loads from each source in turn.  A source which fails does not stop
+/// the others being loaded, but the first failure is returned.
+impl CertificateSource for [CertSource] {
+    fn build_certs(&self, purpose: Purpose, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+        let mut first_error = None;
+        for source in self {
+            if let Err(err) = source.build_certs(purpose, builder) {
+                first_error = first_error.or(Some(err));
+            }
+        }
+
+        if let Some(err) = first_error {
+            Err(err)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Loads the roots for TLS server authentication from `sources`, in order,
+/// without duplicates, and without looking at the platform's native store
+/// or anything which stands in for it.
+///
+/// This fails if any source fails, as [load_from](fn.load_from.html) does;
+/// call `load_from(sources, purpose)` for other purposes.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use rustls_native_certs::{load_certs_from, CertSource};
+///
+/// let roots = load_certs_from(&[
+///     CertSource::PemFile("/etc/ssl/certs/ca-certificates.crt".into()),
+///     CertSource::Directory("/run/secrets/extra-roots".into()),
+/// ])?;
+/// println!("{} roots", roots.len());
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_certs_from(sources: &[CertSource]) -> Result<Vec<Vec<u8>>, Error> {
+    load_from(sources, Purpose::ServerAuth).map(|loaded| loaded.roots)
+}
+
+fn load_pem_file(path: &Path, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+    let mut f = BufReader::new(File::open(path)?);
+    builder.set_provenance(&Provenance::File(path.to_owned()), PlatformTrust::InStore);
+    builder.load_pem_file(&mut f)
+        .map_err(|err| Error::new(ErrorKind::InvalidData,
+                                  format!("Could not load PEM file {:?}: {}", path, err)))
+}
+
+/// Loads each file in `dir` as DER if it starts as a DER certificate does,
+/// and as PEM otherwise.  A file which fails does not stop the others being
+/// loaded, but the first failure is returned.
+fn load_directory(dir: &Path, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    let mut first_error = None;
+    for path in paths.into_iter().filter(|path| path.is_file()) {
+        let contents = match std::fs::read(&path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                first_error = first_error.or(Some(err));
+                continue;
+            }
+        };
+
+        builder.set_provenance(&Provenance::File(path.clone()), PlatformTrust::InStore);
+        let result = if contents.first() == Some(&0x30) {
+            builder.load_der(contents)
+        } else {
+            builder.load_pem_file(&mut contents.as_slice())
+        };
+        if let Err(err) = result {
+            first_error = first_error.or_else(|| Some(Error::new(
+                ErrorKind::InvalidData, format!("Could not load certificate file {:?}: {}", path, err))));
+        }
+    }
+
+    if let Some(err) = first_error {
+        Err(err)
+    } else {
+        Ok(())
+    }
+}
+
+impl<S: CertificateSource + ?Sized> CertificateSource for &S {
+    fn build_certs(&self, purpose: Purpose, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+        (**self).build_certs(purpose, builder)
+    }
+
+    fn intermediates(&self) -> Result<Vec<Vec<u8>>, Error> {
+        (**self).intermediates()
+    }
+
+    fn distrusted(&self) -> Result<Vec<Vec<u8>>, Error> {
+        (**self).distrusted()
+    }
+
+    fn crls(&self) -> Result<Vec<Vec<u8>>, Error> {
+        (**self).crls()
+    }
+}
+
+/// Lets a `dyn RootStoreBuilder` be passed where a sized builder is wanted.
+struct DynBuilder<'a>(&'a mut dyn RootStoreBuilder);
+
+impl RootStoreBuilder for DynBuilder<'_> {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.0.load_der(der)
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        self.0.load_pem_file(rd)
+    }
+
+    fn set_provenance(&mut self, provenance: &Provenance, trust: PlatformTrust) {
+        self.0.set_provenance(provenance, trust);
+    }
+
+    fn set_host_restrictions(&mut self, hosts: &[String]) {
+        self.0.set_host_restrictions(hosts);
+    }
+
+    fn set_allowed_errors(&mut self, errors: &[i32]) {
+        self.0.set_allowed_errors(errors);
+    }
+
+    fn denied(&mut self, der: &[u8]) {
+        self.0.denied(der);
+    }
+
+    fn skipped(&mut self, error: Error) {
+        self.0.skipped(error);
+    }
+}
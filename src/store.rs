@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::iter::FromIterator;
+
+use crate::snapshot::diff_maps;
+use crate::{der, NativeCert, StoreDiff};
+
+/// Roots loaded from the native store, with lookups for applications
+/// which build chains or check pins themselves.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use rustls_native_certs::CertStore;
+///
+/// let store = CertStore::load()?;
+/// for root in store.find_by_subject("CN=ISRG Root X1, O=Internet Security Research Group, C=US") {
+///     println!("{:?}", root.provenance());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// *This type is available only if the crate is built with the "metadata" feature.*
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CertStore {
+    certs: Vec<NativeCert>,
+    denied: Vec<NativeCert>,
+}
+
+impl CertStore {
+    /// Loads the roots found in the platform's native certificate store,
+    /// as [load_native_certs_with_metadata](fn.load_native_certs_with_metadata.html)
+    /// does.
+    pub fn load() -> Result<Self, Error> {
+        let (certs, denied) = crate::metadata::load_with_denied()?;
+        Ok(CertStore { certs, denied })
+    }
+
+    /// Returns the roots, in the order they were loaded.
+    pub fn certs(&self) -> &[NativeCert] {
+        &self.certs
+    }
+
+    /// Returns the certificates the load found the platform denies, as
+    /// trust settings on macOS can, each with the domain which denied it
+    /// and the trust `PlatformTrust::Deny`.  None of them is among the
+    /// roots, even where a less specific domain trusts it, so they are for
+    /// leaving out of trust anchors found elsewhere.  A store made from a
+    /// list of certificates has none.
+    pub fn denied(&self) -> &[NativeCert] {
+        &self.denied
+    }
+
+    /// Returns the number of roots.
+    pub fn len(&self) -> usize {
+        self.certs.len()
+    }
+
+    /// Returns whether there are no roots.
+    pub fn is_empty(&self) -> bool {
+        self.certs.is_empty()
+    }
+
+    /// Returns the roots whose subject is `subject`, written as the
+    /// `native-certs` command writes it: `CN=ISRG Root X1, O=Internet
+    /// Security Research Group, C=US`, for example.
+    pub fn find_by_subject<'a>(&'a self, subject: &'a str) -> impl Iterator<Item = &'a NativeCert> + 'a {
+        self.certs.iter().filter(move |cert| {
+            der::cert_fields(cert.der())
+                .and_then(|fields| der::name_to_string(fields.subject))
+                .is_some_and(|name| name == subject)
+        })
+    }
+
+    /// Returns the roots whose subject is the issuer of `leaf`, a
+    /// DER-encoded certificate, as candidates to verify its signature.  A
+    /// CA which was reissued or cross-signed can have more than one.
+    ///
+    /// Names are compared as encoded, and no signature is checked.  A leaf
+    /// which cannot be parsed has no issuer here.
+    pub fn find_issuer_of<'a>(&'a self, leaf: &'a [u8]) -> impl Iterator<Item = &'a NativeCert> + 'a {
+        let issuer = der::cert_fields(leaf).map(|fields| fields.issuer);
+        self.certs.iter().filter(move |cert| {
+            issuer.is_some() && der::cert_fields(cert.der()).map(|fields| fields.subject) == issuer
+        })
+    }
+
+    /// Returns whether there is a root whose SHA-256 fingerprint is
+    /// `fingerprint`, as [NativeCert::fingerprint_sha256](struct.NativeCert.html#method.fingerprint_sha256)
+    /// gives it.
+    pub fn contains_fingerprint(&self, fingerprint: &[u8; 32]) -> bool {
+        self.certs.iter().any(|cert| cert.fingerprint_sha256() == *fingerprint)
+    }
+
+    /// Returns whether there is a root whose public key has the pin
+    /// `sha256`: the SHA-256 digest of its DER-encoded
+    /// SubjectPublicKeyInfo, as [NativeCert::spki_sha256](struct.NativeCert.html#method.spki_sha256)
+    /// gives it, and as `pin-sha256` pins give it in base64.  Unlike a
+    /// fingerprint, a pin matches every certificate issued for the key.
+    pub fn contains_spki_pin(&self, sha256: &[u8; 32]) -> bool {
+        self.certs.iter().any(|cert| cert.spki_sha256().as_ref() == Some(sha256))
+    }
+
+    /// Returns the roots added to `older`, and removed from it, to give this
+    /// store, for reporting what changed on a host between two loads.
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// use rustls_native_certs::CertStore;
+    ///
+    /// let before = CertStore::load()?;
+    /// // ...
+    /// let diff = CertStore::load()?.diff(&before);
+    /// println!("{} roots installed, {} removed", diff.added.len(), diff.removed.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn diff(&self, older: &CertStore) -> StoreDiff {
+        diff_maps(&self.by_fingerprint(), &older.by_fingerprint())
+    }
+
+    fn by_fingerprint(&self) -> BTreeMap<[u8; 32], ()> {
+        self.certs.iter().map(|cert| (cert.fingerprint_sha256(), ())).collect()
+    }
+}
+
+impl From<Vec<NativeCert>> for CertStore {
+    fn from(certs: Vec<NativeCert>) -> Self {
+        CertStore { certs, denied: Vec::new() }
+    }
+}
+
+impl FromIterator<NativeCert> for CertStore {
+    fn from_iter<I: IntoIterator<Item = NativeCert>>(iter: I) -> Self {
+        CertStore { certs: iter.into_iter().collect(), denied: Vec::new() }
+    }
+}
+
+impl IntoIterator for CertStore {
+    type Item = NativeCert;
+    type IntoIter = std::vec::IntoIter<NativeCert>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.certs.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CertStore {
+    type Item = &'a NativeCert;
+    type IntoIter = std::slice::Iter<'a, NativeCert>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.certs.iter()
+    }
+}
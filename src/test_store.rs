@@ -0,0 +1,82 @@
+use std::io::Error;
+use std::sync::{Arc, Mutex};
+
+use crate::platform;
+
+static INSTALLED: Mutex<Option<Arc<platform::TestStore>>> = Mutex::new(None);
+
+/// Returns the installed test store, if there is one.
+pub(crate) fn installed() -> Option<Arc<platform::TestStore>> {
+    INSTALLED.lock().unwrap_or_else(|err| err.into_inner()).clone()
+}
+
+/// A scratch certificate store, made with the platform's own APIs, which
+/// the Windows or macOS backend reads in place of the user's while it is
+/// [installed](#method.install).
+///
+/// Unlike a [MockCertificateSource](struct.MockCertificateSource.html),
+/// this exercises the platform code paths end to end:
+///
+/// * on Windows, it is a set of in-memory stores standing in for the
+///   current user's "ROOT", "CA", "Disallowed" and "MY" stores.  Keys
+///   added with [add_identity_pkcs12](#method.add_identity_pkcs12) are
+///   imported into the user's key storage, as for any PFX import;
+/// * on macOS, it is a temporary keychain, which keychain searches are
+///   limited to, and deleted when the store is dropped.  Trust settings
+///   cannot be changed without the user authorizing it, so roots and
+///   distrusted certificates are recorded in memory instead, in place of
+///   the trust settings domains, and apply to every purpose.  Searches of
+///   smart cards and other tokens find nothing.
+///
+/// *This type is available only on Windows and macOS, if the crate is built with the "test-util" feature.*
+pub struct TestStore(platform::TestStore);
+
+impl TestStore {
+    /// Creates an empty store.
+    pub fn new() -> Result<Self, Error> {
+        platform::TestStore::new().map(TestStore)
+    }
+
+    /// Adds a trusted root, given as DER.
+    pub fn add_root(&mut self, der: &[u8]) -> Result<(), Error> {
+        self.0.add_root(der)
+    }
+
+    /// Adds an intermediate certificate, given as DER.
+    pub fn add_intermediate(&mut self, der: &[u8]) -> Result<(), Error> {
+        self.0.add_intermediate(der)
+    }
+
+    /// Adds an explicitly distrusted certificate, given as DER.
+    pub fn add_distrusted(&mut self, der: &[u8]) -> Result<(), Error> {
+        self.0.add_distrusted(der)
+    }
+
+    /// Adds the client identities in a PKCS#12 archive.
+    pub fn add_identity_pkcs12(&mut self, pkcs12: &[u8], password: &str) -> Result<(), Error> {
+        self.0.add_identity_pkcs12(pkcs12, password)
+    }
+
+    /// Points every load of the native store at this store, until the
+    /// returned guard is dropped.  This is for the whole process, so tests
+    /// which install stores should not run in parallel.
+    pub fn install(self) -> TestStoreGuard {
+        let mut installed = INSTALLED.lock().unwrap_or_else(|err| err.into_inner());
+        TestStoreGuard { previous: installed.replace(Arc::new(self.0)) }
+    }
+}
+
+/// Uninstalls a [TestStore](struct.TestStore.html) when dropped, restoring
+/// whichever was installed before.
+///
+/// *This type is available only on Windows and macOS, if the crate is built with the "test-util" feature.*
+#[must_use = "the store is uninstalled when the guard is dropped"]
+pub struct TestStoreGuard {
+    previous: Option<Arc<platform::TestStore>>,
+}
+
+impl Drop for TestStoreGuard {
+    fn drop(&mut self) {
+        *INSTALLED.lock().unwrap_or_else(|err| err.into_inner()) = self.previous.take();
+    }
+}
@@ -0,0 +1,31 @@
+//! The spans loads open around each source of roots they read, such as a
+//! bundle, a trust domain or a store, with the "tracing" feature, so that
+//! the events for what a source skips or denies fall inside its span.
+//! Without the feature, spans are nothing.
+
+use std::marker::PhantomData;
+
+/// The span of one source of roots, as `source_span!` opens it.
+#[derive(Clone)]
+pub(crate) struct SourceSpan(#[cfg(feature = "tracing")] tracing::Span);
+
+impl SourceSpan {
+    #[cfg(feature = "tracing")]
+    pub(crate) fn new(span: tracing::Span) -> Self {
+        SourceSpan(span)
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn new() -> Self {
+        SourceSpan()
+    }
+
+    /// Enters the span, until the guard returned is dropped.
+    pub(crate) fn enter(&self) -> Entered<'_> {
+        Entered(#[cfg(feature = "tracing")] self.0.enter(), PhantomData)
+    }
+}
+
+/// A guard which keeps a [SourceSpan] entered.
+pub(crate) struct Entered<'a>(#[cfg(feature = "tracing")] #[allow(dead_code)] tracing::span::Entered<'a>,
+                              PhantomData<&'a ()>);
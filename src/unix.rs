@@ -1,13 +1,18 @@
-use crate::RootStoreBuilder;
-use openssl_probe;
+use crate::trace::SourceSpan;
+use crate::{ClientIdentity, NativeCertIter, PlatformTrust, Provenance, Purpose, RootStoreBuilder};
+use rustls_pemfile::Item;
 use std::io::{Error, ErrorKind};
 use std::io::BufReader;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 fn load_file(builder: &mut impl RootStoreBuilder, path: &Path) -> Result<(), Error> {
-    let f = File::open(&path)?;
+    let span = source_span!(bundle = path);
+    let _entered = span.enter();
+    debug!("loading roots from {:?}", path);
+    let f = File::open(path)?;
     let mut f = BufReader::new(f);
+    builder.set_provenance(&Provenance::File(path.to_owned()), PlatformTrust::InStore);
     if builder.load_pem_file(&mut f).is_err() {
         Err(Error::new(ErrorKind::InvalidData,
                        format!("Could not load PEM file {:?}", path)))
@@ -16,16 +21,261 @@ fn load_file(builder: &mut impl RootStoreBuilder, path: &Path) -> Result<(), Err
     }
 }
 
-pub fn build_native_certs<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Error> {
+pub fn build_native_certs<B: RootStoreBuilder>(purpose: Purpose, builder: &mut B) -> Result<(), Error> {
+    if purpose != Purpose::ServerAuth {
+        return build_certs_for(purpose, builder);
+    }
+
     let likely_locations = openssl_probe::probe();
     let mut first_error = None;
 
     if let Some(file) = likely_locations.cert_file {
-        match load_file(builder, &file) {
-            Err(err) => {
-                first_error = first_error.or_else(|| Some(err));
+        if let Err(err) = load_file(builder, &file) {
+            first_error = first_error.or(Some(err));
+        }
+    } else {
+        debug!("found no CA bundle to load roots from");
+    }
+
+    if let Some(err) = first_error {
+        Err(err)
+    } else {
+        Ok(())
+    }
+}
+
+/// Describes where the roots for `purpose` are read from.
+pub fn describe_roots(purpose: Purpose) -> String {
+    if purpose == Purpose::ServerAuth {
+        return match openssl_probe::probe().cert_file {
+            Some(file) => file.display().to_string(),
+            None => "no CA bundle found by openssl-probe".to_owned(),
+        };
+    }
+
+    let extracted = Path::new(EXTRACTED_DIR);
+    match purpose_bundle(purpose).map(|name| extracted.join(name)) {
+        Some(bundle) if bundle.is_file() => bundle.display().to_string(),
+        _ => extracted.join(OPENSSL_TRUST_BUNDLE).display().to_string(),
+    }
+}
+
+/// Describes where `list` is read from.
+#[cfg(feature = "cli")]
+pub fn describe_list(list: crate::List) -> String {
+    let configured = |var: &str| match std::env::var_os(var) {
+        Some(dirs) if !dirs.is_empty() => format!("${} ({})", var, dirs.to_string_lossy()),
+        _ => format!("${} (unset)", var),
+    };
+    match list {
+        crate::List::Intermediates => configured(INTERMEDIATE_DIRS_VAR),
+        crate::List::Distrusted => format!("p11-kit blocklist directories, {}", configured(DISTRUSTED_DIRS_VAR)),
+        crate::List::Crls => format!("{}, OpenSSL's certificate directory, {}", CRL_DIR, configured(CRL_DIRS_VAR)),
+    }
+}
+
+/// The environment variables naming paths which this backend reads, or
+/// which `openssl_probe` does for it.
+#[cfg(feature = "cli")]
+pub const ENVIRONMENT: &[&str] = &[
+    openssl_probe::ENV_CERT_FILE,
+    openssl_probe::ENV_CERT_DIR,
+    INTERMEDIATE_DIRS_VAR,
+    DISTRUSTED_DIRS_VAR,
+    CRL_DIRS_VAR,
+    CLIENT_PKCS12_VAR,
+];
+
+/// What to do when no roots are found.
+#[cfg(feature = "cli")]
+pub const REMEDY: &str = "\
+Install your distribution's CA certificates: `apt install ca-certificates`,
+`dnf install ca-certificates` or `apk add ca-certificates`, say.  In a
+container built from scratch, copy a bundle such as
+/etc/ssl/certs/ca-certificates.crt into the image.  Otherwise set
+SSL_CERT_FILE to a PEM bundle of roots: SSL_CERT_DIR is not read.";
+
+/// The bundles `openssl_probe` looks for in each of its candidate
+/// directories, in its order.
+#[cfg(feature = "cli")]
+const CERT_FILENAMES: &[&str] = &[
+    "cert.pem",
+    "certs.pem",
+    "ca-bundle.pem",
+    "cacert.pem",
+    "ca-certificates.crt",
+    "certs/ca-certificates.crt",
+    "certs/ca-root-nss.crt",
+    "certs/ca-bundle.crt",
+    "CARootCertificates.pem",
+    "tls-ca-bundle.pem",
+];
+
+/// Probes `$SSL_CERT_FILE`, every bundle `openssl_probe` could choose, and
+/// the bundles extracted for purposes other than server authentication.
+#[cfg(feature = "cli")]
+pub fn probe_locations() -> Vec<crate::Probe> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    if let Some(file) = std::env::var_os(openssl_probe::ENV_CERT_FILE).filter(|file| !file.is_empty()) {
+        paths.push(file.into());
+    }
+    for dir in openssl_probe::candidate_cert_dirs() {
+        paths.extend(CERT_FILENAMES.iter().map(|name| dir.join(name)).filter(|path| path.exists()));
+    }
+    let extracted = Path::new(EXTRACTED_DIR);
+    paths.extend([Some(OPENSSL_TRUST_BUNDLE), purpose_bundle(Purpose::EmailProtection), purpose_bundle(Purpose::CodeSigning)]
+        .iter()
+        .flatten()
+        .map(|name| extracted.join(name))
+        .filter(|path| path.exists()));
+
+    let server_auth = openssl_probe::probe().cert_file;
+    let mut probes: Vec<crate::Probe> = Vec::new();
+    for path in paths {
+        let location = path.display().to_string();
+        if probes.iter().any(|probe| probe.location == location) {
+            continue;
+        }
+        let used_for = Purpose::ALL
+            .iter()
+            .copied()
+            .filter(|&purpose| match purpose {
+                Purpose::ServerAuth => server_auth.as_deref() == Some(&path),
+                _ => describe_roots(purpose) == location,
+            })
+            .collect();
+        probes.push(crate::Probe { location, found: count_certificates(&path), used_for });
+    }
+    probes
+}
+
+/// Counts the certificates in the PEM file at `path`, trusted
+/// certificates included.
+#[cfg(feature = "cli")]
+fn count_certificates(path: &Path) -> Result<usize, Error> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(rustls_pemfile::certs(&mut contents.as_bytes())?.len() + trusted_certificates(&contents).len())
+}
+
+/// Reads the roots in the bundle `openssl_probe` finds one at a time, as
+/// the iterator is advanced.
+pub fn native_cert_iter() -> NativeCertIter {
+    let path = match openssl_probe::probe().cert_file {
+        Some(path) => path,
+        None => {
+            debug!("found no CA bundle to load roots from");
+            return NativeCertIter::new(std::iter::empty());
+        }
+    };
+    let span = source_span!(bundle = path);
+    {
+        let _entered = span.enter();
+        debug!("loading roots from {:?}", path);
+    }
+    match File::open(&path) {
+        Ok(file) => NativeCertIter::new(BundleCerts { path, reader: Some(BufReader::new(file)), span }),
+        Err(err) => NativeCertIter::new(std::iter::once(Err(err))),
+    }
+}
+
+/// The certificates in a PEM bundle, read as they are asked for.  Each
+/// malformed block is an error, after which the rest of the bundle is read.
+struct BundleCerts {
+    path: PathBuf,
+    /// The rest of the bundle, until it is finished or cannot be read.
+    reader: Option<BufReader<File>>,
+    /// The span each read is made in.
+    span: SourceSpan,
+}
+
+impl Iterator for BundleCerts {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let _entered = self.span.enter();
+        loop {
+            match rustls_pemfile::read_one(self.reader.as_mut()?) {
+                Ok(Some(Item::X509Certificate(der))) => return Some(Ok(der)),
+                Ok(Some(_)) => continue,
+                Ok(None) => {
+                    self.reader = None;
+                    return None;
+                }
+                // A malformed block is read to its end, so the blocks after
+                // it can still be read.
+                Err(err) if err.kind() == ErrorKind::InvalidData => {
+                    debug!("skipped a malformed block in {:?}: {}", self.path, err);
+                    return Some(Err(Error::new(ErrorKind::InvalidData,
+                                               format!("Could not load PEM file {:?}: {}", self.path, err))));
+                }
+                Err(err) => {
+                    self.reader = None;
+                    return Some(Err(Error::new(err.kind(),
+                                               format!("Could not load PEM file {:?}: {}", self.path, err))));
+                }
+            }
+        }
+    }
+}
+
+const INTERMEDIATE_DIRS_VAR: &str = "RUSTLS_NATIVE_CERTS_INTERMEDIATE_DIRS";
+
+/// Where p11-kit's `update-ca-trust` writes the bundles it extracts from
+/// the trust store, as on Fedora and its relatives.  Other distributions
+/// write only the TLS bundle which `openssl_probe` finds.
+const EXTRACTED_DIR: &str = "/etc/pki/ca-trust/extracted";
+const OPENSSL_TRUST_BUNDLE: &str = "openssl/ca-bundle.trust.crt";
+
+/// Returns the bundle of roots trusted for just `purpose`, relative to
+/// `EXTRACTED_DIR`.  There is none for time-stamping, which is only found
+/// in the trust bundle.
+fn purpose_bundle(purpose: Purpose) -> Option<&'static str> {
+    match purpose {
+        Purpose::EmailProtection => Some("pem/email-ca-bundle.pem"),
+        Purpose::CodeSigning => Some("pem/objsign-ca-bundle.pem"),
+        _ => None,
+    }
+}
+
+fn build_certs_for<B: RootStoreBuilder>(purpose: Purpose, builder: &mut B) -> Result<(), Error> {
+    let extracted = Path::new(EXTRACTED_DIR);
+    if let Some(bundle) = purpose_bundle(purpose).map(|name| extracted.join(name)) {
+        if bundle.is_file() {
+            return load_file(builder, &bundle);
+        }
+    }
+
+    let path = extracted.join(OPENSSL_TRUST_BUNDLE);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Err(Error::new(ErrorKind::NotFound,
+                                  format!("no roots are recorded as trusted for {:?}", purpose)));
+        }
+        Err(err) => return Err(err),
+    };
+
+    let span = source_span!(bundle = path);
+    let _entered = span.enter();
+    debug!("loading roots trusted for {:?} from {:?}", purpose, path);
+    builder.set_provenance(&Provenance::File(path.clone()), PlatformTrust::InStore);
+    let mut first_error = None;
+    for der in trusted_certificates(&contents) {
+        let cert = match crate::der::openssl_trusted_certificate(&der, purpose.oid_der()) {
+            Some((cert, true)) => cert,
+            Some((cert, false)) => {
+                debug!("skipped {}: not trusted for {:?}", crate::der::describe(cert), purpose);
+                continue;
             }
-            _ => {}
+            None => {
+                debug!("skipped a trusted certificate in {:?} which cannot be parsed", path);
+                first_error = first_error.or_else(|| Some(Error::new(ErrorKind::InvalidData,
+                    format!("Could not load trusted certificate in {:?}", path))));
+                continue;
+            }
+        };
+        if let Err(err) = builder.load_der(cert.to_vec()) {
+            first_error = first_error.or(Some(err));
         }
     }
 
@@ -35,3 +285,285 @@ pub fn build_native_certs<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Er
         Ok(())
     }
 }
+
+/// Returns the contents of the "TRUSTED CERTIFICATE" PEM sections in
+/// `pem`, which `rustls_pemfile` does not read.  Sections which are not
+/// valid base64 are left out.
+pub(crate) fn trusted_certificates(pem: &str) -> Vec<Vec<u8>> {
+    use base64::Engine;
+
+    let mut found = Vec::new();
+    let mut section: Option<String> = None;
+    for line in pem.lines().map(str::trim) {
+        match (&mut section, line) {
+            (None, "-----BEGIN TRUSTED CERTIFICATE-----") => section = Some(String::new()),
+            (Some(b64), "-----END TRUSTED CERTIFICATE-----") => {
+                if let Ok(der) = base64::engine::general_purpose::STANDARD.decode(b64.as_bytes()) {
+                    found.push(der);
+                }
+                section = None;
+            }
+            (Some(b64), line) => b64.push_str(line),
+            (None, _) => {}
+        }
+    }
+    found
+}
+
+/// Distributions have no convention for where intermediates are kept, so
+/// only the configured directories are read.
+pub fn load_intermediates() -> Result<Vec<Vec<u8>>, Error> {
+    let mut certs = Vec::new();
+    if let Some(dirs) = std::env::var_os(INTERMEDIATE_DIRS_VAR) {
+        for dir in std::env::split_paths(&dirs).filter(|p| !p.as_os_str().is_empty()) {
+            load_der_dir(&mut certs, &dir, |_| true, "certificate", rustls_pemfile::certs)?;
+        }
+    }
+    Ok(certs)
+}
+
+/// Where p11-kit, and so `update-ca-trust` and `trust extract`, keeps
+/// certificates which are never to be trusted.  The older name for these
+/// directories was "blacklist".
+const DISTRUSTED_DIRS: &[&str] = &[
+    "/etc/pki/ca-trust/source/blocklist",
+    "/etc/pki/ca-trust/source/blacklist",
+    "/usr/share/pki/ca-trust-source/blocklist",
+    "/usr/share/pki/ca-trust-source/blacklist",
+    "/etc/ca-certificates/trust-source/blocklist",
+    "/etc/ca-certificates/trust-source/blacklist",
+    "/usr/share/ca-certificates/trust-source/blocklist",
+    "/usr/share/ca-certificates/trust-source/blacklist",
+];
+const DISTRUSTED_DIRS_VAR: &str = "RUSTLS_NATIVE_CERTS_DISTRUSTED_DIRS";
+
+pub fn load_distrusted() -> Result<Vec<Vec<u8>>, Error> {
+    let mut certs = Vec::new();
+
+    for dir in DISTRUSTED_DIRS {
+        load_der_dir(&mut certs, Path::new(dir), |_| true, "certificate", rustls_pemfile::certs)?;
+    }
+
+    if let Some(dirs) = std::env::var_os(DISTRUSTED_DIRS_VAR) {
+        for dir in std::env::split_paths(&dirs).filter(|p| !p.as_os_str().is_empty()) {
+            load_der_dir(&mut certs, &dir, |_| true, "certificate", rustls_pemfile::certs)?;
+        }
+    }
+
+    Ok(certs)
+}
+
+/// Where distributions conventionally keep CRLs.
+const CRL_DIR: &str = "/etc/ssl/crls";
+const CRL_DIRS_VAR: &str = "RUSTLS_NATIVE_CERTS_CRL_DIRS";
+
+pub fn load_crls() -> Result<Vec<Vec<u8>>, Error> {
+    let mut crls = Vec::new();
+
+    load_crl_dir(&mut crls, Path::new(CRL_DIR), |_| true)?;
+
+    // OpenSSL names CRLs in its hashed directory after their issuer, with
+    // an `.rN` suffix; the certificates there end in `.N`.
+    if let Some(dir) = openssl_probe::probe().cert_dir {
+        load_crl_dir(&mut crls, &dir, |name| {
+            name.rsplit_once(".r")
+                .is_some_and(|(_, n)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        })?;
+    }
+
+    if let Some(dirs) = std::env::var_os(CRL_DIRS_VAR) {
+        for dir in std::env::split_paths(&dirs).filter(|p| !p.as_os_str().is_empty()) {
+            load_crl_dir(&mut crls, &dir, |_| true)?;
+        }
+    }
+
+    Ok(crls)
+}
+
+/// Loads the CRLs in the files in `dir` whose names pass `wanted`.  A
+/// missing directory holds no CRLs.
+fn load_crl_dir(crls: &mut Vec<Vec<u8>>, dir: &Path, wanted: impl Fn(&str) -> bool) -> Result<(), Error> {
+    load_der_dir(crls, dir, wanted, "CRL", rustls_pemfile::crls)
+}
+
+/// Reads the DER encodings of one kind of object from PEM.
+type PemReader = fn(&mut dyn std::io::BufRead) -> Result<Vec<Vec<u8>>, Error>;
+
+/// Loads the `kind` objects in the files in `dir` whose names pass
+/// `wanted`, each file holding one in DER or any number in PEM, which
+/// `pem` extracts.  A missing directory holds none.
+fn load_der_dir(found: &mut Vec<Vec<u8>>,
+                dir: &Path,
+                wanted: impl Fn(&str) -> bool,
+                kind: &str,
+                pem: PemReader)
+                -> Result<(), Error> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    let mut paths = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    for path in paths {
+        let wanted = path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(&wanted);
+        if !wanted || !path.is_file() {
+            continue;
+        }
+
+        let contents = std::fs::read(&path)?;
+        let loaded = if contents.starts_with(&[0x30]) {
+            vec![contents]
+        } else {
+            pem(&mut &contents[..])
+                .map_err(|_| Error::new(ErrorKind::InvalidData,
+                                        format!("Could not load {} file {:?}", kind, path)))?
+        };
+        for der in loaded {
+            if !found.contains(&der) {
+                found.push(der);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "watch")]
+pub use crate::fs_watch::Watcher;
+
+/// Calls `on_change` whenever the directories holding the system CA
+/// bundle, or the system certificate directory, are modified.
+#[cfg(feature = "watch")]
+pub fn watch(on_change: impl FnMut() + Send + 'static) -> Result<Watcher, Error> {
+    let likely_locations = openssl_probe::probe();
+    let dirs = likely_locations.cert_file
+        .as_deref()
+        .and_then(Path::parent)
+        .into_iter()
+        .chain(likely_locations.cert_dir.as_deref());
+
+    crate::fs_watch::watch_dirs(dirs, on_change)
+}
+
+/// A private key which stays with its holder.  Keys loaded from PKCS#12
+/// files are not among these.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "rustls"), allow(dead_code))]
+pub enum NativeKey {
+    #[cfg(feature = "pkcs11")]
+    Pkcs11(crate::pkcs11::TokenKey),
+}
+
+#[cfg(feature = "rustls")]
+pub fn signing_key(key: &NativeKey, _key_type: crate::der::KeyType)
+    -> Result<Box<dyn rustls::sign::SigningKey>, Error>
+{
+    match *key {
+        #[cfg(feature = "pkcs11")]
+        NativeKey::Pkcs11(ref key) => crate::pkcs11::signing_key(key, _key_type),
+    }
+}
+
+pub fn export_pkcs12(key: &NativeKey, _chain: &[Vec<u8>], _password: &str) -> Result<Vec<u8>, Error> {
+    match *key {
+        #[cfg(feature = "pkcs11")]
+        NativeKey::Pkcs11(_) => Err(Error::new(ErrorKind::Unsupported,
+                                               "keys on PKCS#11 tokens cannot be exported")),
+    }
+}
+
+/// Builds a PKCS#12 archive from a chain and its PKCS#8 private key.
+#[cfg(feature = "pkcs12")]
+pub fn pkcs12_with_key(chain: &[Vec<u8>],
+                       pkcs8_der: &[u8],
+                       label: Option<&str>,
+                       password: &str) -> Result<Vec<u8>, Error> {
+    use p12_keystore::{Certificate, KeyStore, KeyStoreEntry, PrivateKey, PrivateKeyChain};
+
+    let invalid = |err: p12_keystore::error::Error| Error::new(ErrorKind::InvalidData,
+                                                               format!("Could not build PKCS#12 archive: {}", err));
+    let certs = chain.iter()
+        .map(|der| Certificate::from_der(der))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid)?;
+    let key = PrivateKey::from_der(pkcs8_der).map_err(invalid)?;
+
+    // The local key ID only has to tie the key to its certificate within
+    // the archive.
+    let mut store = KeyStore::new();
+    store.add_entry(label.unwrap_or("client"),
+                    KeyStoreEntry::PrivateKeyChain(PrivateKeyChain::new(vec![1], key, certs)));
+    store.writer(password).write().map_err(invalid)
+}
+
+const CLIENT_PKCS12_VAR: &str = "RUSTLS_NATIVE_CERTS_CLIENT_PKCS12";
+const CLIENT_PKCS11_VAR: &str = "RUSTLS_NATIVE_CERTS_CLIENT_PKCS11";
+#[cfg(feature = "pkcs12")]
+const CLIENT_PKCS12_PASSWORD_VAR: &str = "RUSTLS_NATIVE_CERTS_CLIENT_PKCS12_PASSWORD";
+
+pub fn load_client_identities() -> Result<Vec<ClientIdentity>, Error> {
+    let mut identities = Vec::new();
+
+    if let Some(paths) = std::env::var_os(CLIENT_PKCS12_VAR) {
+        for path in std::env::split_paths(&paths).filter(|p| !p.as_os_str().is_empty()) {
+            load_pkcs12_file(&mut identities, path)?;
+        }
+    }
+
+    if let Ok(uris) = std::env::var(CLIENT_PKCS11_VAR) {
+        for uri in uris.split_whitespace() {
+            load_pkcs11_uri(&mut identities, uri)?;
+        }
+    }
+
+    Ok(identities)
+}
+
+#[cfg(feature = "pkcs11")]
+fn load_pkcs11_uri(identities: &mut Vec<ClientIdentity>, uri: &str) -> Result<(), Error> {
+    identities.extend(crate::pkcs11::load_pkcs11_identities(uri)?);
+    Ok(())
+}
+
+#[cfg(not(feature = "pkcs11"))]
+fn load_pkcs11_uri(_identities: &mut Vec<ClientIdentity>, uri: &str) -> Result<(), Error> {
+    Err(Error::new(ErrorKind::Unsupported,
+                   format!("Could not load PKCS#11 URI {:?}: built without the \"pkcs11\" feature", uri)))
+}
+
+#[cfg(feature = "pkcs12")]
+fn load_pkcs12_file(identities: &mut Vec<ClientIdentity>, path: PathBuf) -> Result<(), Error> {
+    use p12_keystore::{KeyStore, KeyStoreEntry, Pkcs12ImportPolicy};
+    use crate::IdentitySource;
+
+    let password = std::env::var(CLIENT_PKCS12_PASSWORD_VAR).unwrap_or_default();
+    let data = std::fs::read(&path)?;
+    let store = KeyStore::from_pkcs12(&data, &password, Pkcs12ImportPolicy::Strict)
+        .map_err(|err| Error::new(ErrorKind::InvalidData,
+                                  format!("Could not load PKCS#12 file {:?}: {}", path, err)))?;
+
+    for (alias, entry) in store.entries() {
+        if let KeyStoreEntry::PrivateKeyChain(chain) = entry {
+            let certs = chain.certs()
+                .iter()
+                .map(|cert| cert.as_der().to_vec())
+                .collect();
+            identities.push(
+                ClientIdentity::new(certs, Some(alias.clone()), IdentitySource::Pkcs12File(path.clone()))
+                    .with_private_key(chain.key().as_der().to_vec()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "pkcs12"))]
+fn load_pkcs12_file(_identities: &mut Vec<ClientIdentity>, path: PathBuf) -> Result<(), Error> {
+    Err(Error::new(ErrorKind::Unsupported,
+                   format!("Could not load PKCS#12 file {:?}: built without the \"pkcs12\" feature", path)))
+}
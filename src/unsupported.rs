@@ -0,0 +1,83 @@
+//! A backend for targets whose certificate store this crate cannot read,
+//! whose loads fail with `ErrorKind::Unsupported`, so that crates which
+//! build for every target can depend on this one and fall back to roots of
+//! their own.
+//!
+//! iOS, tvOS, watchOS and visionOS are such targets: their Security
+//! framework can evaluate trust in a chain, but has no public API which
+//! lists the roots it trusts.
+
+use std::io::{Error, ErrorKind};
+
+use crate::{ClientIdentity, Purpose, RootStoreBuilder};
+
+fn unsupported() -> Error {
+    #[cfg(all(target_vendor = "apple", not(target_os = "macos")))]
+    let message = "this Apple platform has no public API which lists the roots it trusts";
+    #[cfg(not(all(target_vendor = "apple", not(target_os = "macos"))))]
+    let message = "this platform has no certificate store rustls-native-certs can read";
+    Error::new(ErrorKind::Unsupported, message)
+}
+
+pub fn build_native_certs<B: RootStoreBuilder>(_purpose: Purpose, _builder: &mut B) -> Result<(), Error> {
+    Err(unsupported())
+}
+
+pub fn load_intermediates() -> Result<Vec<Vec<u8>>, Error> {
+    Err(unsupported())
+}
+
+pub fn load_distrusted() -> Result<Vec<Vec<u8>>, Error> {
+    Err(unsupported())
+}
+
+pub fn load_crls() -> Result<Vec<Vec<u8>>, Error> {
+    Err(unsupported())
+}
+
+pub fn describe_roots(_purpose: Purpose) -> String {
+    "no store on this platform".to_owned()
+}
+
+#[cfg(feature = "cli")]
+pub fn describe_list(_list: crate::List) -> String {
+    "no store on this platform".to_owned()
+}
+
+#[cfg(feature = "cli")]
+pub const ENVIRONMENT: &[&str] = &[];
+
+#[cfg(feature = "cli")]
+pub const REMEDY: &str = "This platform has no store to read: bundle roots with the application instead.";
+
+#[cfg(feature = "cli")]
+pub fn probe_locations() -> Vec<crate::Probe> {
+    Vec::new()
+}
+
+pub fn load_client_identities() -> Result<Vec<ClientIdentity>, Error> {
+    Err(unsupported())
+}
+
+/// There is no store to hold keys, so none can be handed out.
+#[derive(Clone)]
+pub enum NativeKey {}
+
+#[cfg(feature = "rustls")]
+pub fn signing_key(key: &NativeKey, _key_type: crate::der::KeyType)
+    -> Result<Box<dyn rustls::sign::SigningKey>, Error>
+{
+    match *key {}
+}
+
+pub fn export_pkcs12(key: &NativeKey, _chain: &[Vec<u8>], _password: &str) -> Result<Vec<u8>, Error> {
+    match *key {}
+}
+
+#[cfg(feature = "watch")]
+pub struct Watcher;
+
+#[cfg(feature = "watch")]
+pub fn watch(_on_change: impl FnMut() + Send + 'static) -> Result<Watcher, Error> {
+    Err(unsupported())
+}
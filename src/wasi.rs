@@ -0,0 +1,109 @@
+//! A backend for WASI, where a guest has no certificate store: it sees only
+//! the files its host grants it, and so reads the bundle, or directory of
+//! certificates, which `SSL_CERT_FILE` or `SSL_CERT_DIR` names, as a host
+//! running it with a preopened directory sets them.
+//!
+//! Hosts grant roots alone, so there are no intermediates, distrusted
+//! certificates or CRLs to list.
+
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+use crate::{CertSource, CertificateSource, ClientIdentity, Purpose, RootStoreBuilder};
+
+const CERT_FILE_VAR: &str = "SSL_CERT_FILE";
+const CERT_DIR_VAR: &str = "SSL_CERT_DIR";
+
+/// Returns the bundle and the directories the host named.
+fn granted_paths() -> (Option<PathBuf>, Vec<PathBuf>) {
+    let file = std::env::var_os(CERT_FILE_VAR)
+        .filter(|file| !file.is_empty())
+        .map(PathBuf::from);
+    let dirs = std::env::var(CERT_DIR_VAR)
+        .unwrap_or_default()
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    (file, dirs)
+}
+
+pub fn build_native_certs<B: RootStoreBuilder>(purpose: Purpose, builder: &mut B) -> Result<(), Error> {
+    let (file, dirs) = granted_paths();
+    let sources = file.map(CertSource::PemFile)
+        .into_iter()
+        .chain(dirs.into_iter().map(CertSource::Directory))
+        .collect::<Vec<_>>();
+    if sources.is_empty() {
+        return Err(Error::new(ErrorKind::NotFound,
+                              "the host granted no roots: neither SSL_CERT_FILE nor SSL_CERT_DIR is set"));
+    }
+    sources.as_slice().build_certs(purpose, builder)
+}
+
+pub fn load_intermediates() -> Result<Vec<Vec<u8>>, Error> {
+    Ok(Vec::new())
+}
+
+pub fn load_distrusted() -> Result<Vec<Vec<u8>>, Error> {
+    Ok(Vec::new())
+}
+
+pub fn load_crls() -> Result<Vec<Vec<u8>>, Error> {
+    Ok(Vec::new())
+}
+
+pub fn describe_roots(_purpose: Purpose) -> String {
+    let (file, dirs) = granted_paths();
+    let paths = file.iter()
+        .chain(&dirs)
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    match paths.is_empty() {
+        true => "no SSL_CERT_FILE or SSL_CERT_DIR".to_owned(),
+        false => paths.join(", "),
+    }
+}
+
+#[cfg(feature = "cli")]
+pub fn describe_list(_list: crate::List) -> String {
+    "nothing: a WASI host grants only roots".to_owned()
+}
+
+#[cfg(feature = "cli")]
+pub const ENVIRONMENT: &[&str] = &[CERT_FILE_VAR, CERT_DIR_VAR];
+
+#[cfg(feature = "cli")]
+pub const REMEDY: &str = "Preopen the host's bundle for the guest and name it with SSL_CERT_FILE.";
+
+#[cfg(feature = "cli")]
+pub fn probe_locations() -> Vec<crate::Probe> {
+    Vec::new()
+}
+
+pub fn load_client_identities() -> Result<Vec<ClientIdentity>, Error> {
+    Ok(Vec::new())
+}
+
+/// There is no store to hold keys, so none can be handed out.
+#[derive(Clone)]
+pub enum NativeKey {}
+
+#[cfg(feature = "rustls")]
+pub fn signing_key(key: &NativeKey, _key_type: crate::der::KeyType)
+    -> Result<Box<dyn rustls::sign::SigningKey>, Error>
+{
+    match *key {}
+}
+
+pub fn export_pkcs12(key: &NativeKey, _chain: &[Vec<u8>], _password: &str) -> Result<Vec<u8>, Error> {
+    match *key {}
+}
+
+#[cfg(feature = "watch")]
+pub struct Watcher;
+
+#[cfg(feature = "watch")]
+pub fn watch(_on_change: impl FnMut() + Send + 'static) -> Result<Watcher, Error> {
+    Err(Error::new(ErrorKind::Unsupported, "a WASI guest cannot watch the files its host grants"))
+}
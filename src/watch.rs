@@ -0,0 +1,308 @@
+use std::io::Error;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::snapshot::{Snapshot, StoreDiff};
+use crate::{platform, poll};
+
+/// A change observed in the platform's native certificate store.
+///
+/// *This type is available only if the crate is built with the "watch" feature.*
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChangeEvent {
+    /// The set of certificates in the store changed.  Callers should reload
+    /// the store to observe the new contents.
+    Changed(StoreDiff),
+
+    /// A reload after a change found the store empty, or failed partway
+    /// through.  This usually means the store was caught in the middle of an
+    /// update, so the result is disregarded: the next `Changed` event
+    /// describes the difference from the last good load.  Callers holding a
+    /// previously loaded store should keep using it.
+    ///
+    /// The string describes what was wrong with the store.
+    Warning(String),
+}
+
+/// Called with each event, and the last good load of the store, which for
+/// a `Changed` event is the load it describes.
+pub(crate) type Subscriber = Box<dyn FnMut(&ChangeEvent, &Snapshot) -> bool + Send>;
+
+/// The set of parties interested in events from one `CertWatcher`.
+///
+/// Each subscriber returns `false` once it is no longer interested
+/// (typically because the receiving half of its channel was dropped),
+/// and is then discarded.
+#[derive(Default)]
+struct Subscribers(Mutex<Vec<Subscriber>>);
+
+impl Subscribers {
+    fn add(&self, subscriber: Subscriber) {
+        self.0.lock()
+            .unwrap()
+            .push(subscriber);
+    }
+
+    /// Delivers `event`, with `last`, the last good load, to each subscriber
+    /// in turn.
+    ///
+    /// The lock is not held while subscribers run, so that a callback can
+    /// register further subscribers without deadlocking.
+    fn notify(&self, event: &ChangeEvent, last: &Snapshot) {
+        let mut current = std::mem::take(&mut *self.0.lock().unwrap());
+        current.retain_mut(|subscriber| subscriber(event, last));
+
+        let mut subscribers = self.0.lock().unwrap();
+        current.append(&mut subscribers);
+        *subscribers = current;
+    }
+}
+
+/// Watches the platform's native certificate store for changes.
+///
+/// Events are delivered to every subscriber obtained from this watcher,
+/// for as long as the watcher is alive.  Dropping the watcher stops
+/// watching the store, and closes all its subscriptions.
+///
+/// *This type is available only if the crate is built with the "watch" feature.*
+pub struct CertWatcher {
+    subscribers: Arc<Subscribers>,
+    _backend: Backend,
+}
+
+/// Whatever produces raw notifications for a `CertWatcher`; kept alive
+/// only to be dropped alongside it.
+enum Backend {
+    Native(#[allow(dead_code)] platform::Watcher),
+    Polling(#[allow(dead_code)] poll::PollingWatcher),
+}
+
+impl CertWatcher {
+    /// Starts watching the platform's native certificate store, with
+    /// default options.
+    ///
+    /// Where the platform cannot notify us of changes to the store, this
+    /// falls back to polling it.  See [CertWatcherBuilder](struct.CertWatcherBuilder.html).
+    ///
+    /// This function fails if the background threads cannot be started.
+    pub fn new() -> Result<Self, Error> {
+        Self::builder().build()
+    }
+
+    /// Returns a builder for a `CertWatcher` with non-default options.
+    pub fn builder() -> CertWatcherBuilder {
+        CertWatcherBuilder::default()
+    }
+
+    /// Returns a channel on which subsequent change events are delivered.
+    pub fn subscribe(&self) -> mpsc::Receiver<ChangeEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.add_subscriber(Box::new(move |event, _| tx.send(event.clone()).is_ok()));
+        rx
+    }
+
+    /// Registers a callback which is called with each subsequent change event.
+    ///
+    /// Callbacks run on the watcher's background thread, never concurrently
+    /// with one another: each event is delivered to every callback and channel
+    /// in registration order before the next event is processed.  A slow
+    /// callback therefore delays delivery to everyone else; hand long-running
+    /// work off to another thread.  Callbacks may register further callbacks
+    /// or subscriptions on this watcher, which take effect from the next event.
+    ///
+    /// The callback is dropped when the watcher is.
+    pub fn on_change(&self, mut callback: impl FnMut(&ChangeEvent) + Send + 'static) {
+        self.add_subscriber(Box::new(move |event, _| {
+            callback(event);
+            true
+        }));
+    }
+
+    pub(crate) fn add_subscriber(&self, subscriber: Subscriber) {
+        self.subscribers.add(subscriber);
+    }
+
+    /// Returns a `Stream` of subsequent change events, for use from async code.
+    ///
+    /// The stream ends once this `CertWatcher` is dropped.
+    ///
+    /// *This function is available only if the crate is built with the "tokio" feature.*
+    #[cfg(feature = "tokio")]
+    pub fn stream(&self) -> impl futures_core::Stream<Item = ChangeEvent> + Send + Unpin {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.add_subscriber(Box::new(move |event, _| tx.send(event.clone()).is_ok()));
+        ChangeStream(rx)
+    }
+}
+
+/// Options controlling how a [CertWatcher](struct.CertWatcher.html) turns
+/// raw platform notifications into change events.
+///
+/// Each event follows a reload of the store, and describes how it differs
+/// from the previous load.  Notifications which turn out not to have changed
+/// the set of certificates (for example, a file being rewritten with the same
+/// contents) produce no event.
+///
+/// Tools like `update-ca-certificates` touch hundreds of files in quick
+/// succession.  To avoid reacting to each of those separately, a watcher
+/// waits until the store has been quiet for the *debounce* period before
+/// delivering an event, and then delivers at most one event per *cooldown*
+/// period.  Changes seen in the meantime are coalesced into the next event.
+///
+/// Where the platform offers no change notification, or watching the store
+/// fails (for example because the CA bundle does not exist yet), a watcher
+/// instead reloads the store every *poll interval* and reports a change when
+/// its contents differ.  Polling can also be forced, which is useful for
+/// stores on network filesystems where notifications are unreliable.
+///
+/// *This type is available only if the crate is built with the "watch" feature.*
+#[derive(Clone, Debug)]
+pub struct CertWatcherBuilder {
+    debounce: Duration,
+    cooldown: Duration,
+    poll_interval: Duration,
+    force_polling: bool,
+}
+
+impl Default for CertWatcherBuilder {
+    fn default() -> Self {
+        CertWatcherBuilder {
+            debounce: Duration::from_millis(500),
+            cooldown: Duration::from_secs(0),
+            poll_interval: Duration::from_secs(60),
+            force_polling: false,
+        }
+    }
+}
+
+impl CertWatcherBuilder {
+    /// Sets how long the store must be quiet before a change is reported.
+    /// The default is 500 milliseconds.
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Sets the minimum time between two reported changes.  The default is
+    /// zero, so changes are reported as soon as the debounce period elapses.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Sets how often the store is reloaded when polling.  The default is
+    /// 60 seconds.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Sets whether to poll the store even where the platform could notify
+    /// us of changes.  The default is `false`.
+    pub fn force_polling(mut self, force: bool) -> Self {
+        self.force_polling = force;
+        self
+    }
+
+    /// Starts watching the platform's native certificate store.
+    ///
+    /// See [CertWatcher::new](struct.CertWatcher.html#method.new) for how this can fail.
+    pub fn build(self) -> Result<CertWatcher, Error> {
+        let initial = Snapshot::load().unwrap_or_else(|(partial, _)| partial);
+        self.start(initial, Vec::new())
+    }
+
+    /// Starts watching the store, reporting changes from `initial`, a load
+    /// the caller has already made, to `subscribers`, which are registered
+    /// before the watcher starts, so that they see every change after it.
+    pub(crate) fn start(self, initial: Snapshot, subscribers: Vec<Subscriber>) -> Result<CertWatcher, Error> {
+        let subscribers = Arc::new(Subscribers(Mutex::new(subscribers)));
+        let (tx, rx) = mpsc::channel();
+        let on_change = move || {
+            let _ = tx.send(());
+        };
+
+        let backend = if self.force_polling {
+            Backend::Polling(poll::watch(self.poll_interval, on_change)?)
+        } else {
+            match platform::watch(on_change.clone()) {
+                Ok(watcher) => Backend::Native(watcher),
+                Err(_) => Backend::Polling(poll::watch(self.poll_interval, on_change)?),
+            }
+        };
+
+        {
+            let subscribers = subscribers.clone();
+            thread::Builder::new()
+                .name("rustls-native-certs watcher".into())
+                .spawn(move || self.run(&rx, initial, &subscribers))?;
+        }
+
+        Ok(CertWatcher {
+            subscribers,
+            _backend: backend,
+        })
+    }
+
+    /// Delivers at most one event for each burst of raw notifications on
+    /// `rx`, until the platform backend goes away.
+    fn run(self, rx: &mpsc::Receiver<()>, mut last: Snapshot, subscribers: &Subscribers) {
+        let mut last_event: Option<Instant> = None;
+
+        while rx.recv().is_ok() {
+            loop {
+                match rx.recv_timeout(self.debounce) {
+                    Ok(()) => continue,
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            if let Some(ready) = last_event.map(|at| at + self.cooldown) {
+                let now = Instant::now();
+                if ready > now {
+                    thread::sleep(ready - now);
+                    while rx.try_recv().is_ok() {}
+                }
+            }
+
+            let current = match Snapshot::load() {
+                Ok(current) if current.is_empty() && !last.is_empty() => {
+                    subscribers.notify(&ChangeEvent::Warning("certificate store is empty".into()), &last);
+                    last_event = Some(Instant::now());
+                    continue;
+                }
+                Ok(current) => current,
+                Err((_, err)) => {
+                    subscribers.notify(&ChangeEvent::Warning(err.to_string()), &last);
+                    last_event = Some(Instant::now());
+                    continue;
+                }
+            };
+
+            let diff = current.diff(&last);
+            if diff.is_empty() {
+                continue;
+            }
+
+            last = current;
+            subscribers.notify(&ChangeEvent::Changed(diff), &last);
+            last_event = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+struct ChangeStream(tokio::sync::mpsc::UnboundedReceiver<ChangeEvent>);
+
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for ChangeStream {
+    type Item = ChangeEvent;
+
+    fn poll_next(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>)
+        -> std::task::Poll<Option<ChangeEvent>> {
+        self.0.poll_recv(cx)
+    }
+}
@@ -1,35 +1,421 @@
-use crate::RootStoreBuilder;
-use schannel;
+use crate::{ClientIdentity, IdentitySource, PlatformTrust, Provenance, Purpose, RootStoreBuilder, StoreAccess,
+            WindowsLocation};
+use std::collections::HashSet;
 use std::io::{Error, ErrorKind};
 
-static PKIX_SERVER_AUTH: &str = "1.3.6.1.5.5.7.3.1";
+#[cfg(any(feature = "rustls", feature = "watch"))]
+use std::sync::Arc;
 
-fn usable_for_rustls(uses: schannel::cert_context::ValidUses) -> bool {
+#[cfg(feature = "rustls")]
+use rustls::internal::msgs::enums::SignatureAlgorithm;
+#[cfg(feature = "rustls")]
+use rustls::sign::{Signer, SigningKey};
+#[cfg(feature = "rustls")]
+use rustls::{SignatureScheme, TLSError};
+#[cfg(feature = "rustls")]
+use schannel::cert_context::PrivateKey;
+#[cfg(feature = "rustls")]
+use schannel::ncrypt_key::NcryptKey;
+
+#[cfg(feature = "rustls")]
+use crate::der::{self, KeyType};
+#[cfg(feature = "rustls")]
+use crate::sign;
+
+fn usable_for(uses: schannel::cert_context::ValidUses, purpose: Purpose) -> bool {
     match uses {
         schannel::cert_context::ValidUses::All => true,
         schannel::cert_context::ValidUses::Oids(strs) => {
-            strs.iter().any(|x| x == PKIX_SERVER_AUTH)
+            strs.iter().any(|x| x == purpose.oid())
+        }
+    }
+}
+
+/// Opens the current user's store called `name`, or while a
+/// [TestStore](struct.TestStore.html) is installed, its store of that name.
+fn open_store(name: &str) -> Result<schannel::cert_store::CertStore, Error> {
+    #[cfg(feature = "test-util")]
+    if let Some(test_store) = crate::test_store::installed() {
+        return test_store.store(name);
+    }
+
+    schannel::cert_store::CertStore::open_current_user(name)
+}
+
+/// Opens the store called `name` in `location`, read-only, or while a
+/// [TestStore](struct.TestStore.html) is installed, its store of that name,
+/// wherever the load reads.
+///
+/// The current user's system stores hold the local machine's as physical
+/// stores inside them; what the user added alone is the physical store
+/// ".Default".
+fn open_store_in(name: &str, location: WindowsLocation) -> Result<schannel::cert_store::CertStore, Error> {
+    use windows_sys::Win32::Security::Cryptography::{
+        CERT_STORE_PROV_PHYSICAL_W,
+        CERT_STORE_PROV_SYSTEM_W,
+        CERT_SYSTEM_STORE_CURRENT_USER,
+        CERT_SYSTEM_STORE_LOCAL_MACHINE,
+    };
+
+    let (provider, store_location, path) = match location {
+        WindowsLocation::Merged => return open_store(name),
+        WindowsLocation::CurrentUser => {
+            (CERT_STORE_PROV_PHYSICAL_W, CERT_SYSTEM_STORE_CURRENT_USER, format!("{}\\.Default", name))
         }
+        WindowsLocation::LocalMachine => {
+            (CERT_STORE_PROV_SYSTEM_W, CERT_SYSTEM_STORE_LOCAL_MACHINE, name.to_owned())
+        }
+    };
+
+    #[cfg(feature = "test-util")]
+    if let Some(test_store) = crate::test_store::installed() {
+        return test_store.store(name);
+    }
+
+    open_existing(provider, store_location, &path)
+}
+
+/// Opens the current user's Group Policy store called `name`, read-only,
+/// or `None` if policy pushed nothing there, as is usual outside a domain.
+/// While a [TestStore](struct.TestStore.html) is installed, there is none.
+fn open_group_policy_store(name: &str) -> Result<Option<schannel::cert_store::CertStore>, Error> {
+    use windows_sys::Win32::Security::Cryptography::{
+        CERT_STORE_PROV_SYSTEM_W,
+        CERT_SYSTEM_STORE_CURRENT_USER_GROUP_POLICY_ID,
+        CERT_SYSTEM_STORE_LOCATION_SHIFT,
+    };
+
+    #[cfg(feature = "test-util")]
+    if crate::test_store::installed().is_some() {
+        return Ok(None);
+    }
+
+    let store_location = CERT_SYSTEM_STORE_CURRENT_USER_GROUP_POLICY_ID << CERT_SYSTEM_STORE_LOCATION_SHIFT;
+    match open_existing(CERT_STORE_PROV_SYSTEM_W, store_location, name) {
+        Ok(store) => Ok(Some(store)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Opens the existing store at `path` with `provider` in `store_location`,
+/// read-only.
+fn open_existing(provider: windows_sys::core::PCSTR, store_location: u32,
+                 path: &str) -> Result<schannel::cert_store::CertStore, Error> {
+    use schannel::RawPointer;
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Security::Cryptography::{
+        CertOpenStore,
+        CERT_STORE_OPEN_EXISTING_FLAG,
+        CERT_STORE_READONLY_FLAG,
+    };
+
+    let path: Vec<u16> = OsStr::new(path).encode_wide().chain(Some(0)).collect();
+    let flags = store_location | CERT_STORE_OPEN_EXISTING_FLAG | CERT_STORE_READONLY_FLAG;
+    // Safety: `path` is a NUL-terminated wide string, which outlives the
+    // call.
+    let store = unsafe { CertOpenStore(provider, 0, 0, flags, path.as_ptr() as *const _) };
+    if store.is_null() {
+        return Err(Error::last_os_error());
     }
+    // Safety: `store` is an open store, which the `CertStore` now owns and
+    // closes when dropped.
+    Ok(unsafe { schannel::cert_store::CertStore::from_ptr(store) })
 }
 
-pub fn build_native_certs<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Error> {
+/// Describes `location`, as in "the current user's ROOT store".
+fn location_name(location: WindowsLocation) -> &'static str {
+    match location {
+        WindowsLocation::Merged => "current user's",
+        WindowsLocation::CurrentUser => "current user's own",
+        WindowsLocation::LocalMachine => "local machine's",
+    }
+}
+
+/// Loads the certificates in each of the stores `access` names in its
+/// location in turn, which are usable for `purpose`, and then, if `access`
+/// says so, reports the roots the AuthRoot list names which Windows has not
+/// downloaded.  A store which fails does not stop the others being read,
+/// but the first failure is returned.  A certificate also in the current
+/// user's "Disallowed" store, which includes the local machine's, is left
+/// out, and reported as denied, as schannel never trusts it.  This stands
+/// in for the `build_native_certs` of the other backends; loads read the
+/// current user's "ROOT" store alone unless told otherwise.
+pub fn build_stores_certs<B: RootStoreBuilder>(access: &StoreAccess, purpose: Purpose,
+                                               builder: &mut B) -> Result<(), Error> {
+    let disallowed = disallowed()?;
     let mut first_error = None;
+    for name in &access.windows_stores {
+        if let Err(err) = build_allowed_certs(name, access.windows_location, purpose, &disallowed,
+                                              &mut HashSet::new(), builder) {
+            first_error = first_error.or(Some(err));
+        }
+    }
 
-    let current_user_store = schannel::cert_store::CertStore::open_current_user("ROOT")?;
+    match access.windows_location {
+        _ if !access.windows_auth_root => {}
+        WindowsLocation::CurrentUser => {
+            debug!("skipped the AuthRoot list: it is the local machine's");
+        }
+        _ => {
+            if let Err(err) = report_missing_auth_roots(builder) {
+                first_error = first_error.or(Some(err));
+            }
+        }
+    }
 
-    for cert in current_user_store.certs() {
-        if !usable_for_rustls(cert.valid_uses().unwrap()) {
-            continue;
+    if let Some(err) = first_error {
+        Err(err)
+    } else {
+        Ok(())
+    }
+}
+
+/// Loads the certificates in the current user's store called `name` which
+/// are usable for `purpose`, leaving out those in the "Disallowed" store,
+/// as `build_stores_certs` does.
+pub fn build_store_certs<B: RootStoreBuilder>(name: &str, purpose: Purpose,
+                                              builder: &mut B) -> Result<(), Error> {
+    build_allowed_certs(name, WindowsLocation::Merged, purpose, &disallowed()?, &mut HashSet::new(), builder)
+}
+
+/// Returns the DER encodings of the certificates in the current user's
+/// "Disallowed" store, which CryptoAPI, and so schannel, never trusts,
+/// whichever store holds them.
+fn disallowed() -> Result<HashSet<Vec<u8>>, Error> {
+    Ok(load_distrusted()?.into_iter().collect())
+}
+
+/// Loads the certificates in the store called `name` in `location` which
+/// are usable for `purpose`, reporting each of `disallowed` as denied
+/// rather than loading it.
+///
+/// The merged view, and the local machine's, include the Group Policy and
+/// enterprise stores as physical stores of their own, but the current
+/// user's ".Default" physical store holds only what the user added, so with
+/// `WindowsLocation::CurrentUser` the user's Group Policy store is read as
+/// well.  A certificate found in both is loaded once.  What is loaded is
+/// added to `loaded`.
+fn build_allowed_certs<B: RootStoreBuilder>(name: &str,
+                                            location: WindowsLocation,
+                                            purpose: Purpose,
+                                            disallowed: &HashSet<Vec<u8>>,
+                                            loaded: &mut HashSet<Vec<u8>>,
+                                            builder: &mut B) -> Result<(), Error> {
+    let mut first_error = {
+        let span = source_span!(store = name, location = location_name(location));
+        let _entered = span.enter();
+        debug!("loading roots for {:?} from the {} {} store", purpose, location_name(location), name);
+        let store = open_store_in(name, location)?;
+        build_usable_certs(store.certs(), name, purpose, disallowed, loaded, builder).err()
+    };
+
+    if location == WindowsLocation::CurrentUser {
+        let result = open_group_policy_store(name).and_then(|store| match store {
+            Some(store) => {
+                let span = source_span!(store = name, location = "current user's Group Policy");
+                let _entered = span.enter();
+                debug!("loading roots for {:?} from the current user's Group Policy {} store", purpose, name);
+                build_usable_certs(store.certs(), name, purpose, disallowed, loaded, builder)
+            }
+            None => Ok(()),
+        });
+        if let Err(err) = result {
+            first_error = first_error.or(Some(err));
         }
+    }
 
-        match builder.load_der(cert.to_der().to_vec()) {
-            Err(err) => {
-                first_error = first_error
-                    .or_else(|| Some(Error::new(ErrorKind::InvalidData, err)));
+    if let Some(err) = first_error {
+        Err(err)
+    } else {
+        Ok(())
+    }
+}
+
+/// Reports the roots Microsoft's root program trusts, as the AuthRoot list
+/// Windows last fetched names them, which Windows has not downloaded to the
+/// local machine's "AuthRoot" store, as one error skipped with
+/// `ErrorKind::NotFound`, which lists their SHA-1 thumbprints.
+///
+/// This only reports them: it fetches none.  Windows downloads each root
+/// when a chain it builds first needs it, and a caller which needs one can
+/// build a chain with schannel to have it fetched.
+///
+/// The roots it has downloaded need no reading of their own: the "AuthRoot"
+/// store is a physical store of the merged and local machine's "ROOT"
+/// stores, so they are loaded with those.  Without a list, as where
+/// automatic updates are turned off, this reports nothing.  While a
+/// [TestStore](struct.TestStore.html) is installed, there is no list.
+fn report_missing_auth_roots<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Error> {
+    use schannel::RawPointer;
+    use windows_sys::Win32::Security::Cryptography::{
+        CertFindCertificateInStore,
+        CertFreeCertificateContext,
+        CERT_FIND_SHA1_HASH,
+        CERT_STORE_PROV_SYSTEM_W,
+        CERT_SYSTEM_STORE_LOCAL_MACHINE,
+        CRYPT_INTEGER_BLOB,
+        PKCS_7_ASN_ENCODING,
+        X509_ASN_ENCODING,
+    };
+
+    #[cfg(feature = "test-util")]
+    if crate::test_store::installed().is_some() {
+        return Ok(());
+    }
+
+    let span = source_span!(store = AUTH_ROOT_STORE, location = "local machine's");
+    let _entered = span.enter();
+    let listed = match auth_root_list()? {
+        Some(listed) => listed,
+        None => {
+            debug!("skipped the AuthRoot list: Windows has not fetched one");
+            return Ok(());
+        }
+    };
+    let store = open_existing(CERT_STORE_PROV_SYSTEM_W, CERT_SYSTEM_STORE_LOCAL_MACHINE, AUTH_ROOT_STORE)?;
+
+    let mut missing = Vec::new();
+    for sha1 in &listed {
+        let hash = CRYPT_INTEGER_BLOB { cbData: sha1.len() as u32, pbData: sha1.as_ptr() as *mut u8 };
+        // Safety: `store` is open, and `hash` points into `sha1`, which
+        // outlives the call.  The context found is the caller's to free.
+        unsafe {
+            let found = CertFindCertificateInStore(store.as_ptr() as _, X509_ASN_ENCODING | PKCS_7_ASN_ENCODING, 0,
+                                                   CERT_FIND_SHA1_HASH, &hash as *const _ as *const _,
+                                                   std::ptr::null());
+            if !found.is_null() {
+                CertFreeCertificateContext(found);
+                continue;
             }
-            _ => {}
+        }
+        missing.push(sha1.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    }
+
+    if !missing.is_empty() {
+        builder.skipped(Error::new(ErrorKind::NotFound,
+                                   format!("{} of the {} roots the AuthRoot list names have not been \
+                                            downloaded by Windows, with SHA-1 thumbprints {}",
+                                           missing.len(), listed.len(), missing.join(", "))));
+    }
+    Ok(())
+}
+
+/// Returns the SHA-1 fingerprint of each root the AuthRoot list Windows
+/// last fetched names, or `None` if it has fetched none.  The list is a
+/// certificate trust list Windows Update signs, which Windows caches in the
+/// registry.  Entries Microsoft has disabled, which carry the date their
+/// trust was withdrawn, are left out: Windows does not download those.
+fn auth_root_list() -> Result<Option<Vec<Vec<u8>>>, Error> {
+    /// The attribute of a disabled entry, `CERT_DISALLOWED_FILETIME_PROP_ID`
+    /// under the prefix of the CTL entry properties.
+    const DISALLOWED_FILETIME_OID: &[u8] = b"1.3.6.1.4.1.311.10.11.104";
+
+    use windows_sys::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_MORE_DATA, ERROR_SUCCESS};
+    use windows_sys::Win32::Security::Cryptography::{
+        CertCreateCTLContext,
+        CertFreeCTLContext,
+        PKCS_7_ASN_ENCODING,
+        X509_ASN_ENCODING,
+    };
+    use windows_sys::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY};
+
+    let key = windows_sys::core::w!("SOFTWARE\\Microsoft\\SystemCertificates\\AuthRoot\\AutoUpdate");
+    let value = windows_sys::core::w!("EncodedCtl");
+    let mut len = 0;
+    let mut encoded = Vec::new();
+    // The list can grow between the call which sizes it and the one which
+    // reads it.
+    loop {
+        encoded.resize(len as usize, 0);
+        let data = match encoded.is_empty() {
+            true => std::ptr::null_mut(),
+            false => encoded.as_mut_ptr() as *mut _,
         };
+        // Safety: `key` and `value` are NUL-terminated wide strings, and
+        // `data`, if not null, is writable for `len` bytes.
+        let status = unsafe {
+            RegGetValueW(HKEY_LOCAL_MACHINE, key, value, RRF_RT_REG_BINARY, std::ptr::null_mut(), data, &mut len)
+        };
+        match status {
+            ERROR_SUCCESS if !data.is_null() || len == 0 => break,
+            ERROR_SUCCESS | ERROR_MORE_DATA => continue,
+            ERROR_FILE_NOT_FOUND => return Ok(None),
+            status => return Err(Error::from_raw_os_error(status as i32)),
+        }
+    }
+    encoded.truncate(len as usize);
+
+    // Safety: `encoded` outlives the call, and the context made from it is
+    // only read before being freed.
+    unsafe {
+        let ctl = CertCreateCTLContext(X509_ASN_ENCODING | PKCS_7_ASN_ENCODING, encoded.as_ptr(), encoded.len() as u32);
+        if ctl.is_null() {
+            return Err(Error::new(ErrorKind::InvalidData,
+                                  format!("the cached AuthRoot list cannot be parsed: {}", Error::last_os_error())));
+        }
+        let info = &*(*ctl).pCtlInfo;
+        let listed = (0..info.cCTLEntry as usize)
+            .map(|i| &*info.rgCTLEntry.add(i))
+            .filter(|entry| {
+                !(0..entry.cAttribute as usize).any(|j| {
+                    let oid = std::ffi::CStr::from_ptr((*entry.rgAttribute.add(j)).pszObjId as *const _);
+                    oid.to_bytes() == DISALLOWED_FILETIME_OID
+                })
+            })
+            .map(|entry| {
+                let id = &entry.SubjectIdentifier;
+                std::slice::from_raw_parts(id.pbData, id.cbData as usize).to_vec()
+            })
+            .collect();
+        CertFreeCTLContext(ctl);
+        Ok(Some(listed))
+    }
+}
+
+/// Loads `certs`, from the store called `name`, which are usable for
+/// `purpose` and not yet `loaded`, reporting each of `disallowed` as denied
+/// rather than loading it.
+fn build_usable_certs<B: RootStoreBuilder>(certs: impl IntoIterator<Item = schannel::cert_context::CertContext>,
+                                           name: &str,
+                                           purpose: Purpose,
+                                           disallowed: &HashSet<Vec<u8>>,
+                                           loaded: &mut HashSet<Vec<u8>>,
+                                           builder: &mut B) -> Result<(), Error> {
+    let mut first_error = None;
+    builder.set_provenance(&Provenance::WindowsStore(name.to_owned()), PlatformTrust::InStore);
+
+    for cert in certs {
+        match cert.valid_uses() {
+            Ok(uses) => if !usable_for(uses, purpose) {
+                debug!("skipped {}: not enabled for {:?}", crate::der::describe(cert.to_der()), purpose);
+                continue;
+            },
+            // Skip just this root.
+            Err(err) => {
+                debug!("skipped {}: its uses cannot be read: {}", crate::der::describe(cert.to_der()), err);
+                first_error = first_error.or(Some(err));
+                continue;
+            }
+        }
+
+        let der = cert.to_der();
+        if !loaded.insert(der.to_vec()) {
+            continue;
+        }
+        if disallowed.contains(der) {
+            debug!("skipped {}: it is in the Disallowed store", crate::der::describe(der));
+            builder.set_provenance(&Provenance::WindowsStore(DISALLOWED_STORE.to_owned()), PlatformTrust::Deny);
+            builder.denied(der);
+            builder.set_provenance(&Provenance::WindowsStore(name.to_owned()), PlatformTrust::InStore);
+            continue;
+        }
+
+        if let Err(err) = builder.load_der(der.to_vec()) {
+            first_error = first_error
+                .or_else(|| Some(Error::new(ErrorKind::InvalidData, err)));
+        }
     }
 
     if let Some(err) = first_error {
@@ -38,3 +424,542 @@ pub fn build_native_certs<B: RootStoreBuilder>(builder: &mut B) -> Result<(), Er
         Ok(())
     }
 }
+
+/// Describes where the roots for `purpose` are read from.
+pub fn describe_roots(purpose: Purpose) -> String {
+    format!("current user's Root store, for extended key usage {}", purpose.oid())
+}
+
+/// Describes where `list` is read from.
+#[cfg(feature = "cli")]
+pub fn describe_list(list: crate::List) -> String {
+    match list {
+        crate::List::Intermediates => "current user's CA store",
+        crate::List::Distrusted => "current user's Disallowed store",
+        crate::List::Crls => "current user's CA and Root stores",
+    }.to_owned()
+}
+
+/// This backend reads no environment variables.
+#[cfg(feature = "cli")]
+pub const ENVIRONMENT: &[&str] = &[];
+
+/// What to do when no roots are found.
+#[cfg(feature = "cli")]
+pub const REMEDY: &str = "\
+List the Root store with `certutil -user -store Root`.  Windows fetches
+most roots on first use, by Automatic Root Certificates Update; where Group
+Policy turns that off, deploy roots by Group Policy instead, or import them
+with `certutil -user -addstore Root FILE`.";
+
+/// Probes the current user's stores which certificates are loaded from.
+#[cfg(feature = "cli")]
+pub fn probe_locations() -> Vec<crate::Probe> {
+    vec![("ROOT", Purpose::ALL.to_vec()), ("CA", Vec::new()), ("Disallowed", Vec::new())]
+        .into_iter()
+        .map(|(name, used_for)| crate::Probe {
+            location: format!("current user's {} store", name),
+            found: open_store(name).map(|store| store.certs().count()),
+            used_for,
+        })
+        .collect()
+}
+
+/// The stores the loads read, which a `Watcher` watches for changes.
+#[cfg(feature = "watch")]
+const WATCHED_STORES: &[&str] = &["ROOT", "CA", "Disallowed"];
+
+/// Stops its thread when dropped.  The thread does not wait for this, but
+/// ends once it has seen the stop event, so it is not joined.
+#[cfg(feature = "watch")]
+pub struct Watcher {
+    stop: Arc<Event>,
+}
+
+#[cfg(feature = "watch")]
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        // Safety: the event is open for as long as `self.stop` is.
+        unsafe {
+            windows_sys::Win32::System::Threading::SetEvent(self.stop.0);
+        }
+    }
+}
+
+/// An auto-reset event, closed when dropped.
+#[cfg(feature = "watch")]
+struct Event(windows_sys::Win32::Foundation::HANDLE);
+
+// Safety: an event handle can be waited on and signalled from any thread.
+#[cfg(feature = "watch")]
+unsafe impl Send for Event {}
+#[cfg(feature = "watch")]
+unsafe impl Sync for Event {}
+
+#[cfg(feature = "watch")]
+impl Event {
+    fn new() -> Result<Self, Error> {
+        use windows_sys::Win32::Foundation::FALSE;
+
+        // Safety: an unnamed event with default security, which starts
+        // unsignalled.
+        let handle = unsafe {
+            windows_sys::Win32::System::Threading::CreateEventW(std::ptr::null(), FALSE, FALSE, std::ptr::null())
+        };
+        if handle.is_null() {
+            Err(Error::last_os_error())
+        } else {
+            Ok(Event(handle))
+        }
+    }
+}
+
+#[cfg(feature = "watch")]
+impl Drop for Event {
+    fn drop(&mut self) {
+        // Safety: the handle is open until now, and nothing uses it after.
+        unsafe {
+            windows_sys::Win32::Foundation::CloseHandle(self.0);
+        }
+    }
+}
+
+/// Calls `on_change` whenever the stores the loads read are changed, as
+/// the stores themselves notify, from a thread of its own.
+///
+/// This fails where a store cannot notify changes, as stores standing in
+/// for the user's do not.
+#[cfg(feature = "watch")]
+pub fn watch(mut on_change: impl FnMut() + Send + 'static) -> Result<Watcher, Error> {
+    use schannel::RawPointer;
+    use windows_sys::Win32::Foundation::{FALSE, HANDLE, WAIT_OBJECT_0};
+    use windows_sys::Win32::Security::Cryptography::{
+        CertControlStore,
+        CERT_STORE_CTRL_NOTIFY_CHANGE,
+        CERT_STORE_CTRL_RESYNC,
+    };
+    use windows_sys::Win32::System::Threading::{WaitForMultipleObjects, INFINITE};
+
+    let stop = Arc::new(Event::new()?);
+    let mut stores = Vec::new();
+    for name in WATCHED_STORES {
+        let store = open_store(name)?;
+        let changed = Event::new()?;
+        // Safety: the store signals the event until the store is closed,
+        // which happens before the event is, as `stores` holds them so.
+        let armed = unsafe {
+            CertControlStore(store.as_ptr() as _, 0, CERT_STORE_CTRL_NOTIFY_CHANGE,
+                             &changed.0 as *const HANDLE as *const _)
+        };
+        if armed == 0 {
+            return Err(Error::last_os_error());
+        }
+        stores.push((store, changed));
+    }
+
+    let thread_stop = stop.clone();
+    std::thread::Builder::new()
+        .name("rustls-native-certs store notifications".into())
+        .spawn(move || {
+            let handles: Vec<HANDLE> = std::iter::once(thread_stop.0)
+                .chain(stores.iter().map(|(_, changed)| changed.0))
+                .collect();
+            loop {
+                // Safety: each handle is open for as long as the thread runs.
+                let signalled = unsafe {
+                    WaitForMultipleObjects(handles.len() as u32, handles.as_ptr(), FALSE, INFINITE)
+                };
+                // The stop event, or a failed wait, ends the thread.
+                let (store, changed) = match signalled.checked_sub(WAIT_OBJECT_0 + 1)
+                    .and_then(|index| stores.get(index as usize))
+                {
+                    Some(store) => store,
+                    None => break,
+                };
+                // Resynchronizing the store rearms its notification.
+                // Safety: as when the notification was first armed.
+                unsafe {
+                    CertControlStore(store.as_ptr() as _, 0, CERT_STORE_CTRL_RESYNC,
+                                     &changed.0 as *const HANDLE as *const _);
+                }
+                on_change();
+            }
+        })?;
+
+    Ok(Watcher { stop })
+}
+
+pub fn load_intermediates() -> Result<Vec<Vec<u8>>, Error> {
+    let store = open_store("CA")?;
+    Ok(store.certs()
+        .filter(|cert| cert.valid_uses().is_ok_and(|uses| usable_for(uses, Purpose::ServerAuth)))
+        .map(|cert| cert.to_der().to_vec())
+        .collect())
+}
+
+/// The store of certificates the user, or Windows Update, distrusts.
+static DISALLOWED_STORE: &str = "Disallowed";
+
+/// The local machine's store of the roots Windows has downloaded from
+/// Microsoft's root program.
+static AUTH_ROOT_STORE: &str = "AuthRoot";
+
+pub fn load_distrusted() -> Result<Vec<Vec<u8>>, Error> {
+    let store = open_store(DISALLOWED_STORE)?;
+    Ok(store.certs()
+        .map(|cert| cert.to_der().to_vec())
+        .collect())
+}
+
+pub fn load_crls() -> Result<Vec<Vec<u8>>, Error> {
+    use schannel::RawPointer;
+    use windows_sys::Win32::Security::Cryptography::{CertEnumCRLsInStore, CRL_CONTEXT};
+
+    let mut crls: Vec<Vec<u8>> = Vec::new();
+    for name in &["CA", "ROOT"] {
+        let store = open_store(name)?;
+
+        // Safety: `store` is open for the duration of the loop, and each
+        // call frees the context passed in (the final null one excepted).
+        unsafe {
+            let mut crl: *mut CRL_CONTEXT = std::ptr::null_mut();
+            loop {
+                crl = CertEnumCRLsInStore(store.as_ptr() as _, crl);
+                if crl.is_null() {
+                    break;
+                }
+                let der = std::slice::from_raw_parts((*crl).pbCrlEncoded, (*crl).cbCrlEncoded as usize);
+                if !crls.iter().any(|seen| seen == der) {
+                    crls.push(der.to_vec());
+                }
+            }
+        }
+    }
+    Ok(crls)
+}
+
+/// The store holding the current user's certificates with private keys.
+static MY_STORE: &str = "MY";
+
+pub fn load_client_identities() -> Result<Vec<ClientIdentity>, Error> {
+    let store = open_store(MY_STORE)?;
+
+    let mut identities = Vec::new();
+    for cert in store.certs() {
+        if !has_private_key(&cert) {
+            continue;
+        }
+
+        let label = cert.friendly_name()
+            .ok()
+            .filter(|name| !name.is_empty());
+        let identity = ClientIdentity::new(chain_for(&cert)?,
+                                           label,
+                                           IdentitySource::WindowsStore(MY_STORE.to_string()));
+        identities.push(identity.with_native_key(NativeKey(cert)));
+    }
+    Ok(identities)
+}
+
+/// Checks whether `cert` is linked to a private key, without opening the
+/// key: that can prompt for a smart card or PIN.
+fn has_private_key(cert: &schannel::cert_context::CertContext) -> bool {
+    use schannel::RawPointer;
+    use windows_sys::Win32::Security::Cryptography::{
+        CertGetCertificateContextProperty,
+        CERT_KEY_PROV_INFO_PROP_ID,
+    };
+
+    let mut len = 0;
+    // Safety: `cert` is a live CERT_CONTEXT; a null buffer asks only for the length.
+    unsafe {
+        CertGetCertificateContextProperty(cert.as_ptr() as _,
+                                          CERT_KEY_PROV_INFO_PROP_ID,
+                                          std::ptr::null_mut(),
+                                          &mut len) != 0
+    }
+}
+
+/// Builds the chain for `cert` with the system chain engine, returning the
+/// DER encodings of its certificates, starting with `cert` itself.
+fn chain_for(cert: &schannel::cert_context::CertContext) -> Result<Vec<Vec<u8>>, Error> {
+    use schannel::RawPointer;
+    use windows_sys::Win32::Security::Cryptography::{
+        CertFreeCertificateChain,
+        CertGetCertificateChain,
+        CERT_CHAIN_CONTEXT,
+        CERT_CHAIN_PARA,
+    };
+
+    let para = CERT_CHAIN_PARA {
+        cbSize: std::mem::size_of::<CERT_CHAIN_PARA>() as u32,
+        ..Default::default()
+    };
+    let mut context: *mut CERT_CHAIN_CONTEXT = std::ptr::null_mut();
+
+    // Safety: `cert` is a live CERT_CONTEXT, and on success `context` is
+    // a chain context which is only read before being freed.
+    unsafe {
+        if CertGetCertificateChain(std::ptr::null_mut(),
+                                   cert.as_ptr() as _,
+                                   std::ptr::null(),
+                                   std::ptr::null_mut(),
+                                   &para,
+                                   0,
+                                   std::ptr::null(),
+                                   &mut context) == 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let mut chain = Vec::new();
+        if (*context).cChain > 0 {
+            let simple = &**(*context).rgpChain;
+            for i in 0..simple.cElement as usize {
+                let element = &**simple.rgpElement.add(i);
+                let cert = &*element.pCertContext;
+                chain.push(std::slice::from_raw_parts(cert.pbCertEncoded,
+                                                      cert.cbCertEncoded as usize)
+                           .to_vec());
+            }
+        }
+        CertFreeCertificateChain(context);
+
+        if chain.is_empty() {
+            chain.push(cert.to_der().to_vec());
+        }
+        Ok(chain)
+    }
+}
+
+/// In-memory stores standing in for the current user's.
+#[cfg(feature = "test-util")]
+pub struct TestStore {
+    root: schannel::cert_store::CertStore,
+    ca: schannel::cert_store::CertStore,
+    disallowed: schannel::cert_store::CertStore,
+    my: schannel::cert_store::CertStore,
+}
+
+#[cfg(feature = "test-util")]
+impl TestStore {
+    pub fn new() -> Result<Self, Error> {
+        let memory = || schannel::cert_store::Memory::new().map(|memory| memory.into_store());
+        Ok(TestStore {
+            root: memory()?,
+            ca: memory()?,
+            disallowed: memory()?,
+            my: memory()?,
+        })
+    }
+
+    pub fn add_root(&mut self, der: &[u8]) -> Result<(), Error> {
+        add_encoded(&mut self.root, der)
+    }
+
+    pub fn add_intermediate(&mut self, der: &[u8]) -> Result<(), Error> {
+        add_encoded(&mut self.ca, der)
+    }
+
+    pub fn add_distrusted(&mut self, der: &[u8]) -> Result<(), Error> {
+        add_encoded(&mut self.disallowed, der)
+    }
+
+    /// Certificates with keys go in "MY", and the rest of the chain in
+    /// "CA".  The keys are imported into the user's key storage, as for
+    /// any PFX import.
+    pub fn add_identity_pkcs12(&mut self, pkcs12: &[u8], password: &str) -> Result<(), Error> {
+        use schannel::cert_store::{CertAdd, PfxImportOptions};
+
+        let imported = PfxImportOptions::new().password(password).import(pkcs12)?;
+        for cert in imported.certs() {
+            let store = if has_private_key(&cert) { &mut self.my } else { &mut self.ca };
+            store.add_cert(&cert, CertAdd::ReplaceExisting)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the store called `name`.  Stores this crate does not read
+    /// are empty.
+    pub fn store(&self, name: &str) -> Result<schannel::cert_store::CertStore, Error> {
+        Ok(match name.to_ascii_uppercase().as_str() {
+            "ROOT" => self.root.clone(),
+            "CA" => self.ca.clone(),
+            "DISALLOWED" => self.disallowed.clone(),
+            "MY" => self.my.clone(),
+            _ => schannel::cert_store::Memory::new()?.into_store(),
+        })
+    }
+}
+
+#[cfg(feature = "test-util")]
+fn add_encoded(store: &mut schannel::cert_store::CertStore, der: &[u8]) -> Result<(), Error> {
+    use schannel::cert_store::CertAdd;
+
+    let cert = schannel::cert_context::CertContext::new(der)?;
+    store.add_cert(&cert, CertAdd::ReplaceExisting)?;
+    Ok(())
+}
+
+pub fn export_pkcs12(key: &NativeKey, chain: &[Vec<u8>], password: &str) -> Result<Vec<u8>, Error> {
+    use schannel::cert_store::{CertAdd, Memory};
+    use schannel::RawPointer;
+    use windows_sys::Win32::Security::Cryptography::{
+        PFXExportCertStoreEx,
+        CRYPT_INTEGER_BLOB,
+        EXPORT_PRIVATE_KEYS,
+        REPORT_NOT_ABLE_TO_EXPORT_PRIVATE_KEY,
+        REPORT_NO_PRIVATE_KEY,
+    };
+
+    let mut memory = Memory::new()?;
+    for der in chain.iter().skip(1) {
+        memory.add_encoded_certificate(der)?;
+    }
+    let mut store = memory.into_store();
+    // The copy keeps the link to the private key.
+    store.add_cert(&key.0, CertAdd::Always)?;
+
+    // Without the REPORT flags, a key which cannot be exported is left out
+    // rather than failing the export.
+    let password = password.encode_utf16().chain(Some(0)).collect::<Vec<_>>();
+    let flags = EXPORT_PRIVATE_KEYS | REPORT_NO_PRIVATE_KEY | REPORT_NOT_ABLE_TO_EXPORT_PRIVATE_KEY;
+    let mut blob = CRYPT_INTEGER_BLOB { cbData: 0, pbData: std::ptr::null_mut() };
+
+    // Safety: `store` is live, and the second call writes no more than the
+    // length the first one reported into a buffer of that length.
+    unsafe {
+        if PFXExportCertStoreEx(store.as_ptr() as _, &mut blob, password.as_ptr(), std::ptr::null(), flags) == 0 {
+            return Err(Error::last_os_error());
+        }
+        let mut pfx = vec![0u8; blob.cbData as usize];
+        blob.pbData = pfx.as_mut_ptr();
+        if PFXExportCertStoreEx(store.as_ptr() as _, &mut blob, password.as_ptr(), std::ptr::null(), flags) == 0 {
+            return Err(Error::last_os_error());
+        }
+        pfx.truncate(blob.cbData as usize);
+        Ok(pfx)
+    }
+}
+
+/// A certificate in the "My" store, through which its private key is opened.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "rustls"), allow(dead_code))]
+pub struct NativeKey(schannel::cert_context::CertContext);
+
+/// Acquiring the key is what may prompt for a smart card or PIN, so it
+/// waits until the first signature.
+#[cfg(feature = "rustls")]
+pub fn signing_key(key: &NativeKey, key_type: KeyType) -> Result<Box<dyn SigningKey>, Error> {
+    let cert = key.0.clone();
+    Ok(Box::new(CngSigningKey {
+        key: sign::LazyKey::new(move || match cert.private_key().acquire()? {
+            PrivateKey::NcryptKey(key) => Ok(key),
+            PrivateKey::CryptProv(_) => Err(Error::new(ErrorKind::Unsupported,
+                                                       "only CNG private keys are supported")),
+        }),
+        key_type,
+    }))
+}
+
+/// A `rustls::sign::SigningKey` which signs using a CNG key handle.
+#[cfg(feature = "rustls")]
+struct CngSigningKey {
+    key: Arc<sign::LazyKey<NcryptKey>>,
+    key_type: KeyType,
+}
+
+#[cfg(feature = "rustls")]
+impl SigningKey for CngSigningKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        sign::choose_scheme(self.key_type, offered)
+            .map(|scheme| Box::new(CngSigner { key: self.key.clone(), scheme }) as Box<dyn Signer>)
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        sign::algorithm(self.key_type)
+    }
+}
+
+#[cfg(feature = "rustls")]
+struct CngSigner {
+    key: Arc<sign::LazyKey<NcryptKey>>,
+    scheme: SignatureScheme,
+}
+
+#[cfg(feature = "rustls")]
+impl Signer for CngSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, TLSError> {
+        use ring::digest;
+        use schannel::RawPointer;
+        use windows_sys::Win32::Security::Cryptography::{
+            NCryptSignHash,
+            BCRYPT_PKCS1_PADDING_INFO,
+            BCRYPT_PSS_PADDING_INFO,
+            BCRYPT_SHA256_ALGORITHM,
+            BCRYPT_SHA384_ALGORITHM,
+            BCRYPT_SHA512_ALGORITHM,
+            NCRYPT_PAD_PKCS1_FLAG,
+            NCRYPT_PAD_PSS_FLAG,
+        };
+
+        let (hash, alg_id) = match self.scheme {
+            SignatureScheme::RSA_PSS_SHA256 |
+                SignatureScheme::RSA_PKCS1_SHA256 |
+                SignatureScheme::ECDSA_NISTP256_SHA256 => (&digest::SHA256, BCRYPT_SHA256_ALGORITHM),
+            SignatureScheme::RSA_PSS_SHA384 |
+                SignatureScheme::RSA_PKCS1_SHA384 |
+                SignatureScheme::ECDSA_NISTP384_SHA384 => (&digest::SHA384, BCRYPT_SHA384_ALGORITHM),
+            SignatureScheme::RSA_PSS_SHA512 |
+                SignatureScheme::RSA_PKCS1_SHA512 => (&digest::SHA512, BCRYPT_SHA512_ALGORITHM),
+            _ => return Err(TLSError::General("unsupported signature scheme".into())),
+        };
+        let key = self.key.get()?;
+        let hash = digest::digest(hash, message);
+        let hash = hash.as_ref();
+
+        let pkcs1 = BCRYPT_PKCS1_PADDING_INFO { pszAlgId: alg_id };
+        let pss = BCRYPT_PSS_PADDING_INFO { pszAlgId: alg_id, cbSalt: hash.len() as u32 };
+        let (padding, flags) = match self.scheme {
+            SignatureScheme::RSA_PSS_SHA256 |
+                SignatureScheme::RSA_PSS_SHA384 |
+                SignatureScheme::RSA_PSS_SHA512 => (&pss as *const _ as *const _, NCRYPT_PAD_PSS_FLAG),
+            SignatureScheme::RSA_PKCS1_SHA256 |
+                SignatureScheme::RSA_PKCS1_SHA384 |
+                SignatureScheme::RSA_PKCS1_SHA512 => (&pkcs1 as *const _ as *const _, NCRYPT_PAD_PKCS1_FLAG),
+            _ => (std::ptr::null(), 0),
+        };
+
+        let failed = |status| TLSError::General(format!("NCryptSignHash failed: {:#x}", status));
+        let mut len = 0;
+        // Safety: the key handle is live for as long as `key`, `padding`
+        // points to a structure of the type `flags` says, and the signature
+        // buffer is as long as we say it is.
+        let signature = unsafe {
+            let key = key.as_ptr() as usize;
+            let status = NCryptSignHash(key, padding, hash.as_ptr(), hash.len() as u32,
+                                        std::ptr::null_mut(), 0, &mut len, flags);
+            if status != 0 {
+                return Err(failed(status));
+            }
+
+            let mut signature = vec![0u8; len as usize];
+            let status = NCryptSignHash(key, padding, hash.as_ptr(), hash.len() as u32,
+                                        signature.as_mut_ptr(), len, &mut len, flags);
+            if status != 0 {
+                return Err(failed(status));
+            }
+            signature.truncate(len as usize);
+            signature
+        };
+
+        match self.scheme {
+            SignatureScheme::ECDSA_NISTP256_SHA256 |
+                SignatureScheme::ECDSA_NISTP384_SHA384 => Ok(der::ecdsa_signature(&signature)),
+            _ => Ok(signature),
+        }
+    }
+
+    fn get_scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
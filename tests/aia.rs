@@ -0,0 +1,87 @@
+// These tests serve the test intermediate from the address named in the
+// test AIA leaf's "CA Issuers" URL.
+#![cfg(feature = "aia")]
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+
+use rustls_native_certs::IntermediateFetcher;
+
+use common::der;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+const TEST_AIA_LEAF: &[u8] = include_bytes!("../integration-tests/test-aia-leaf.pem");
+
+/// Serializes the tests, which all need the same port.
+static PORT: Mutex<()> = Mutex::new(());
+
+/// Serves the test intermediate, in DER, to `requests` requests.
+fn serve_intermediate(requests: usize) -> (MutexGuard<'static, ()>, thread::JoinHandle<()>) {
+    let guard = PORT.lock().unwrap_or_else(|err| err.into_inner());
+    let listener = TcpListener::bind("127.0.0.1:47831").unwrap();
+    let body = der(TEST_INTERMEDIATE);
+    let server = thread::spawn(move || {
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let len = stream.read(&mut buf).unwrap();
+                assert!(len > 0);
+                request.extend_from_slice(&buf[..len]);
+            }
+            assert!(request.starts_with(b"GET /test-intermediate.der HTTP/1.0\r\n"));
+
+            write!(stream, "HTTP/1.0 200 OK\r\nContent-Type: application/pkix-cert\r\n\r\n").unwrap();
+            stream.write_all(&body).unwrap();
+        }
+    });
+    (guard, server)
+}
+
+#[test]
+fn fetches_and_caches_missing_intermediate() {
+    let (_guard, server) = serve_intermediate(1);
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-aia-{}", std::process::id()));
+
+    let leaf = der(TEST_AIA_LEAF);
+    let fetcher = IntermediateFetcher::new().cache_dir(&dir);
+    assert_eq!(fetcher.complete_chain(std::slice::from_ref(&leaf)), vec![leaf.clone(), der(TEST_INTERMEDIATE)]);
+    // A complete chain needs no fetch.
+    let complete = vec![leaf.clone(), der(TEST_INTERMEDIATE)];
+    assert_eq!(fetcher.complete_chain(&complete), complete);
+    server.join().unwrap();
+
+    // The server has gone, so this must come from the disk cache.
+    let fetcher = IntermediateFetcher::new().cache_dir(&dir);
+    assert_eq!(fetcher.complete_chain(&[leaf]), complete);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Roots end the chain without a fetch.
+    let root = der(TEST_CA);
+    assert_eq!(IntermediateFetcher::new().complete_chain(std::slice::from_ref(&root)), vec![root]);
+}
+
+#[cfg(feature = "rustls")]
+#[test]
+fn verifier_completes_chain() {
+    use rustls::{Certificate, RootCertStore, ServerCertVerifier};
+    use rustls_native_certs::AiaVerifier;
+
+    let mut roots = RootCertStore::empty();
+    roots.add(&Certificate(der(TEST_CA))).unwrap();
+    let name = webpki::DNSNameRef::try_from_ascii_str("aia.testserver.example").unwrap();
+    let presented = [Certificate(der(TEST_AIA_LEAF))];
+
+    let (_guard, server) = serve_intermediate(1);
+    let verifier = AiaVerifier::new(IntermediateFetcher::new());
+    assert!(verifier.verify_server_cert(&roots, &presented, name, &[]).is_ok());
+    // The second verification uses the fetcher's memory cache.
+    assert!(verifier.verify_server_cert(&roots, &presented, name, &[]).is_ok());
+    server.join().unwrap();
+}
@@ -0,0 +1,41 @@
+// This test replaces the native store with fixtures via
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`.
+#![cfg(feature = "test-util")]
+
+mod common;
+
+use std::env;
+use std::sync::Arc;
+
+use rustls_native_certs::{invalidate_native_certs_cache, native_certs_cached};
+
+use common::der;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+
+fn fixture(name: &str) -> String {
+    format!("{}/integration-tests/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[test]
+fn caches_until_invalidated() {
+    env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", fixture("no-such-fixture.pem"));
+    native_certs_cached().unwrap_err();
+
+    // A failed load is not cached.
+    env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", fixture("test-ca.pem"));
+    let first = native_certs_cached().unwrap();
+    assert_eq!(*first, vec![der(TEST_CA)]);
+
+    env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", fixture("one-existing-ca.pem"));
+    let second = native_certs_cached().unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+
+    invalidate_native_certs_cache();
+    let third = native_certs_cached().unwrap();
+    assert_eq!(*third, vec![der(ONE_EXISTING_CA)]);
+    assert_eq!(*first, vec![der(TEST_CA)]);
+
+    env::remove_var("RUSTLS_NATIVE_CERTS_OVERRIDE");
+}
@@ -0,0 +1,82 @@
+// These tests replay hand-written captures via `RUSTLS_NATIVE_CERTS_REPLAY`.
+// The environment is shared by every thread, so only one test changes it.
+#![cfg(all(feature = "capture", feature = "test-util"))]
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use rustls_native_certs::{
+    build_native_certs,
+    build_native_certs_for,
+    load_native_crls,
+    load_native_distrusted,
+    load_native_intermediates,
+    Purpose,
+    StoreCapture,
+};
+
+use common::{Collect, der};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+
+#[test]
+fn recording_round_trips_through_text() {
+    let capture = StoreCapture::record();
+    assert_eq!(capture.platform(), env::consts::OS);
+    assert_eq!(StoreCapture::parse(&capture.to_string()).unwrap(), capture);
+
+    assert!(StoreCapture::parse("not a capture").is_err());
+    let header = "# rustls-native-certs store capture\n";
+    assert!(StoreCapture::parse(&format!("{}roots.server-auth der !!!\n", header)).is_err());
+    assert!(StoreCapture::parse(&format!("{}roots.nothing der AA==\n", header)).is_err());
+}
+
+#[test]
+fn replays_captured_store() {
+    let text = format!(
+        "# rustls-native-certs store capture\n\
+         platform plan9\n\
+         roots.server-auth pem {}\n\
+         roots.server-auth der {}\n\
+         roots.email-protection error NotFound no email roots here\n\
+         intermediates der {}\n\
+         crls error PermissionDenied access denied\n",
+        STANDARD.encode(TEST_CA),
+        STANDARD.encode(der(TEST_INTERMEDIATE)),
+        STANDARD.encode(der(TEST_INTERMEDIATE)),
+    );
+    let capture = StoreCapture::parse(&text).unwrap();
+    assert_eq!(capture.platform(), "plan9");
+    assert_eq!(StoreCapture::parse(&capture.to_string()).unwrap(), capture);
+
+    let path = env::temp_dir().join(format!("rustls-native-certs-capture-{}", std::process::id()));
+    fs::write(&path, capture.to_string()).unwrap();
+    env::set_var("RUSTLS_NATIVE_CERTS_REPLAY", &path);
+
+    let mut roots = Collect::default();
+    build_native_certs(&mut roots).unwrap();
+    assert_eq!(roots.0, vec![der(TEST_CA), der(TEST_INTERMEDIATE)]);
+
+    let err = build_native_certs_for(Purpose::EmailProtection, &mut Collect::default()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(err.to_string(), "no email roots here");
+    // Purposes with nothing captured find nothing.
+    let mut roots = Collect::default();
+    build_native_certs_for(Purpose::CodeSigning, &mut roots).unwrap();
+    assert!(roots.0.is_empty());
+
+    assert_eq!(load_native_intermediates().unwrap(), vec![der(TEST_INTERMEDIATE)]);
+    assert!(load_native_distrusted().unwrap().is_empty());
+    assert_eq!(load_native_crls().unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(load_native_intermediates().unwrap_err().kind(), ErrorKind::NotFound);
+    env::remove_var("RUSTLS_NATIVE_CERTS_REPLAY");
+}
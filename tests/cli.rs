@@ -0,0 +1,150 @@
+// These tests run the `native-certs` command over a fixture named by
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`, which only the child process sees.
+#![cfg(all(feature = "cli", feature = "test-util"))]
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("integration-tests").join(name)
+}
+
+fn native_certs(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_native-certs"))
+        .args(args)
+        .env("RUSTLS_NATIVE_CERTS_OVERRIDE", fixture("test-ca.pem"))
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+const TEST_CA_SHA256: &str = "d6e5c2b2f866f7ad084c8eb44b5fe473b44852d40bb6e04567d8562831df839c";
+
+#[test]
+fn dumps_store() {
+    let table = stdout(&native_certs(&["dump"]));
+    let mut lines = table.lines();
+    assert_eq!(lines.next().unwrap(),
+               format!("1 roots for server-auth, from override fixture {}", fixture("test-ca.pem").display()));
+    lines.next();
+    assert_eq!(lines.next().unwrap(), format!("{}  2126-09-20  CN=rustls-native-certs test CA", &TEST_CA_SHA256[..16]));
+
+    let json = stdout(&native_certs(&["dump", "--format", "json", "--all"]));
+    assert!(json.starts_with("[\n  {\"kind\": \"root\", \"purpose\": \"server-auth\", \
+                              \"subject\": \"CN=rustls-native-certs test CA\""), "{}", json);
+    assert!(json.contains(&format!("\"sha256\": \"{}\", \"not_after\": \"2126-09-20T10:38:32Z\", \"expired\": false",
+                                   TEST_CA_SHA256)));
+    assert!(json.ends_with("}\n]\n"));
+
+    let pem = stdout(&native_certs(&["dump", "--format", "pem"]));
+    assert!(pem.starts_with("# root: CN=rustls-native-certs test CA\n"));
+    let certs = rustls_pemfile::certs(&mut pem.as_bytes()).unwrap();
+    let expected = rustls_pemfile::certs(&mut &std::fs::read(fixture("test-ca.pem")).unwrap()[..]).unwrap();
+    assert_eq!(certs, expected);
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    assert_eq!(native_certs(&[]).status.code(), Some(2));
+    assert_eq!(native_certs(&["frobnicate"]).status.code(), Some(2));
+    assert_eq!(native_certs(&["dump", "--format", "xml"]).status.code(), Some(2));
+    assert_eq!(native_certs(&["dump", "--purpose"]).status.code(), Some(2));
+    assert!(stdout(&native_certs(&["dump", "--help"])).starts_with("usage: native-certs dump"));
+}
+
+#[test]
+fn diffs_store() {
+    let test_ca = fixture("test-ca.pem");
+    let other_ca = fixture("one-existing-ca.pem");
+
+    let same = native_certs(&["diff", "--against", test_ca.to_str().unwrap()]);
+    assert!(stdout(&same).starts_with("0 roots only in the native store"));
+
+    let other = native_certs(&["diff", "--against", other_ca.to_str().unwrap()]);
+    assert_eq!(other.status.code(), Some(1));
+    let text = String::from_utf8(other.stdout).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", text);
+    assert_eq!(lines[1], format!("  {}  CN=rustls-native-certs test CA", &TEST_CA_SHA256[..16]));
+    assert_eq!(lines[2], format!("1 roots only in {}:", other_ca.display()));
+    assert!(lines[3].ends_with("OU=GlobalSign Root CA - R3, O=GlobalSign, CN=GlobalSign"));
+
+    let mozilla = native_certs(&["diff", "--format", "json"]);
+    assert_eq!(mozilla.status.code(), Some(1));
+    let json = String::from_utf8(mozilla.stdout).unwrap();
+    assert!(json.starts_with(&format!("{{\n  \"only_native\": [\n    {{\"subject\": \"CN=rustls-native-certs test CA\", \
+                                       \"sha256\": \"{}\"}}\n  ],", TEST_CA_SHA256)), "{}", json);
+
+    let missing = native_certs(&["diff", "--against", "/no/such/bundle.pem"]);
+    assert_eq!(missing.status.code(), Some(2));
+}
+
+#[test]
+fn watches_store() {
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-watch-{}", std::process::id()));
+    // A run which failed, in a process since given the same id, may have
+    // left the directory behind.
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(fixture("test-ca.pem"), dir.join("test-ca.pem")).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_native-certs"))
+        .args(["watch", "--poll", "0.1", "--format", "json"])
+        .env("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // The watcher announces itself once it has loaded the store.
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut banner = String::new();
+    stderr.read_line(&mut banner).unwrap();
+    assert!(banner.starts_with("native-certs: watching 1 roots"), "{}", banner);
+
+    let (tx, rx) = mpsc::channel();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    std::thread::spawn(move || {
+        for line in stdout.lines() {
+            if tx.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+
+    std::fs::copy(fixture("one-existing-ca.pem"), dir.join("one-existing-ca.pem")).unwrap();
+    let line = rx.recv_timeout(Duration::from_secs(30));
+    child.kill().unwrap();
+    child.wait().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let line = line.expect("no change reported");
+    assert!(line.contains("\"change\": \"added\", \"sha256\": \"cbb522d7b7f127ad6a0113865bdf1cd4102e7d0759af635a7cf4720dc963c53b\", \
+                           \"subject\": \"OU=GlobalSign Root CA - R3, O=GlobalSign, CN=GlobalSign\"}"), "{}", line);
+}
+
+#[test]
+fn doctors_store() {
+    let healthy = stdout(&native_certs(&["doctor"]));
+    assert!(healthy.contains(&format!("\n  1 roots for server-auth, from override fixture {}\n",
+                                      fixture("test-ca.pem").display())), "{}", healthy);
+    assert!(healthy.contains("Certificates are loaded from the override fixture"), "{}", healthy);
+    assert!(healthy.ends_with("\nNo problems found.\n"), "{}", healthy);
+
+    let missing = Command::new(env!("CARGO_BIN_EXE_native-certs"))
+        .arg("doctor")
+        .env("RUSTLS_NATIVE_CERTS_OVERRIDE", "/no/such/fixture.pem")
+        .output()
+        .unwrap();
+    assert_eq!(missing.status.code(), Some(1));
+    let report = String::from_utf8(missing.stdout).unwrap();
+    assert!(report.contains("$RUSTLS_NATIVE_CERTS_OVERRIDE names /no/such/fixture.pem, which does not exist"), "{}", report);
+    assert!(report.contains("Loading roots for server-auth failed"), "{}", report);
+}
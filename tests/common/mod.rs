@@ -0,0 +1,27 @@
+//! Helpers the integration tests share.  Each test uses only some of them.
+#![allow(dead_code)]
+
+use std::io::{BufRead, Error};
+
+use rustls_native_certs::RootStoreBuilder;
+
+/// Returns the DER encoding of the first certificate in `pem`.
+pub fn der(pem: &[u8]) -> Vec<u8> {
+    rustls_pemfile::certs(&mut &pem[..]).unwrap().remove(0)
+}
+
+/// A builder which keeps each root it is given.
+#[derive(Default)]
+pub struct Collect(pub Vec<Vec<u8>>);
+
+impl RootStoreBuilder for Collect {
+    fn load_der(&mut self, der: Vec<u8>) -> Result<(), Error> {
+        self.0.push(der);
+        Ok(())
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn BufRead) -> Result<(), Error> {
+        self.0.extend(rustls_pemfile::certs(rd)?);
+        Ok(())
+    }
+}
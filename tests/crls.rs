@@ -0,0 +1,27 @@
+// This test points the unix backend at a scratch CRL directory via
+// `RUSTLS_NATIVE_CERTS_CRL_DIRS`.
+#![cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+
+use std::env;
+use std::fs;
+
+use rustls_native_certs::load_native_crls;
+
+const TEST_CA_CRL: &[u8] = include_bytes!("../integration-tests/test-ca.crl.pem");
+
+#[test]
+fn loads_crls_from_configured_dirs() {
+    let dir = env::temp_dir().join(format!("rustls-native-certs-crls-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let der = rustls_pemfile::crls(&mut &TEST_CA_CRL[..]).unwrap().remove(0);
+    fs::write(dir.join("test-ca.crl.pem"), TEST_CA_CRL).unwrap();
+    fs::write(dir.join("test-ca.crl"), &der).unwrap();
+
+    env::set_var("RUSTLS_NATIVE_CERTS_CRL_DIRS", &dir);
+    let crls = load_native_crls().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    // The PEM and DER copies are the same CRL, so it appears once.
+    assert_eq!(crls.iter().filter(|crl| **crl == der).count(), 1);
+}
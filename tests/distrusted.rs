@@ -0,0 +1,40 @@
+// This test points the unix backend at a scratch directory via
+// `RUSTLS_NATIVE_CERTS_DISTRUSTED_DIRS`.
+#![cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+
+use std::env;
+use std::fs;
+
+use rustls_native_certs::load_native_distrusted;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+
+#[test]
+fn loads_distrusted_from_configured_dirs() {
+    let dir = env::temp_dir().join(format!("rustls-native-certs-distrusted-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let der = rustls_pemfile::certs(&mut &TEST_CA[..]).unwrap().remove(0);
+    fs::write(dir.join("test-ca.pem"), TEST_CA).unwrap();
+    fs::write(dir.join("test-ca.der"), &der).unwrap();
+
+    env::set_var("RUSTLS_NATIVE_CERTS_DISTRUSTED_DIRS", &dir);
+    let distrusted = load_native_distrusted().unwrap();
+    #[cfg(feature = "metadata")]
+    let fingerprints = rustls_native_certs::distrusted_fingerprints().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    // The PEM and DER copies are the same certificate, so it appears once.
+    assert_eq!(distrusted.iter().filter(|cert| **cert == der).count(), 1);
+
+    #[cfg(feature = "metadata")]
+    {
+        let hex: Vec<String> = fingerprints.iter()
+            .map(|fingerprint| fingerprint.iter().map(|b| format!("{:02x}", b)).collect())
+            .collect();
+        assert_eq!(hex.len(), distrusted.len());
+        assert!(hex.iter().any(|fingerprint| {
+            fingerprint == "d6e5c2b2f866f7ad084c8eb44b5fe473b44852d40bb6e04567d8562831df839c"
+        }));
+    }
+}
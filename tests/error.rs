@@ -0,0 +1,40 @@
+use std::error::Error as _;
+use std::io::{self, ErrorKind};
+
+use rustls_native_certs::Error;
+
+#[test]
+fn classifies_errors() {
+    let classify = |kind, msg| Error::from(io::Error::new(kind, msg));
+    assert!(matches!(classify(ErrorKind::PermissionDenied, "denied"), Error::PermissionDenied(_)));
+    assert!(matches!(classify(ErrorKind::NotFound, "no bundle"), Error::StoreNotFound(_)));
+    assert!(matches!(classify(ErrorKind::InvalidData, "bad PEM"), Error::Parse(_)));
+    assert!(matches!(classify(ErrorKind::Unsupported, "no watching"), Error::Unsupported(_)));
+    assert!(matches!(classify(ErrorKind::TimedOut, "too slow"), Error::Other(_)));
+
+    // Failures the operating system reports are the platform's.
+    let os = Error::from(io::Error::from_raw_os_error(5));
+    assert!(matches!(os, Error::Platform(_)));
+    assert_eq!(os.to_string(), io::Error::from_raw_os_error(5).to_string());
+    assert!(os.source().is_some());
+
+    let err = io::Error::from(classify(ErrorKind::InvalidData, "bad PEM"));
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "bad PEM");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn classifies_injected_faults() {
+    use rustls_native_certs::{inject_fault, Fault};
+
+    let _fault = inject_fault(Fault::PermissionDenied);
+    let err = rustls_native_certs::load_native_intermediates().unwrap_err();
+    assert!(matches!(Error::from(err), Error::PermissionDenied(_)));
+}
+
+#[test]
+fn supports_the_test_platforms() {
+    // Every platform the tests run on has a backend.
+    assert!(rustls_native_certs::is_supported());
+}
@@ -0,0 +1,46 @@
+// This test loads from a scratch directory named by
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`.
+#![cfg(feature = "test-util")]
+
+mod common;
+
+use common::der;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+
+#[test]
+fn exports_pem_bundle() {
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-export-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let store = dir.join("store");
+    std::fs::create_dir_all(&store).unwrap();
+    std::fs::write(store.join("a.pem"), TEST_CA).unwrap();
+    std::fs::write(store.join("b.pem"), [ONE_EXISTING_CA, TEST_CA].concat()).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &store);
+
+    // The bundle replaces what was there, without duplicates.
+    let bundle = dir.join("bundle.pem");
+    std::fs::write(&bundle, "stale").unwrap();
+    assert_eq!(rustls_native_certs::export_pem_bundle(&bundle).unwrap(), 2);
+    let contents = std::fs::read(&bundle).unwrap();
+    assert_eq!(rustls_pemfile::certs(&mut &contents[..]).unwrap(), vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+
+    // A failed load leaves the bundle alone.
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", dir.join("missing"));
+    assert!(rustls_native_certs::export_pem_bundle(&bundle).is_err());
+    assert_eq!(std::fs::read(&bundle).unwrap(), contents);
+
+    // A failed write leaves no temporary file behind.
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &store);
+    let taken = dir.join("taken");
+    std::fs::create_dir(&taken).unwrap();
+    assert!(rustls_native_certs::export_pem_bundle(&taken).is_err());
+    let mut names = std::fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["bundle.pem", "store", "taken"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
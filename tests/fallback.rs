@@ -0,0 +1,36 @@
+// This test loads from a scratch directory named by
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`.
+#![cfg(all(feature = "webpki-roots", feature = "test-util"))]
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+// A root in the Mozilla set.
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+
+#[test]
+fn adds_mozilla_roots() {
+    let mozilla = webpki_roots::TLS_SERVER_ROOTS.0.len();
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-fallback-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+
+    // An empty store falls back to the Mozilla roots alone.
+    assert_eq!(rustls_native_certs::load_native_certs_or_fallback().len(), mozilla);
+
+    // A native root is kept, and one in the Mozilla set not added twice.
+    std::fs::write(dir.join("a.pem"), TEST_CA).unwrap();
+    std::fs::write(dir.join("b.pem"), ONE_EXISTING_CA).unwrap();
+    assert_eq!(rustls_native_certs::load_native_certs_or_fallback().len(), mozilla + 1);
+
+    // A Mozilla root the platform distrusts is not added.
+    std::fs::remove_file(dir.join("b.pem")).unwrap();
+    std::fs::create_dir_all(dir.join("distrusted")).unwrap();
+    std::fs::write(dir.join("distrusted").join("b.pem"), ONE_EXISTING_CA).unwrap();
+    assert_eq!(rustls_native_certs::load_native_certs_or_fallback().len(), mozilla);
+
+    // A store which cannot be read falls back too.
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", dir.join("missing"));
+    assert_eq!(rustls_native_certs::load_native_certs_or_fallback().len(), mozilla);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,65 @@
+// This test calls the C API as a C program would, loading from a scratch
+// directory named by `RUSTLS_NATIVE_CERTS_OVERRIDE`.
+#![cfg(all(feature = "ffi", feature = "test-util"))]
+
+mod common;
+
+use std::ffi::CStr;
+use std::ptr;
+
+use rustls_native_certs::ffi::*;
+
+use common::der;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+
+fn last_error() -> String {
+    unsafe { CStr::from_ptr(rnc_last_error_message()) }.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn loads_through_c_api() {
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-ffi-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.pem"), TEST_CA).unwrap();
+    std::fs::write(dir.join("b.pem"), ONE_EXISTING_CA).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+
+    unsafe {
+        let mut certs = ptr::null_mut();
+        assert_eq!(rnc_load_certs(RNC_PURPOSE_SERVER_AUTH, &mut certs), RNC_OK);
+        assert_eq!(rnc_certs_len(certs), 2);
+        let mut found = Vec::new();
+        for i in 0..rnc_certs_len(certs) {
+            let (mut der, mut der_len) = (ptr::null(), 0);
+            assert_eq!(rnc_certs_get(certs, i, &mut der, &mut der_len), RNC_OK);
+            found.push(std::slice::from_raw_parts(der, der_len).to_vec());
+        }
+        assert_eq!(found, vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+
+        let (mut der, mut der_len) = (ptr::null(), 0);
+        assert_eq!(rnc_certs_get(certs, 2, &mut der, &mut der_len), RNC_ERROR_INVALID_ARGUMENT);
+        assert_eq!(last_error(), "index 2 is past the 2 roots");
+        assert_eq!(rnc_certs_get(certs, 0, ptr::null_mut(), &mut der_len), RNC_ERROR_INVALID_ARGUMENT);
+        rnc_certs_free(certs);
+
+        assert_eq!(rnc_load_certs(4, &mut certs), RNC_ERROR_INVALID_ARGUMENT);
+        assert!(certs.is_null());
+        assert_eq!(rnc_load_certs(RNC_PURPOSE_SERVER_AUTH, ptr::null_mut()), RNC_ERROR_INVALID_ARGUMENT);
+        assert_eq!(rnc_certs_len(ptr::null()), 0);
+        rnc_certs_free(ptr::null_mut());
+
+        // Failures of the store come back as their codes, with messages.
+        let empty = dir.join("empty");
+        std::fs::create_dir_all(&empty).unwrap();
+        std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &empty);
+        assert_eq!(rnc_load_certs(RNC_PURPOSE_SERVER_AUTH, &mut certs), RNC_ERROR_NO_CERTS_FOUND);
+        assert!(last_error().starts_with("no root certificates found in "));
+        std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", dir.join("missing"));
+        assert_eq!(rnc_load_certs(RNC_PURPOSE_SERVER_AUTH, &mut certs), RNC_ERROR_STORE_NOT_FOUND);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
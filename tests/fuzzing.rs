@@ -0,0 +1,33 @@
+// Runs the fuzzing entry points over the fixtures and some malformed
+// input, as a smoke test for the fuzz targets in `fuzz/`.
+#![cfg(feature = "fuzzing")]
+
+use rustls_native_certs::fuzzing;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+
+#[test]
+fn parses_fixtures_and_rejects_garbage() {
+    let bundle = [TEST_CA, TEST_INTERMEDIATE].concat();
+    let certs = fuzzing::pem_bundle(&bundle);
+    assert_eq!(certs.len(), 2);
+    assert!(certs.iter().all(|der| fuzzing::certificate(der)));
+    assert!(fuzzing::pkcs7_certificates(&certs[0]).is_none());
+
+    for len in 0..certs[0].len() {
+        assert!(!fuzzing::certificate(&certs[0][..len]));
+        let _ = fuzzing::pkcs7_certificates(&certs[0][..len]);
+    }
+    assert!(fuzzing::pem_bundle(b"-----BEGIN CERTIFICATE-----\n!!\n-----END CERTIFICATE-----\n").is_empty());
+
+    #[cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+    {
+        use rustls_native_certs::Purpose;
+
+        // A plain certificate carries no trust settings, so is trusted for nothing.
+        let trusted = String::from_utf8_lossy(TEST_CA).replace("CERTIFICATE", "TRUSTED CERTIFICATE");
+        assert!(fuzzing::trusted_certificates(trusted.as_bytes(), Purpose::ServerAuth).is_empty());
+        assert!(fuzzing::trusted_certificates(b"-----BEGIN TRUSTED CERTIFICATE-----", Purpose::ServerAuth).is_empty());
+    }
+}
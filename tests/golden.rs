@@ -0,0 +1,75 @@
+#![cfg(feature = "test-util")]
+
+use std::io::ErrorKind;
+
+use rustls_native_certs::{MockCertificateSource, Purpose, StoreSnapshot};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+
+fn snapshot(source: &MockCertificateSource) -> StoreSnapshot {
+    StoreSnapshot::capture_from(source, Purpose::ServerAuth).unwrap()
+}
+
+#[test]
+fn snapshots_are_canonical() {
+    let both = MockCertificateSource::new().with_root_pem(ONE_EXISTING_CA).with_root_pem(TEST_CA);
+    let reversed = MockCertificateSource::new().with_root_pem(TEST_CA).with_root_pem(ONE_EXISTING_CA);
+    let text = snapshot(&both).to_text();
+    assert_eq!(text, snapshot(&reversed).to_text());
+    assert_eq!(text.lines().count(), 3);
+    assert!(text.contains(" CN=rustls-native-certs test CA"));
+    assert_eq!(StoreSnapshot::parse(&text).unwrap(), snapshot(&both));
+    assert!(StoreSnapshot::parse("not a fingerprint").is_err());
+}
+
+#[test]
+fn checks_golden_files() {
+    let path = std::env::temp_dir().join(format!("rustls-native-certs-golden-{}.txt", std::process::id()));
+    let one = snapshot(&MockCertificateSource::new().with_root_pem(TEST_CA));
+    let both = snapshot(&MockCertificateSource::new().with_root_pem(TEST_CA).with_root_pem(ONE_EXISTING_CA));
+
+    assert_eq!(one.check_golden(&path).unwrap_err().kind(), ErrorKind::NotFound);
+    std::fs::write(&path, one.to_text()).unwrap();
+    one.check_golden(&path).unwrap();
+
+    let err = both.check_golden(&path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string().lines().filter(|line| line.starts_with("+ ")).count(), 1);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn assertions_over_snapshots() {
+    use rustls_native_certs::{
+        assert_contains_fingerprint,
+        assert_contains_subject,
+        assert_not_contains_fingerprint,
+        assert_not_contains_subject,
+        assert_store_eq,
+    };
+
+    let one = snapshot(&MockCertificateSource::new().with_root_pem(TEST_CA));
+    let both = snapshot(&MockCertificateSource::new().with_root_pem(TEST_CA).with_root_pem(ONE_EXISTING_CA));
+    let hex: String = one.entries().next().unwrap().0.iter().map(|b| format!("{:02x}", b)).collect();
+    let colons: Vec<String> = hex.as_bytes().chunks(2).map(|pair| String::from_utf8(pair.to_vec()).unwrap()).collect();
+
+    assert_contains_fingerprint(&one, &hex);
+    assert_contains_fingerprint(&one, &colons.join(":").to_uppercase());
+    assert_not_contains_fingerprint(&StoreSnapshot::default(), &hex);
+    assert_contains_subject(&one, "CN=rustls-native-certs test CA");
+    assert_not_contains_subject(&one, "O=DigiNotar");
+    assert_store_eq(&both, &both.clone());
+
+    let message = |f: &(dyn Fn() + std::panic::RefUnwindSafe)| {
+        let err = std::panic::catch_unwind(f).unwrap_err();
+        err.downcast_ref::<String>().unwrap().clone()
+    };
+    let failure = message(&|| assert_contains_subject(&one, "CN=missing"));
+    assert!(failure.contains(&format!("\n  {} CN=rustls-native-certs test CA", hex)), "{}", failure);
+    assert!(message(&|| assert_contains_fingerprint(&StoreSnapshot::default(), &hex)).ends_with("it is empty"));
+    assert!(message(&|| assert_not_contains_fingerprint(&one, &hex)).contains("test CA"));
+    assert!(message(&|| assert_not_contains_subject(&both, "test CA")).contains(&hex));
+    assert!(message(&|| assert_contains_fingerprint(&one, "abc")).contains("not a SHA-256 fingerprint"));
+    assert_eq!(message(&|| assert_store_eq(&one, &both)).lines().filter(|line| line.starts_with("- ")).count(), 1);
+}
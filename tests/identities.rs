@@ -0,0 +1,115 @@
+// These tests point the unix backend at a PKCS#12 fixture via
+// `RUSTLS_NATIVE_CERTS_CLIENT_PKCS12`.  The environment is shared by
+// every thread, so only one test changes it, running each case in turn.
+#![cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+
+#[cfg(feature = "pkcs12")]
+use std::env;
+#[cfg(any(feature = "pkcs12", feature = "pkcs11"))]
+use std::path::PathBuf;
+
+#[cfg(feature = "pkcs12")]
+use rustls_native_certs::{load_native_client_identities, load_native_client_identities_for, IdentitySource};
+
+#[cfg(feature = "pkcs12")]
+fn fixture() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("integration-tests/test-leaf.p12")
+}
+
+/// Returns the DER encoding of a distinguished name with just a common name.
+#[cfg(feature = "pkcs12")]
+fn common_name(cn: &str) -> Vec<u8> {
+    let atv = [&[0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, cn.len() as u8][..], cn.as_bytes()].concat();
+    let rdn = [&[0x30, atv.len() as u8][..], &atv].concat();
+    let set = [&[0x31, rdn.len() as u8][..], &rdn].concat();
+    [&[0x30, set.len() as u8][..], &set].concat()
+}
+
+#[cfg(feature = "pkcs12")]
+#[test]
+fn loads_configured_pkcs12_files() {
+    env::remove_var("RUSTLS_NATIVE_CERTS_CLIENT_PKCS12");
+    assert!(load_native_client_identities().unwrap().is_empty());
+
+    env::set_var("RUSTLS_NATIVE_CERTS_CLIENT_PKCS12", fixture());
+    env::set_var("RUSTLS_NATIVE_CERTS_CLIENT_PKCS12_PASSWORD", "test");
+    let identities = load_native_client_identities().unwrap();
+    assert_eq!(identities.len(), 1);
+
+    let identity = &identities[0];
+    assert_eq!(identity.label(), Some("test client"));
+    assert_eq!(identity.source(), &IdentitySource::Pkcs12File(fixture()));
+    assert!(identity.private_key_der().is_some());
+    // The fixture also carries the self-signed test CA, which is not sent.
+    assert_eq!(identity.chain().len(), 1);
+    assert_eq!(identity.certificate(), &identity.chain()[0][..]);
+
+    let test_ca = common_name("rustls-native-certs test CA");
+    let other_ca = common_name("some other CA");
+    assert!(identity.is_issued_by_any(&[]));
+    assert!(identity.is_issued_by_any(&[&other_ca, &test_ca]));
+    assert!(!identity.is_issued_by_any(&[&other_ca]));
+    assert_eq!(load_native_client_identities_for(&[&test_ca]).unwrap().len(), 1);
+    assert!(load_native_client_identities_for(&[&other_ca]).unwrap().is_empty());
+
+    #[cfg(feature = "rustls")]
+    {
+        use rustls::{ResolvesClientCert, SignatureScheme};
+        use rustls_native_certs::NativeClientCertResolver;
+
+        let key = identity.signing_key().unwrap();
+        let signer = key.choose_scheme(&[SignatureScheme::RSA_PSS_SHA256,
+                                         SignatureScheme::ECDSA_NISTP256_SHA256])
+            .unwrap();
+        assert_eq!(signer.get_scheme(), SignatureScheme::ECDSA_NISTP256_SHA256);
+        assert!(!signer.sign(b"hello").unwrap().is_empty());
+
+        let ecdsa = [SignatureScheme::ECDSA_NISTP256_SHA256];
+        let resolver = NativeClientCertResolver::from_identities(identities.clone());
+        assert!(resolver.has_certs());
+        let resolved = resolver.resolve(&[&test_ca], &ecdsa).unwrap();
+        assert_eq!(resolved.cert[0].0, identity.certificate());
+        assert!(resolver.resolve(&[], &ecdsa).is_some());
+        assert!(resolver.resolve(&[&other_ca], &ecdsa).is_none());
+        assert!(resolver.resolve(&[&test_ca], &[SignatureScheme::RSA_PSS_SHA256]).is_none());
+
+        let resolver = NativeClientCertResolver::from_identities(identities.clone()).label("test client");
+        assert_eq!(resolver.identities().count(), 1);
+        let resolver = NativeClientCertResolver::from_identities(identities.clone()).label("other");
+        assert!(!resolver.has_certs());
+        let resolver = NativeClientCertResolver::from_identities(identities.clone()).issued_by(&[&other_ca]);
+        assert!(resolver.resolve(&[], &ecdsa).is_none());
+    }
+
+    // An exported identity loads back the same.
+    let exported = env::temp_dir().join(format!("rustls-native-certs-export-{}.p12", std::process::id()));
+    std::fs::write(&exported, identity.to_pkcs12("exported").unwrap()).unwrap();
+    env::set_var("RUSTLS_NATIVE_CERTS_CLIENT_PKCS12", &exported);
+    env::set_var("RUSTLS_NATIVE_CERTS_CLIENT_PKCS12_PASSWORD", "exported");
+    let reloaded = load_native_client_identities().unwrap();
+    std::fs::remove_file(&exported).unwrap();
+    assert_eq!(reloaded.len(), 1);
+    assert_eq!(reloaded[0].chain(), identity.chain());
+    assert_eq!(reloaded[0].private_key_der(), identity.private_key_der());
+
+    env::set_var("RUSTLS_NATIVE_CERTS_CLIENT_PKCS12", fixture());
+    env::set_var("RUSTLS_NATIVE_CERTS_CLIENT_PKCS12_PASSWORD", "wrong");
+    assert!(load_native_client_identities().is_err());
+}
+
+#[cfg(feature = "pkcs11")]
+#[test]
+fn rejects_bad_pkcs11_uris() {
+    use rustls_native_certs::load_pkcs11_identities;
+    use std::io::ErrorKind;
+
+    let err = load_pkcs11_identities("https://example.com").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let err = load_pkcs11_identities("pkcs11:token=a;flavour=b").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let missing = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("no-such-module.so");
+    let uri = format!("pkcs11:token=a?module-path={}", missing.display());
+    assert!(load_pkcs11_identities(&uri).is_err());
+}
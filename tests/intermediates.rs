@@ -0,0 +1,30 @@
+// This test points the unix backend at a scratch directory via
+// `RUSTLS_NATIVE_CERTS_INTERMEDIATE_DIRS`.
+#![cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+
+use std::env;
+use std::fs;
+
+use rustls_native_certs::load_native_intermediates;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+
+#[test]
+fn loads_intermediates_from_configured_dirs() {
+    let dir = env::temp_dir().join(format!("rustls-native-certs-intermediates-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let der = rustls_pemfile::certs(&mut &TEST_INTERMEDIATE[..]).unwrap().remove(0);
+    fs::write(dir.join("test-intermediate.pem"), TEST_INTERMEDIATE).unwrap();
+    fs::write(dir.join("test-intermediate.der"), &der).unwrap();
+    fs::write(dir.join("test-ca.pem"), TEST_CA).unwrap();
+
+    env::set_var("RUSTLS_NATIVE_CERTS_INTERMEDIATE_DIRS", &dir);
+    let intermediates = load_native_intermediates().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    // The PEM and DER copies are the same certificate, and the root is
+    // self-issued, so only the intermediate appears, once.
+    assert_eq!(intermediates, vec![der]);
+}
@@ -0,0 +1,33 @@
+// This test points `SSL_CERT_FILE` at a scratch bundle, so that on Linux
+// and other Unix systems it is what the native store is read from.
+#![cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+
+mod common;
+
+use std::io::ErrorKind;
+
+use rustls_native_certs::native_cert_iter;
+
+use common::der;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+
+#[test]
+fn reads_bundle_lazily() {
+    let bundle = std::env::temp_dir().join(format!("rustls-native-certs-iter-{}.pem", std::process::id()));
+    let corrupt = b"-----BEGIN CERTIFICATE-----\nnot base64!\n-----END CERTIFICATE-----\n";
+    std::fs::write(&bundle, [TEST_CA, ONE_EXISTING_CA, corrupt].concat()).unwrap();
+    std::env::remove_var("RUSTLS_NATIVE_CERTS_OVERRIDE");
+    std::env::set_var("SSL_CERT_FILE", &bundle);
+
+    // The corrupt block is never reached.
+    let first: Vec<Vec<u8>> = native_cert_iter().take(2).map(Result::unwrap).collect();
+    assert_eq!(first, vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+
+    let all: Vec<_> = native_cert_iter().collect();
+    assert_eq!(all.len(), 3);
+    assert_eq!(all[2].as_ref().unwrap_err().kind(), ErrorKind::InvalidData);
+
+    std::fs::remove_file(&bundle).unwrap();
+}
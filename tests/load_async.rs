@@ -0,0 +1,18 @@
+// This test loads from a fixture named by `RUSTLS_NATIVE_CERTS_OVERRIDE`.
+#![cfg(all(feature = "rustls", feature = "tokio", feature = "test-util"))]
+
+#[test]
+fn loads_on_blocking_pool() {
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE",
+                      format!("{}/integration-tests/test-ca.pem", env!("CARGO_MANIFEST_DIR")));
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let roots = runtime.block_on(rustls_native_certs::load_native_certs_async())
+        .map_err(|(_, err)| err)
+        .unwrap();
+    assert_eq!(roots.len(), 1);
+
+    std::env::remove_var("RUSTLS_NATIVE_CERTS_OVERRIDE");
+}
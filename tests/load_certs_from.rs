@@ -0,0 +1,73 @@
+mod common;
+
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use rustls_native_certs::{load_certs_from, load_from, CertSource, Purpose};
+
+use common::der;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+const ANOTHER_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/another-existing-ca.pem");
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("integration-tests").join(name)
+}
+
+#[test]
+fn loads_files_and_directories() {
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-sources-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("subdirectory")).unwrap();
+    std::fs::write(dir.join("b.der"), der(ANOTHER_EXISTING_CA)).unwrap();
+    std::fs::write(dir.join("a.pem"), TEST_CA).unwrap();
+    std::fs::write(dir.join("subdirectory").join("c.pem"), ONE_EXISTING_CA).unwrap();
+
+    let sources = [
+        CertSource::PemFile(fixture("one-existing-ca.pem")),
+        CertSource::Directory(dir.clone()),
+        CertSource::PemFile(fixture("test-ca.pem")),
+    ];
+    let roots = load_certs_from(&sources).unwrap();
+
+    // In order, without the duplicate of the test CA, and without the
+    // subdirectory.
+    assert_eq!(roots, vec![der(ONE_EXISTING_CA), der(TEST_CA), der(ANOTHER_EXISTING_CA)]);
+
+    // Files and directories hold roots for every purpose.
+    let loaded = load_from(&sources[..], Purpose::EmailProtection).unwrap();
+    assert_eq!(loaded.roots(), roots.as_slice());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fails_for_missing_sources() {
+    let err = load_certs_from(&[
+        CertSource::PemFile(fixture("test-ca.pem")),
+        CertSource::Directory(fixture("no-such-directory")),
+    ]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn windows_stores_are_unsupported_elsewhere() {
+    let err = load_certs_from(&[CertSource::WindowsStore("ROOT".to_owned())]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
+
+#[cfg(not(all(target_os = "macos", not(feature = "mock-backend"))))]
+#[test]
+fn keychains_are_unsupported_elsewhere() {
+    let err = load_certs_from(&[CertSource::Keychain(fixture("test.keychain"))]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
+
+#[cfg(all(target_os = "macos", not(feature = "mock-backend")))]
+#[test]
+fn fails_for_missing_keychains() {
+    let err = load_certs_from(&[CertSource::Keychain(fixture("no-such.keychain"))]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
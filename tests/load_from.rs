@@ -0,0 +1,54 @@
+mod common;
+
+use std::io::{Error, ErrorKind};
+
+use rustls_native_certs::{load_from, CertificateSource, Purpose, RootStoreBuilder};
+
+use common::der;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+
+/// A source which reports duplicates, and trusts what it also distrusts.
+struct Untidy {
+    fail_crls: bool,
+}
+
+impl CertificateSource for Untidy {
+    fn build_certs(&self, purpose: Purpose, builder: &mut dyn RootStoreBuilder) -> Result<(), Error> {
+        if purpose != Purpose::ServerAuth {
+            return Ok(());
+        }
+        builder.load_pem_file(&mut &[TEST_CA, ONE_EXISTING_CA].concat()[..])?;
+        builder.load_der(der(TEST_CA))
+    }
+
+    fn intermediates(&self) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(vec![der(TEST_INTERMEDIATE), der(TEST_CA), der(TEST_INTERMEDIATE), vec![1, 2, 3]])
+    }
+
+    fn distrusted(&self) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(vec![der(ONE_EXISTING_CA), der(ONE_EXISTING_CA)])
+    }
+
+    fn crls(&self) -> Result<Vec<Vec<u8>>, Error> {
+        if self.fail_crls {
+            Err(Error::new(ErrorKind::PermissionDenied, "no CRLs for you"))
+        } else {
+            Ok(vec![vec![4, 5, 6], vec![4, 5, 6]])
+        }
+    }
+}
+
+#[test]
+fn processes_source_like_native_store() {
+    let loaded = load_from(Untidy { fail_crls: false }, Purpose::ServerAuth).unwrap();
+    assert_eq!(loaded.roots(), &[der(TEST_CA)][..]);
+    assert_eq!(loaded.intermediates(), &[der(TEST_INTERMEDIATE)][..]);
+    assert_eq!(loaded.distrusted(), &[der(ONE_EXISTING_CA)][..]);
+    assert_eq!(loaded.crls(), &[vec![4, 5, 6]][..]);
+
+    let source = Untidy { fail_crls: true };
+    assert_eq!(load_from(&source, Purpose::EmailProtection).unwrap_err().kind(), ErrorKind::PermissionDenied);
+}
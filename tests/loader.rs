@@ -0,0 +1,135 @@
+// This test loads from a scratch directory named by
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`.
+#![cfg(feature = "test-util")]
+
+mod common;
+
+use std::io::ErrorKind;
+use std::time::{Duration, UNIX_EPOCH};
+
+use rustls_native_certs::{inject_fault, CertSource, Error, Fault, NativeCertLoader, RootStoreBuilder, WindowsLocation};
+
+use common::{der, Collect};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+const ANOTHER_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/another-existing-ca.pem");
+// The test CA again, with the same name and key but another serial number.
+const TEST_CA_REISSUED: &[u8] = include_bytes!("../integration-tests/test-ca-reissued.pem");
+
+/// A builder which rejects the test CA, keeping every other root.
+#[derive(Default)]
+struct RejectTestCa(Collect);
+
+impl RootStoreBuilder for RejectTestCa {
+    fn load_der(&mut self, cert: Vec<u8>) -> Result<(), std::io::Error> {
+        match cert == der(TEST_CA) {
+            true => Err(std::io::Error::new(ErrorKind::InvalidData, "rejected")),
+            false => self.0.load_der(cert),
+        }
+    }
+
+    fn load_pem_file(&mut self, rd: &mut dyn std::io::BufRead) -> Result<(), std::io::Error> {
+        for der in rustls_pemfile::certs(rd)? {
+            self.load_der(der)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn loads_with_options() {
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-loader-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.pem"), TEST_CA).unwrap();
+    std::fs::write(dir.join("b.pem"), ONE_EXISTING_CA).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+
+    assert_eq!(NativeCertLoader::new().load().unwrap(), vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+    // Reading the fixture never prompts, so sandbox-safe loads read it too.
+    assert_eq!(NativeCertLoader::new().sandbox_safe(true).load().unwrap(),
+               vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+    // Nor is the System keychain read in place of it.
+    assert_eq!(NativeCertLoader::new().include_system_keychain(true).load().unwrap(),
+               vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+    // The fixture stands in for every Windows store location.
+    assert_eq!(NativeCertLoader::new().windows_location(WindowsLocation::LocalMachine).load().unwrap(),
+               vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+    // Nor is the AuthRoot list read beside it.
+    let partial = NativeCertLoader::new().windows_auth_root(true).load_partial();
+    assert_eq!(partial.certs, vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+    assert!(partial.is_complete());
+    let partial = NativeCertLoader::new()
+        .skip_inaccessible_domains(true)
+        .keychain_timeout(Duration::from_secs(10))
+        .load_partial();
+    assert_eq!(partial.certs, vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+    assert!(partial.is_complete());
+
+    // The GlobalSign root expires in 2029; the test CA in 2126.
+    let in_2030 = UNIX_EPOCH + Duration::from_secs(1_893_456_000);
+    let unexpired = NativeCertLoader::new().include_expired(false).now(in_2030).load().unwrap();
+    assert_eq!(unexpired, vec![der(TEST_CA)]);
+    let all = NativeCertLoader::new().now(in_2030).load().unwrap();
+    assert_eq!(all.len(), 2);
+
+    {
+        let _fault = inject_fault(Fault::CorruptPem);
+        assert_eq!(NativeCertLoader::new().load().unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(NativeCertLoader::new().tolerate_errors(true).load().unwrap().len(), 2);
+        let partial = NativeCertLoader::new().load_partial();
+        assert_eq!(partial.certs.len(), 2);
+        assert_eq!(partial.errors.len(), 1);
+        assert_eq!(partial.errors[0].kind(), ErrorKind::InvalidData);
+        assert_eq!(partial.errors[0].position(), 2);
+    }
+    {
+        let _fault = inject_fault(Fault::PermissionDenied);
+        let err = NativeCertLoader::new().tolerate_errors(true).load().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+
+    std::fs::write(dir.join("c.pem"), TEST_CA_REISSUED).unwrap();
+    assert_eq!(NativeCertLoader::new().load().unwrap().len(), 3);
+    assert_eq!(NativeCertLoader::new().dedup_by_spki(true).load().unwrap(),
+               vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+    assert_eq!(NativeCertLoader::new().require_roots(true).load().unwrap().len(), 3);
+    // A root the builder rejects leaves its key to the next with it.
+    let bundle = dir.join("bundle");
+    std::fs::create_dir_all(&bundle).unwrap();
+    std::fs::write(bundle.join("a.pem"), [TEST_CA, TEST_CA_REISSUED].concat()).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &bundle);
+    let mut rejecting = RejectTestCa::default();
+    assert!(NativeCertLoader::new().dedup_by_spki(true).build(&mut rejecting).is_err());
+    assert_eq!(rejecting.0.0, vec![der(TEST_CA_REISSUED)]);
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+
+    // Extra anchors follow the native roots, without those already loaded.
+    let extra = [
+        CertSource::Pem([ANOTHER_EXISTING_CA, TEST_CA].concat()),
+        CertSource::Der(der(ONE_EXISTING_CA)),
+        CertSource::Der(der(ANOTHER_EXISTING_CA)),
+    ];
+    assert_eq!(NativeCertLoader::new().extra_anchors(&extra).load().unwrap(),
+               vec![der(TEST_CA), der(ONE_EXISTING_CA), der(TEST_CA_REISSUED), der(ANOTHER_EXISTING_CA)]);
+    let err = NativeCertLoader::new()
+        .extra_anchors(&[CertSource::PemFile(dir.join("no-such-file.pem"))])
+        .load()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // A store with no roots fails only loads which require them, saying
+    // where they were looked for.
+    let empty = dir.join("empty");
+    std::fs::create_dir_all(&empty).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &empty);
+    assert_eq!(NativeCertLoader::new().load().unwrap(), Vec::<Vec<u8>>::new());
+    let err = NativeCertLoader::new().require_roots(true).load().unwrap_err();
+    assert_eq!(err.to_string(), format!("no root certificates found in override fixture {}", empty.display()));
+    assert!(matches!(Error::from(err), Error::NoCertsFound(_)));
+    let extra = [CertSource::Pem(TEST_CA.to_vec())];
+    assert_eq!(NativeCertLoader::new().require_roots(true).extra_anchors(&extra).load().unwrap(),
+               vec![der(TEST_CA)]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,51 @@
+// This test loads from a scratch directory named by
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`, and captures what the load logs.
+#![cfg(all(feature = "log", not(feature = "tracing"), feature = "test-util"))]
+
+use std::sync::Mutex;
+
+use rustls_native_certs::NativeCertLoader;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const TEST_CA_REISSUED: &[u8] = include_bytes!("../integration-tests/test-ca-reissued.pem");
+
+static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct Capture;
+
+impl log::Log for Capture {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        MESSAGES.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn logs_skipped_roots() {
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-log-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.pem"), TEST_CA).unwrap();
+    std::fs::write(dir.join("b.pem"), TEST_CA_REISSUED).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+
+    log::set_logger(&Capture).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let roots = NativeCertLoader::new().dedup_by_spki(true).load().unwrap();
+    assert_eq!(roots.len(), 1);
+
+    let messages = MESSAGES.lock().unwrap();
+    assert_eq!(*messages, vec![
+        format!("loading roots from the override fixture {}", dir.display()),
+        "skipped CN=rustls-native-certs test CA: its key is another root's".to_owned(),
+    ]);
+
+    std::env::remove_var("RUSTLS_NATIVE_CERTS_OVERRIDE");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,68 @@
+// This test points `RUSTLS_NATIVE_CERTS_OVERRIDE` at a scratch bundle.
+#![cfg(all(feature = "metadata", feature = "test-util"))]
+
+mod common;
+
+use rustls_native_certs::{load_native_certs_with_metadata, NativeCert, PlatformTrust, Provenance};
+
+use common::der;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn loads_with_metadata() {
+    let bundle = std::env::temp_dir().join(format!("rustls-native-certs-metadata-{}.pem", std::process::id()));
+    std::fs::write(&bundle, [TEST_CA, ONE_EXISTING_CA].concat()).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &bundle);
+
+    let certs = load_native_certs_with_metadata().unwrap();
+    assert_eq!(certs.len(), 2);
+    assert_eq!(certs[0].der(), &der(TEST_CA)[..]);
+    assert_eq!(certs[1].der(), &der(ONE_EXISTING_CA)[..]);
+    for cert in &certs {
+        assert_eq!(cert.provenance(), &Provenance::File(bundle.clone()));
+        assert_eq!(cert.trust(), PlatformTrust::InStore);
+        assert_eq!(cert.trust_domain(), None);
+        assert!(!cert.is_managed());
+        assert!(cert.allowed_errors().is_empty());
+    }
+    assert_eq!(hex(&certs[1].fingerprint_sha256()),
+               "cbb522d7b7f127ad6a0113865bdf1cd4102e7d0759af635a7cf4720dc963c53b");
+    assert_eq!(hex(&certs[1].spki_sha256().unwrap()),
+               "706bb1017c855c59169bad5c1781cf597f12d2cad2f63d1a4aa37493800ffb80");
+
+    std::fs::remove_file(&bundle).unwrap();
+}
+
+#[test]
+fn converts_pem() {
+    let cert = NativeCert::from_pem(ONE_EXISTING_CA).unwrap();
+    assert_eq!(cert.der(), &der(ONE_EXISTING_CA)[..]);
+    assert_eq!(cert.provenance(), &Provenance::Unknown);
+
+    let pem = cert.to_pem();
+    assert!(pem.starts_with("-----BEGIN CERTIFICATE-----\n"));
+    assert!(pem.lines().all(|line| line.len() <= 64));
+    assert_eq!(NativeCert::from_pem(pem.as_bytes()).unwrap(), cert);
+
+    let err = NativeCert::from_pem(b"no certificates here\n").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[cfg(feature = "x509")]
+#[test]
+fn displays_fields() {
+    let cert = NativeCert::from_pem(ONE_EXISTING_CA).unwrap();
+    let name = "OU=GlobalSign Root CA - R3, O=GlobalSign, CN=GlobalSign";
+    assert_eq!(cert.to_string(),
+               format!("{} (issued by {}, expires 2029-03-18T10:00:00Z)", name, name));
+
+    let debug = format!("{:?}", cert);
+    assert!(debug.starts_with(&format!("NativeCert {{ subject: {:?}, issuer: {:?}, ", name, name)), "{}", debug);
+    assert!(debug.contains("sha256: \"cbb522d7b7f127ad6a0113865bdf1cd4102e7d0759af635a7cf4720dc963c53b\""), "{}", debug);
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "test-util")]
+
+mod common;
+
+use rustls_native_certs::{CertificateSource, MockCertificateSource, Purpose, TrustStatus};
+
+use common::{Collect, der};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+
+fn roots(source: &dyn CertificateSource, purpose: Purpose) -> Vec<Vec<u8>> {
+    let mut roots = Collect::default();
+    source.build_certs(purpose, &mut roots).unwrap();
+    roots.0
+}
+
+#[test]
+fn reports_configured_trust() {
+    let source = MockCertificateSource::new()
+        .with_root_pem(TEST_CA)
+        .with_pem(ONE_EXISTING_CA, TrustStatus::Trusted(vec![Purpose::EmailProtection]))
+        .with_pem(TEST_INTERMEDIATE, TrustStatus::Intermediate)
+        .with_crl(vec![1, 2, 3]);
+
+    assert_eq!(roots(&source, Purpose::ServerAuth), vec![der(TEST_CA)]);
+    assert_eq!(roots(&source, Purpose::EmailProtection), vec![der(ONE_EXISTING_CA)]);
+    assert!(roots(&source, Purpose::CodeSigning).is_empty());
+    assert_eq!(source.intermediates().unwrap(), vec![der(TEST_INTERMEDIATE)]);
+    assert!(source.distrusted().unwrap().is_empty());
+    assert_eq!(source.crls().unwrap(), vec![vec![1, 2, 3]]);
+
+    // Distrust overrides trust.
+    let source = source.with_der(der(TEST_CA), TrustStatus::Distrusted);
+    assert!(roots(&source, Purpose::ServerAuth).is_empty());
+    assert_eq!(source.distrusted().unwrap(), vec![der(TEST_CA)]);
+}
+
+#[test]
+fn fails_with_injected_faults() {
+    use rustls_native_certs::{build_native_certs, inject_fault, load_native_crls, Fault};
+    use std::io::ErrorKind;
+
+    let source = MockCertificateSource::new().with_root_pem(TEST_CA);
+    let mut roots = Collect::default();
+    let err = source.clone().with_fault(Fault::CorruptPem).build_certs(Purpose::ServerAuth, &mut roots).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    // The good roots still load.
+    assert_eq!(roots.0, vec![der(TEST_CA)]);
+    let err = source.with_fault(Fault::Timeout).intermediates().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+    {
+        let _fault = inject_fault(Fault::PermissionDenied);
+        let err = build_native_certs(&mut Collect::default()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        let _nested = inject_fault(Fault::Timeout);
+        assert_eq!(load_native_crls().unwrap_err().kind(), ErrorKind::TimedOut);
+    }
+    assert!(load_native_crls().is_ok());
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "mock-backend")]
+
+mod common;
+
+use rustls_native_certs::{
+    build_native_certs,
+    install_mock_backend,
+    load_native_client_identities,
+    load_native_intermediates,
+    MockCertificateSource,
+    TrustStatus,
+};
+
+use common::Collect;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+
+#[test]
+fn loads_installed_source() {
+    let mut roots = Collect::default();
+    build_native_certs(&mut roots).unwrap();
+    assert!(roots.0.is_empty());
+
+    install_mock_backend(MockCertificateSource::new()
+        .with_root_pem(TEST_CA)
+        .with_pem(TEST_INTERMEDIATE, TrustStatus::Intermediate));
+    let mut roots = Collect::default();
+    build_native_certs(&mut roots).unwrap();
+    assert_eq!(roots.0.len(), 1);
+    assert_eq!(load_native_intermediates().unwrap().len(), 1);
+    assert!(load_native_client_identities().unwrap().is_empty());
+}
@@ -0,0 +1,55 @@
+// This test replaces the native store with a scratch directory via
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`.
+#![cfg(feature = "test-util")]
+
+mod common;
+
+use std::env;
+use std::fs;
+
+use rustls_native_certs::{
+    build_native_certs,
+    build_native_certs_for,
+    load_native_crls,
+    load_native_distrusted,
+    load_native_intermediates,
+    Purpose,
+};
+
+use common::{Collect, der};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+
+#[test]
+fn loads_from_override_fixture() {
+    let dir = env::temp_dir().join(format!("rustls-native-certs-override-{}", std::process::id()));
+    fs::create_dir_all(dir.join("intermediates")).unwrap();
+    fs::write(dir.join("a-test-ca.pem"), TEST_CA).unwrap();
+    fs::write(dir.join("b-existing-ca.der"), der(ONE_EXISTING_CA)).unwrap();
+    fs::write(dir.join("intermediates/test-intermediate.pem"), TEST_INTERMEDIATE).unwrap();
+
+    env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+
+    let mut roots = Collect::default();
+    build_native_certs(&mut roots).unwrap();
+    assert_eq!(roots.0, vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+    let mut roots = Collect::default();
+    build_native_certs_for(Purpose::EmailProtection, &mut roots).unwrap();
+    assert_eq!(roots.0.len(), 2);
+
+    assert_eq!(load_native_intermediates().unwrap(), vec![der(TEST_INTERMEDIATE)]);
+    assert!(load_native_distrusted().unwrap().is_empty());
+    assert!(load_native_crls().unwrap().is_empty());
+
+    // A single file supplies just roots.
+    env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", dir.join("a-test-ca.pem"));
+    let mut roots = Collect::default();
+    build_native_certs(&mut roots).unwrap();
+    assert_eq!(roots.0, vec![der(TEST_CA)]);
+    assert!(load_native_intermediates().unwrap().is_empty());
+
+    env::remove_var("RUSTLS_NATIVE_CERTS_OVERRIDE");
+    fs::remove_dir_all(&dir).unwrap();
+}
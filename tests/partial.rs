@@ -0,0 +1,33 @@
+// This test points `SSL_CERT_FILE` at a scratch bundle, so that on Linux
+// and other Unix systems it is what the native store is read from.
+#![cfg(all(unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+
+mod common;
+
+use std::io::ErrorKind;
+
+use rustls_native_certs::load_native_certs_partial;
+
+use common::der;
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+
+#[test]
+fn skips_malformed_blocks() {
+    let bundle = std::env::temp_dir().join(format!("rustls-native-certs-partial-{}.pem", std::process::id()));
+    let corrupt = b"-----BEGIN CERTIFICATE-----\nnot base64!\n-----END CERTIFICATE-----\n";
+    std::fs::write(&bundle, [TEST_CA, corrupt, ONE_EXISTING_CA].concat()).unwrap();
+    std::env::remove_var("RUSTLS_NATIVE_CERTS_OVERRIDE");
+    std::env::set_var("SSL_CERT_FILE", &bundle);
+
+    let result = load_native_certs_partial();
+    assert_eq!(result.certs, vec![der(TEST_CA), der(ONE_EXISTING_CA)]);
+    assert!(!result.is_complete());
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].position(), 1);
+    assert_eq!(result.errors[0].kind(), ErrorKind::InvalidData);
+    assert!(result.errors[0].to_string().contains(bundle.to_str().unwrap()));
+
+    std::fs::remove_file(&bundle).unwrap();
+}
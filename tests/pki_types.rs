@@ -0,0 +1,57 @@
+// These tests load from a scratch directory named by
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`, holding the test CA and a root no parser
+// accepts.
+#![cfg(all(feature = "pki-types", feature = "test-util"))]
+
+use std::path::PathBuf;
+use std::sync::Once;
+
+use rustls_native_certs::{load_native_certs_der, NativeCertLoader};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const MALFORMED: &[u8] = &[0x30, 0x03, 0x02, 0x01, 0x00];
+
+fn test_ca() -> Vec<u8> {
+    rustls_pemfile::certs(&mut &TEST_CA[..]).unwrap().remove(0)
+}
+
+fn use_fixture() {
+    static FIXTURE: Once = Once::new();
+    FIXTURE.call_once(|| {
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("pki-types-fixture");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.pem"), TEST_CA).unwrap();
+        std::fs::write(dir.join("b.der"), MALFORMED).unwrap();
+        std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+    });
+}
+
+#[test]
+fn loads_certificate_ders() {
+    use_fixture();
+
+    let roots = load_native_certs_der().unwrap();
+    assert_eq!(roots.iter().map(|der| der.to_vec()).collect::<Vec<_>>(), vec![test_ca(), MALFORMED.to_vec()]);
+
+    let mut built = Vec::new();
+    NativeCertLoader::new().build(&mut built).unwrap();
+    assert_eq!(built, roots);
+}
+
+#[cfg(feature = "webpki")]
+#[test]
+fn loads_trust_anchors() {
+    use_fixture();
+
+    let loaded = rustls_native_certs::load_native_trust_anchors().unwrap();
+    assert_eq!(loaded.errors().len(), 1);
+    assert_eq!(loaded.errors()[0].kind(), std::io::ErrorKind::InvalidData);
+
+    let der = test_ca();
+    let expected = webpki::trust_anchor_util::cert_der_as_trust_anchor(&der).unwrap();
+    let anchors = loaded.into_anchors();
+    assert_eq!(anchors.len(), 1);
+    assert_eq!(&anchors[0].subject[..], expected.subject);
+    assert_eq!(&anchors[0].subject_public_key_info[..], expected.spki);
+    assert_eq!(anchors[0].name_constraints.as_deref(), expected.name_constraints);
+}
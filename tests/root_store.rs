@@ -0,0 +1,24 @@
+// This test loads from a scratch directory named by
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`.
+#![cfg(all(feature = "rustls", feature = "test-util"))]
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+
+#[test]
+fn counts_rejected_roots() {
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-root-store-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.pem"), [TEST_CA, ONE_EXISTING_CA].concat()).unwrap();
+    std::fs::write(dir.join("b.der"), [0x30, 0x03, 0x02, 0x01, 0x00]).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+
+    let loaded = rustls_native_certs::load_native_root_store().unwrap();
+    assert_eq!(loaded.roots().len(), 2);
+    assert_eq!(loaded.rejected(), 1);
+
+    // The same store fails the stricter load.
+    assert!(rustls_native_certs::load_native_certs().is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
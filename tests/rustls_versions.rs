@@ -0,0 +1,50 @@
+// These tests load from a scratch directory named by
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`, holding the test CA.
+#![cfg(all(any(feature = "rustls-021", feature = "rustls-022"), feature = "test-util"))]
+
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Once;
+
+use rustls_native_certs::{build_native_certs, RootStoreBuilder};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const MALFORMED: &[u8] = &[0x30, 0x03, 0x02, 0x01, 0x00];
+
+fn use_fixture() {
+    static FIXTURE: Once = Once::new();
+    FIXTURE.call_once(|| {
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("rustls-versions-fixture");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.pem"), TEST_CA).unwrap();
+        std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+    });
+}
+
+#[cfg(feature = "rustls-021")]
+#[test]
+fn loads_rustls_021_root_store() {
+    use_fixture();
+
+    let mut roots = rustls021::RootCertStore::empty();
+    build_native_certs(&mut roots).unwrap();
+    assert_eq!(roots.len(), 1);
+
+    let err = roots.load_der(MALFORMED.to_vec()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(roots.len(), 1);
+}
+
+#[cfg(feature = "rustls-022")]
+#[test]
+fn loads_rustls_022_root_store() {
+    use_fixture();
+
+    let mut roots = rustls022::RootCertStore::empty();
+    build_native_certs(&mut roots).unwrap();
+    assert_eq!(roots.len(), 1);
+
+    let err = roots.load_der(MALFORMED.to_vec()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(roots.len(), 1);
+}
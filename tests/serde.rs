@@ -0,0 +1,59 @@
+#![cfg(feature = "serde")]
+
+use serde_core::de::value::{Error, MapDeserializer, StrDeserializer};
+use serde_core::Deserialize;
+
+use rustls_native_certs::{PlatformTrust, Provenance, Purpose, TrustDomain};
+
+fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T, Error> {
+    T::deserialize(StrDeserializer::<Error>::new(s))
+}
+
+#[test]
+fn reads_names() {
+    assert_eq!(from_str::<Purpose>("email-protection").unwrap(), Purpose::EmailProtection);
+    assert_eq!(from_str::<TrustDomain>("admin").unwrap(), TrustDomain::Admin);
+    assert_eq!(from_str::<PlatformTrust>("trust-as-root").unwrap(), PlatformTrust::TrustAsRoot);
+    assert_eq!(from_str::<PlatformTrust>("deny").unwrap(), PlatformTrust::Deny);
+    assert!(from_str::<Purpose>("ServerAuth").is_err());
+
+    assert_eq!(from_str::<Provenance>("file:/etc/ssl/cert.pem").unwrap(),
+               Provenance::File("/etc/ssl/cert.pem".into()));
+    assert_eq!(from_str::<Provenance>("trust-settings:system").unwrap(),
+               Provenance::TrustSettings(TrustDomain::System));
+    assert_eq!(from_str::<Provenance>("windows-store:ROOT").unwrap(),
+               Provenance::WindowsStore("ROOT".to_owned()));
+    assert_eq!(from_str::<Provenance>("unknown").unwrap(), Provenance::Unknown);
+    assert!(from_str::<Provenance>("trust-settings:everyone").is_err());
+    assert!(from_str::<Provenance>("keychain").is_err());
+}
+
+#[cfg(feature = "metadata")]
+#[test]
+fn reads_certificates() {
+    use base64::Engine;
+    use rustls_native_certs::NativeCert;
+
+    const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+    let expected = NativeCert::from_pem(ONE_EXISTING_CA).unwrap();
+    let der = base64::engine::general_purpose::STANDARD.encode(expected.der());
+
+    let fields = |fields: Vec<(&'static str, &str)>| {
+        let fields: Vec<(&str, String)> = fields.into_iter().map(|(k, v)| (k, v.to_owned())).collect();
+        NativeCert::deserialize(MapDeserializer::<_, Error>::new(fields.into_iter()))
+    };
+
+    let cert = fields(vec![("der", &der), ("provenance", "trust-settings:user"), ("trust", "trust-root")]).unwrap();
+    assert_eq!(cert.der(), expected.der());
+    assert_eq!(cert.provenance(), &Provenance::TrustSettings(TrustDomain::User));
+    assert_eq!(cert.trust_domain(), Some(TrustDomain::User));
+    assert_eq!(cert.trust(), PlatformTrust::TrustRoot);
+    assert!(cert.host_restrictions().is_empty());
+    assert!(!cert.is_managed());
+    assert!(cert.allowed_errors().is_empty());
+    // The fingerprint is worked out again, not read.
+    assert_eq!(cert.fingerprint_sha256(), expected.fingerprint_sha256());
+
+    assert!(fields(vec![("der", &der), ("trust", "trust-root")]).is_err());
+    assert!(fields(vec![("der", "not base64!"), ("provenance", "unknown"), ("trust", "in-store")]).is_err());
+}
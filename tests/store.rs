@@ -0,0 +1,77 @@
+#![cfg(feature = "metadata")]
+
+use rustls_native_certs::{CertStore, NativeCert};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const TEST_CA_REISSUED: &[u8] = include_bytes!("../integration-tests/test-ca-reissued.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+
+fn store() -> CertStore {
+    [TEST_CA, ONE_EXISTING_CA, TEST_CA_REISSUED]
+        .iter()
+        .map(|pem| NativeCert::from_pem(pem).unwrap())
+        .collect()
+}
+
+fn fingerprints<'a>(certs: impl Iterator<Item = &'a NativeCert>) -> Vec<[u8; 32]> {
+    certs.map(NativeCert::fingerprint_sha256).collect()
+}
+
+#[test]
+fn finds_by_subject() {
+    let store = store();
+    let expected = fingerprints(store.certs().iter().step_by(2));
+    assert_eq!(fingerprints(store.find_by_subject("CN=rustls-native-certs test CA")), expected);
+    assert_eq!(store.find_by_subject("CN=rustls-native-certs").count(), 0);
+}
+
+#[test]
+fn finds_issuers() {
+    let store = store();
+    let leaf = NativeCert::from_pem(TEST_INTERMEDIATE).unwrap();
+    let expected = fingerprints(store.certs().iter().step_by(2));
+    assert_eq!(fingerprints(store.find_issuer_of(leaf.der())), expected);
+    assert_eq!(store.find_issuer_of(&[0x30, 0x00]).count(), 0);
+}
+
+#[test]
+fn checks_fingerprints() {
+    let store = store();
+    let test_ca = NativeCert::from_pem(TEST_CA).unwrap();
+    let intermediate = NativeCert::from_pem(TEST_INTERMEDIATE).unwrap();
+    assert!(store.contains_fingerprint(&test_ca.fingerprint_sha256()));
+    assert!(!store.contains_fingerprint(&intermediate.fingerprint_sha256()));
+    assert_eq!(store.len(), 3);
+}
+
+#[test]
+fn checks_spki_pins() {
+    let store: CertStore = [TEST_CA_REISSUED, ONE_EXISTING_CA]
+        .iter()
+        .map(|pem| NativeCert::from_pem(pem).unwrap())
+        .collect();
+    // The test CA has the key of its reissue, which is in the store.
+    let test_ca = NativeCert::from_pem(TEST_CA).unwrap();
+    let intermediate = NativeCert::from_pem(TEST_INTERMEDIATE).unwrap();
+    assert!(!store.contains_fingerprint(&test_ca.fingerprint_sha256()));
+    assert!(store.contains_spki_pin(&test_ca.spki_sha256().unwrap()));
+    assert!(!store.contains_spki_pin(&intermediate.spki_sha256().unwrap()));
+}
+
+#[test]
+fn diffs_stores() {
+    let older = store();
+    let newer: CertStore = [TEST_CA, TEST_INTERMEDIATE]
+        .iter()
+        .map(|pem| NativeCert::from_pem(pem).unwrap())
+        .collect();
+
+    let diff = newer.diff(&older);
+    let fingerprint = |pem| NativeCert::from_pem(pem).unwrap().fingerprint_sha256();
+    assert_eq!(diff.added, vec![fingerprint(TEST_INTERMEDIATE)]);
+    let mut removed = vec![fingerprint(ONE_EXISTING_CA), fingerprint(TEST_CA_REISSUED)];
+    removed.sort();
+    assert_eq!(diff.removed, removed);
+    assert!(newer.diff(&newer).is_empty());
+}
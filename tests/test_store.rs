@@ -0,0 +1,54 @@
+// These tests install a `TestStore`, which the whole process shares, so
+// only one test installs one, checking each load in turn.
+#![cfg(all(feature = "test-util", not(feature = "mock-backend"), any(windows, target_os = "macos")))]
+
+mod common;
+
+use rustls_native_certs::{
+    build_native_certs,
+    load_native_client_identities,
+    load_native_distrusted,
+    load_native_intermediates,
+    TestStore,
+};
+
+use common::{Collect, der};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const ONE_EXISTING_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+const TEST_INTERMEDIATE: &[u8] = include_bytes!("../integration-tests/test-intermediate.pem");
+const TEST_LEAF: &[u8] = include_bytes!("../integration-tests/test-leaf.pem");
+const TEST_LEAF_P12: &[u8] = include_bytes!("../integration-tests/test-leaf.p12");
+
+#[test]
+fn loads_from_test_store() {
+    let mut store = TestStore::new().unwrap();
+    store.add_root(&der(TEST_CA)).unwrap();
+    store.add_intermediate(&der(TEST_INTERMEDIATE)).unwrap();
+    store.add_distrusted(&der(ONE_EXISTING_CA)).unwrap();
+    // On Windows a distrusted certificate is its own store's, so put it in
+    // the Root store too, to see that the Disallowed store wins.
+    #[cfg(windows)]
+    store.add_root(&der(ONE_EXISTING_CA)).unwrap();
+    store.add_identity_pkcs12(TEST_LEAF_P12, "test").unwrap();
+    let _installed = store.install();
+
+    let mut roots = Collect::default();
+    build_native_certs(&mut roots).unwrap();
+    assert_eq!(roots.0, vec![der(TEST_CA)]);
+    assert!(load_native_intermediates().unwrap().contains(&der(TEST_INTERMEDIATE)));
+    assert_eq!(load_native_distrusted().unwrap(), vec![der(ONE_EXISTING_CA)]);
+
+    // The denial is reported, not only left out.
+    #[cfg(feature = "metadata")]
+    {
+        let store = rustls_native_certs::CertStore::load().unwrap();
+        let denied: Vec<&[u8]> = store.denied().iter().map(|cert| cert.der()).collect();
+        assert_eq!(denied, vec![&der(ONE_EXISTING_CA)[..]]);
+        assert_eq!(store.denied()[0].trust(), rustls_native_certs::PlatformTrust::Deny);
+    }
+
+    let identities = load_native_client_identities().unwrap();
+    assert_eq!(identities.len(), 1);
+    assert_eq!(identities[0].certificate(), &der(TEST_LEAF)[..]);
+}
@@ -0,0 +1,110 @@
+// This test loads from a scratch directory named by
+// `RUSTLS_NATIVE_CERTS_OVERRIDE`, and records each event the load reports
+// with the spans it falls within.
+#![cfg(all(feature = "tracing", feature = "test-util"))]
+
+use std::fmt::{Debug, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use rustls_native_certs::NativeCertLoader;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+const TEST_CA_REISSUED: &[u8] = include_bytes!("../integration-tests/test-ca-reissued.pem");
+
+/// Formats the fields it visits as `name=value`, the message as itself.
+#[derive(Default)]
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        match field.name() {
+            "message" => write!(self.0, "{:?}", value),
+            name => write!(self.0, "{}={:?}", name, value),
+        }.unwrap();
+    }
+}
+
+/// A subscriber which keeps each event, after the spans entered when it
+/// was reported, as in `source{fixture="/tmp/x"}: loading roots`.
+#[derive(Default)]
+struct Capture {
+    next_id: AtomicU64,
+    spans: Mutex<Vec<(Id, String)>>,
+    entered: Mutex<Vec<Id>>,
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl Subscriber for Capture {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        let name = format!("{}{{{}}}", span.metadata().name(), fields.0);
+        self.spans.lock().unwrap().push((id.clone(), name));
+        id
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let spans = self.spans.lock().unwrap();
+        let mut line = String::new();
+        for entered in self.entered.lock().unwrap().iter() {
+            let (_, name) = spans.iter().find(|(id, _)| id == entered).unwrap();
+            write!(line, "{}: ", name).unwrap();
+        }
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        line.push_str(&fields.0);
+        self.events.lock().unwrap().push(line);
+    }
+
+    fn enter(&self, span: &Id) {
+        self.entered.lock().unwrap().push(span.clone());
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut entered = self.entered.lock().unwrap();
+        let last = entered.iter().rposition(|id| id == span).unwrap();
+        entered.remove(last);
+    }
+}
+
+#[test]
+fn reports_skipped_roots_within_their_source() {
+    let dir = std::env::temp_dir().join(format!("rustls-native-certs-tracing-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.pem"), TEST_CA).unwrap();
+    std::fs::write(dir.join("b.pem"), TEST_CA_REISSUED).unwrap();
+    std::env::set_var("RUSTLS_NATIVE_CERTS_OVERRIDE", &dir);
+
+    let capture = Capture::default();
+    let events = capture.events.clone();
+    let roots = tracing::subscriber::with_default(capture, || {
+        NativeCertLoader::new().dedup_by_spki(true).load().unwrap()
+    });
+    assert_eq!(roots.len(), 1);
+
+    let source = format!("source{{fixture={:?}}}", dir);
+    assert_eq!(*events.lock().unwrap(), vec![
+        format!("{}: loading roots from the override fixture {}", source, dir.display()),
+        format!("{}: skipped CN=rustls-native-certs test CA: its key is another root's", source),
+    ]);
+
+    std::env::remove_var("RUSTLS_NATIVE_CERTS_OVERRIDE");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,302 @@
+// These tests point the unix backend at a scratch CA bundle via
+// `SSL_CERT_FILE`, and check that modifying it is reported.  They
+// share that bundle, so each holds a lock while it runs.
+#![cfg(all(feature = "watch", unix, not(target_vendor = "apple"), not(feature = "mock-backend")))]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{mpsc, Mutex, MutexGuard, Once};
+use std::time::Duration;
+
+use rustls_native_certs::{CertWatcher, ChangeEvent};
+
+const ONE_CA: &[u8] = include_bytes!("../integration-tests/one-existing-ca.pem");
+const ANOTHER_CA: &[u8] = include_bytes!("../integration-tests/another-existing-ca.pem");
+
+fn fingerprint(pem: &[u8]) -> [u8; 32] {
+    let der = rustls_pemfile::certs(&mut &pem[..]).unwrap().remove(0);
+    let mut fp = [0u8; 32];
+    fp.copy_from_slice(ring::digest::digest(&ring::digest::SHA256, &der).as_ref());
+    fp
+}
+
+fn add_another_ca(bundle: &PathBuf) {
+    fs::write(bundle, [ONE_CA, ANOTHER_CA].concat()).unwrap();
+}
+
+fn scratch_bundle() -> (MutexGuard<'static, ()>, PathBuf) {
+    static INIT: Once = Once::new();
+    static LOCK: Mutex<()> = Mutex::new(());
+    let guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let dir = std::env::temp_dir()
+        .join(format!("rustls-native-certs-watch-{}", std::process::id()));
+    let bundle = dir.join("ca-bundle.pem");
+
+    INIT.call_once(|| {
+        fs::create_dir_all(&dir).unwrap();
+        std::env::set_var("SSL_CERT_FILE", &bundle);
+    });
+    fs::write(&bundle, ONE_CA).unwrap();
+
+    (guard, bundle)
+}
+
+fn touch(bundle: &PathBuf) {
+    let contents = fs::read(bundle).unwrap();
+    fs::write(bundle, contents).unwrap();
+}
+
+#[test]
+fn reports_changes_to_bundle() {
+    let (_guard, bundle) = scratch_bundle();
+    let watcher = CertWatcher::new().unwrap();
+    let events = watcher.subscribe();
+
+    add_another_ca(&bundle);
+
+    match events.recv_timeout(Duration::from_secs(10)) {
+        Ok(ChangeEvent::Changed(diff)) => {
+            assert_eq!(diff.added, vec![fingerprint(ANOTHER_CA)]);
+            assert!(diff.removed.is_empty());
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+
+    fs::write(&bundle, ANOTHER_CA).unwrap();
+
+    match events.recv_timeout(Duration::from_secs(10)) {
+        Ok(ChangeEvent::Changed(diff)) => {
+            assert!(diff.added.is_empty());
+            assert_eq!(diff.removed, vec![fingerprint(ONE_CA)]);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+}
+
+#[test]
+fn ignores_rewrites_with_same_contents() {
+    let (_guard, bundle) = scratch_bundle();
+    let watcher = CertWatcher::new().unwrap();
+    let events = watcher.subscribe();
+
+    touch(&bundle);
+
+    assert!(events.recv_timeout(Duration::from_secs(2)).is_err());
+}
+
+#[test]
+fn warns_about_empty_or_truncated_store() {
+    let (_guard, bundle) = scratch_bundle();
+    let watcher = CertWatcher::new().unwrap();
+    let events = watcher.subscribe();
+
+    fs::write(&bundle, b"").unwrap();
+    assert!(matches!(events.recv_timeout(Duration::from_secs(10)),
+                     Ok(ChangeEvent::Warning(_))));
+
+    fs::write(&bundle, [ONE_CA, &ANOTHER_CA[..100]].concat()).unwrap();
+    assert!(matches!(events.recv_timeout(Duration::from_secs(10)),
+                     Ok(ChangeEvent::Warning(_))));
+
+    // the next change is relative to the last good load
+    add_another_ca(&bundle);
+    match events.recv_timeout(Duration::from_secs(10)) {
+        Ok(ChangeEvent::Changed(diff)) => {
+            assert_eq!(diff.added, vec![fingerprint(ANOTHER_CA)]);
+            assert!(diff.removed.is_empty());
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+}
+
+#[test]
+fn calls_back_on_change() {
+    let (_guard, bundle) = scratch_bundle();
+    let watcher = CertWatcher::new().unwrap();
+    let (tx, rx) = mpsc::channel();
+    watcher.on_change(move |event| tx.send(event.clone()).unwrap());
+
+    add_another_ca(&bundle);
+
+    assert!(matches!(rx.recv_timeout(Duration::from_secs(10)),
+                     Ok(ChangeEvent::Changed(_))));
+}
+
+#[test]
+fn coalesces_bursts_of_changes() {
+    let (_guard, bundle) = scratch_bundle();
+    let watcher = CertWatcher::builder()
+        .debounce(Duration::from_millis(300))
+        .build()
+        .unwrap();
+    let events = watcher.subscribe();
+
+    for _ in 0..10 {
+        fs::write(&bundle, ANOTHER_CA).unwrap();
+        add_another_ca(&bundle);
+    }
+
+    assert!(matches!(events.recv_timeout(Duration::from_secs(10)),
+                     Ok(ChangeEvent::Changed(_))));
+    assert!(events.recv_timeout(Duration::from_secs(1)).is_err());
+}
+
+#[test]
+fn polls_for_changes_when_forced() {
+    let (_guard, bundle) = scratch_bundle();
+    let watcher = CertWatcher::builder()
+        .force_polling(true)
+        .poll_interval(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let events = watcher.subscribe();
+
+    touch(&bundle);
+    assert!(events.recv_timeout(Duration::from_secs(1)).is_err());
+
+    add_another_ca(&bundle);
+    assert!(matches!(events.recv_timeout(Duration::from_secs(10)),
+                     Ok(ChangeEvent::Changed(_))));
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn reports_changes_as_stream() {
+    use futures_core::Stream;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    let (_guard, bundle) = scratch_bundle();
+    let watcher = CertWatcher::new().unwrap();
+    let mut stream = watcher.stream();
+
+    add_another_ca(&bundle);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let next = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx));
+    let event = runtime.block_on(async {
+        tokio::time::timeout(Duration::from_secs(10), next).await
+    })
+        .unwrap();
+    assert!(matches!(event, Some(ChangeEvent::Changed(_))));
+}
+
+#[cfg(feature = "rustls")]
+#[test]
+fn reloads_roots_on_change() {
+    let (_guard, bundle) = scratch_bundle();
+    let roots = rustls_native_certs::ReloadableRoots::new().unwrap();
+    assert_eq!(roots.current().len(), 1);
+
+    fs::write(&bundle, b"").unwrap();
+    std::thread::sleep(Duration::from_secs(2));
+    assert_eq!(roots.current().len(), 1);
+
+    add_another_ca(&bundle);
+
+    for _ in 0..100 {
+        if roots.current().len() == 2 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(roots.client_config().root_store.len(), 2);
+}
+
+#[cfg(feature = "rustls")]
+#[test]
+fn reloads_roots_after_a_failed_load() {
+    let (_guard, bundle) = scratch_bundle();
+    let roots = rustls_native_certs::ReloadableRoots::new().unwrap();
+
+    // A bundle cut off partway through a certificate fails to load.
+    fs::write(&bundle, [ONE_CA, &ANOTHER_CA[..100]].concat()).unwrap();
+    std::thread::sleep(Duration::from_secs(2));
+    assert_eq!(roots.current().len(), 1);
+
+    add_another_ca(&bundle);
+
+    for _ in 0..100 {
+        if roots.current().len() == 2 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(roots.current().len(), 2);
+}
+
+#[cfg(feature = "rustls")]
+#[test]
+fn verifier_honours_newly_installed_roots() {
+    use rustls::ServerCertVerifier;
+
+    const TEST_CA: &[u8] = include_bytes!("../integration-tests/test-ca.pem");
+    const TEST_LEAF: &[u8] = include_bytes!("../integration-tests/test-leaf.pem");
+
+    let (_guard, bundle) = scratch_bundle();
+    let roots = rustls_native_certs::ReloadableRoots::new().unwrap();
+    let verifier = roots.verifier();
+    let chain = rustls_pemfile::certs(&mut &TEST_LEAF[..])
+        .unwrap()
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect::<Vec<_>>();
+    let name = webpki::DNSNameRef::try_from_ascii_str("testserver.example").unwrap();
+    let verify = || verifier.verify_server_cert(&rustls::RootCertStore::empty(), &chain, name, &[]);
+
+    assert!(verify().is_err());
+
+    fs::write(&bundle, [ONE_CA, TEST_CA].concat()).unwrap();
+
+    for _ in 0..100 {
+        if verify().is_ok() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert!(verify().is_ok());
+}
+
+#[cfg(feature = "rustls")]
+#[test]
+fn starts_from_snapshot_file_when_store_is_empty() {
+    use rustls_native_certs::ReloadableRoots;
+
+    let (_guard, bundle) = scratch_bundle();
+    let snapshot_file = bundle.with_file_name("snapshot");
+    add_another_ca(&bundle);
+
+    let roots = ReloadableRoots::builder()
+        .snapshot_file(&snapshot_file)
+        .build()
+        .unwrap();
+    assert_eq!(roots.current().len(), 2);
+    drop(roots);
+
+    // saving leaves no temporary file beside the snapshot
+    let leftover = fs::read_dir(snapshot_file.parent().unwrap())
+        .unwrap()
+        .any(|entry| entry.unwrap().path().extension() == Some("tmp".as_ref()));
+    assert!(!leftover);
+
+    fs::write(&bundle, b"").unwrap();
+    let roots = ReloadableRoots::builder()
+        .snapshot_file(&snapshot_file)
+        .build()
+        .unwrap();
+    assert_eq!(roots.current().len(), 2);
+
+    // a damaged snapshot file is disregarded
+    let mut saved = fs::read(&snapshot_file).unwrap();
+    let last = saved.len() - 1;
+    saved[last] ^= 1;
+    fs::write(&snapshot_file, saved).unwrap();
+    let roots = ReloadableRoots::builder()
+        .snapshot_file(&snapshot_file)
+        .build()
+        .unwrap();
+    assert_eq!(roots.current().len(), 0);
+}